@@ -0,0 +1,300 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use ignore::Match;
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use crate::i18n::Lang;
+use crate::ipfilter::{parse_net, IpRules};
+use crate::tr;
+
+/// 目录规则文件名，放在任意目录中对该目录及其子目录生效
+pub const RULES_FILE: &str = ".fileserver";
+
+/// 路径访问规则
+///
+/// 判定顺序：以 `.` 开头的文件默认隐藏（`--show-hidden` 关闭），然后依次应用
+/// `--exclude`、从根目录到所在目录逐级的 `.gitignore`（`--gitignore`）和 `.fileserver`，
+/// 最后匹配的规则生效。上级目录被排除时，其中的所有内容都被排除。
+///
+/// `.fileserver` 每行一条规则，`#` 开头为注释：
+///
+/// ```text
+/// # 排除 / 重新允许（gitignore 语法，相对于本目录）
+/// deny *.log
+/// allow keep.log
+/// # 禁止列出本目录及子目录的内容（文件仍可直接下载）
+/// listing off
+/// # 本目录及子目录需要管理令牌，子目录中可用 `auth none` 取消
+/// auth required
+/// # 禁止通过文件管理接口修改本目录及子目录（需开启 --allow-write）
+/// write off
+/// # 按客户端地址限制，替换 --allow / --deny 及上级目录的 IP 规则
+/// allow-ip 192.168.1.0/24 10.0.0.5
+/// deny-ip 192.168.1.100
+/// ```
+pub struct AccessRules {
+    root: PathBuf,
+    /// `--exclude` 指定的全局规则
+    exclude: Gitignore,
+    show_hidden: bool,
+    /// 是否读取目录中的 `.gitignore`
+    gitignore: bool,
+    /// 始终排除的路径（位于根目录下的数据目录、日志文件等）
+    reserved: Vec<String>,
+    /// 目录规则缓存，规则文件修改后自动重新加载
+    cache: Mutex<HashMap<PathBuf, CachedRules>>,
+    lang: Lang,
+}
+
+/// 单个目录中的规则
+#[derive(Default)]
+struct DirRules {
+    /// deny / allow 规则，`.fileserver` 排在 `.gitignore` 之后，优先级更高
+    matcher: Option<Gitignore>,
+    listing: Option<bool>,
+    auth: Option<bool>,
+    write: Option<bool>,
+    ip: Option<IpRules>,
+}
+
+struct CachedRules {
+    /// `.fileserver` 与 `.gitignore` 的修改时间
+    stamp: (Option<SystemTime>, Option<SystemTime>),
+    rules: Arc<DirRules>,
+}
+
+/// 对某个路径的访问判定
+pub struct Access {
+    /// 被排除：不出现在目录列表中，直接访问返回 404
+    pub denied: bool,
+    /// 是否允许列出目录内容
+    pub listing: bool,
+    /// 是否需要管理令牌
+    pub auth_required: bool,
+    /// 是否允许通过文件管理接口修改
+    pub writable: bool,
+    /// 最近一级目录中设置的 IP 规则，未设置时使用全局规则
+    pub ip_rules: Option<IpRules>,
+}
+
+impl AccessRules {
+    pub fn new(
+        root: &Path,
+        exclude: &[String],
+        show_hidden: bool,
+        gitignore: bool,
+        lang: Lang,
+    ) -> Result<Self, String> {
+        let mut builder = GitignoreBuilder::new(root);
+        for pattern in exclude {
+            builder
+                .add_line(None, pattern)
+                .map_err(|e| format!("{}: {}", pattern, e))?;
+        }
+        Ok(AccessRules {
+            root: root.to_path_buf(),
+            exclude: builder.build().map_err(|e| e.to_string())?,
+            show_hidden,
+            gitignore,
+            reserved: Vec::new(),
+            cache: Mutex::new(HashMap::new()),
+            lang,
+        })
+    }
+
+    /// 始终排除 `path`（不在根目录下时忽略）
+    pub fn hide(&mut self, path: &Path) {
+        let (Ok(root), Ok(path)) = (self.root.canonicalize(), path.canonicalize()) else {
+            return;
+        };
+        if let Ok(relative) = path.strip_prefix(&root) {
+            let relative = relative.to_string_lossy().replace('\\', "/");
+            if !relative.is_empty() {
+                self.reserved.push(relative);
+            }
+        }
+    }
+
+    /// `relative` 是否为始终排除的路径或包含这样的路径，这些路径不能被移动或删除
+    pub fn contains_reserved(&self, relative: &str) -> bool {
+        self.reserved
+            .iter()
+            .any(|r| relative.is_empty() || r == relative || r.starts_with(&format!("{}/", relative)))
+    }
+
+    /// 判定相对根目录的路径（不带前导 `/`，根目录为空字符串）
+    pub fn check(&self, relative: &str, is_dir: bool) -> Access {
+        let segments: Vec<&str> = relative.split('/').filter(|s| !s.is_empty()).collect();
+        let mut access = Access {
+            denied: false,
+            listing: true,
+            auth_required: false,
+            writable: true,
+            ip_rules: None,
+        };
+        let mut dirs = Vec::with_capacity(segments.len() + 1);
+        let root_rules = self.dir_rules("");
+        access.apply(&root_rules);
+        dirs.push(root_rules);
+
+        for i in 0..segments.len() {
+            let prefix = segments[..=i].join("/");
+            let prefix_is_dir = is_dir || i + 1 < segments.len();
+            if self.is_denied(&prefix, segments[i], prefix_is_dir, &dirs) {
+                access.denied = true;
+                return access;
+            }
+            if prefix_is_dir {
+                let rules = self.dir_rules(&prefix);
+                access.apply(&rules);
+                dirs.push(rules);
+            }
+        }
+        access
+    }
+
+    /// 单级路径是否被排除，`dirs` 为从根目录到其所在目录的规则
+    fn is_denied(&self, prefix: &str, name: &str, is_dir: bool, dirs: &[Arc<DirRules>]) -> bool {
+        if name == RULES_FILE || self.reserved.iter().any(|r| r == prefix) {
+            return true;
+        }
+        let path = self.root.join(prefix);
+        let mut denied = !self.show_hidden && name.starts_with('.');
+        let matchers = std::iter::once(&self.exclude)
+            .chain(dirs.iter().filter_map(|rules| rules.matcher.as_ref()));
+        for matcher in matchers {
+            match matcher.matched(&path, is_dir) {
+                Match::Ignore(_) => denied = true,
+                Match::Whitelist(_) => denied = false,
+                Match::None => {}
+            }
+        }
+        denied
+    }
+
+    /// 取出目录的规则，规则文件未变化时使用缓存
+    fn dir_rules(&self, relative: &str) -> Arc<DirRules> {
+        let dir = self.root.join(relative);
+        let stamp = (
+            modified(&dir.join(RULES_FILE)),
+            if self.gitignore {
+                modified(&dir.join(".gitignore"))
+            } else {
+                None
+            },
+        );
+        let mut cache = self.cache.lock().unwrap();
+        if stamp == (None, None) {
+            cache.remove(&dir);
+            return Arc::new(DirRules::default());
+        }
+        if let Some(cached) = cache.get(&dir) {
+            if cached.stamp == stamp {
+                return cached.rules.clone();
+            }
+        }
+        let rules = Arc::new(self.load(&dir, stamp));
+        cache.insert(dir, CachedRules { stamp, rules: rules.clone() });
+        rules
+    }
+
+    /// 读取目录中的 `.gitignore` 和 `.fileserver`，无法识别的规则打印警告后跳过
+    fn load(&self, dir: &Path, stamp: (Option<SystemTime>, Option<SystemTime>)) -> DirRules {
+        let mut rules = DirRules::default();
+        let mut builder = GitignoreBuilder::new(dir);
+        if stamp.1.is_some() {
+            if let Some(e) = builder.add(dir.join(".gitignore")) {
+                self.warn(&dir.join(".gitignore").display().to_string(), &e.to_string());
+            }
+        }
+
+        let rules_path = dir.join(RULES_FILE);
+        let content = match stamp.0 {
+            Some(_) => fs::read_to_string(&rules_path).unwrap_or_default(),
+            None => String::new(),
+        };
+        for (number, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (directive, value) = line
+                .split_once(char::is_whitespace)
+                .map(|(d, v)| (d, v.trim()))
+                .unwrap_or((line, ""));
+            let ok = match (directive, value) {
+                ("deny", pattern) if !pattern.is_empty() => {
+                    builder.add_line(Some(rules_path.clone()), pattern).is_ok()
+                }
+                ("allow", pattern) if !pattern.is_empty() => builder
+                    .add_line(Some(rules_path.clone()), &format!("!{}", pattern))
+                    .is_ok(),
+                ("listing", "on" | "off") => {
+                    rules.listing = Some(value == "on");
+                    true
+                }
+                ("auth", "required" | "none") => {
+                    rules.auth = Some(value == "required");
+                    true
+                }
+                ("write", "on" | "off") => {
+                    rules.write = Some(value == "on");
+                    true
+                }
+                ("allow-ip" | "deny-ip", nets) if !nets.is_empty() => {
+                    let ip = rules.ip.get_or_insert_with(IpRules::default);
+                    let list = if directive == "allow-ip" { &mut ip.allow } else { &mut ip.deny };
+                    nets.split(|c: char| c == ',' || c.is_whitespace())
+                        .filter(|net| !net.is_empty())
+                        .map(|net| parse_net(net).map(|net| list.push(net)))
+                        .all(|result| result.is_ok())
+                }
+                _ => false,
+            };
+            if !ok {
+                self.warn(&format!("{}:{}", rules_path.display(), number + 1), line);
+            }
+        }
+
+        match builder.build() {
+            Ok(matcher) if !matcher.is_empty() => rules.matcher = Some(matcher),
+            Ok(_) => {}
+            Err(e) => self.warn(&dir.display().to_string(), &e.to_string()),
+        }
+        rules
+    }
+
+    fn warn(&self, location: &str, rule: &str) {
+        eprintln!(
+            "⚠️ \x1B[93m{}\x1B[0m",
+            tr!(self.lang, "access.bad_rule", location = location, rule = rule)
+        );
+    }
+}
+
+impl Access {
+    /// 应用目录的 listing / auth / write / IP 设置，子目录中的设置覆盖上级目录
+    fn apply(&mut self, rules: &DirRules) {
+        if let Some(ip) = &rules.ip {
+            self.ip_rules = Some(ip.clone());
+        }
+        if let Some(listing) = rules.listing {
+            self.listing = listing;
+        }
+        if let Some(auth) = rules.auth {
+            self.auth_required = auth;
+        }
+        if let Some(write) = rules.write {
+            self.writable = write;
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
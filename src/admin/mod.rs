@@ -0,0 +1,242 @@
+use hyper::{
+    header,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, StatusCode,
+};
+use std::{
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::auth;
+use crate::i18n::{self, Lang};
+use crate::listener::{self, Conn, ConnectionCaps, ConnectionLimits, Listener};
+use crate::metrics;
+use crate::tr;
+use crate::util::{read_body, text_response};
+use crate::AppState;
+
+/// 管理页面路径
+pub const ADMIN_PATH: &str = "/_admin";
+
+/// 管理操作请求体的大小上限
+const MAX_ACTION_BODY: usize = 1024;
+
+/// 日志推送的保活间隔，避免空闲超时断开连接
+const KEEP_ALIVE: Duration = Duration::from_secs(20);
+
+/// 页面脚本使用的消息
+const PAGE_STRINGS: &[&str] = &[
+    "uptime",
+    "connections",
+    "transfers",
+    "banned",
+    "log",
+    "none",
+    "download",
+    "upload",
+    "cancel",
+    "ban",
+    "unban",
+    "confirm_cancel",
+    "confirm_ban",
+    "col_direction",
+    "col_path",
+    "col_client",
+    "col_progress",
+    "col_speed",
+    "col_elapsed",
+    "col_remote",
+    "col_transport",
+    "col_duration",
+    "col_requests",
+    "col_ip",
+];
+
+/// 是否为管理页面或其接口的路径
+pub fn is_admin_path(path: &str) -> bool {
+    path == ADMIN_PATH || path.starts_with("/_admin/")
+}
+
+/// 处理 `/_admin` 下的请求，需要管理权限（默认仅本机）
+///
+/// - `GET /_admin`：管理页面
+/// - `GET /_admin/state`：连接、传输和封禁列表（JSON）
+/// - `GET /_admin/log`：实时日志（Server-Sent Events）
+/// - `POST /_admin/cancel`（`id`）、`/_admin/ban`、`/_admin/unban`（`ip`）：管理操作
+pub async fn handle(req: Request<Body>, remote: SocketAddr, state: &AppState) -> Response<Body> {
+    let lang = Lang::for_request(&req, state.lang);
+    if !state.auth.is_authorized(&req, remote) {
+        return auth::unauthorized(lang);
+    }
+    let path = req.uri().path().trim_end_matches('/').to_string();
+    match (req.method().clone(), path.as_str()) {
+        (Method::GET, ADMIN_PATH) => {
+            let mut response = page(lang);
+            if let Some(cookie) = state.auth.cookie_for(&req) {
+                response
+                    .headers_mut()
+                    .insert(header::SET_COOKIE, cookie.parse().unwrap());
+            }
+            response
+        }
+        (Method::GET, "/_admin/state") => {
+            let mut value = serde_json::to_value(state.monitor.snapshot()).unwrap();
+            value["uptime_seconds"] = state.stats.uptime().as_secs().into();
+            Response::builder()
+                .header("Content-Type", "application/json")
+                .header("Cache-Control", "no-store")
+                .body(Body::from(value.to_string()))
+                .unwrap()
+        }
+        (Method::GET, "/_admin/log") => live_log(state),
+        (Method::POST, action @ ("/_admin/cancel" | "/_admin/ban" | "/_admin/unban")) => {
+            if let Some(response) = auth::reject_cross_site(&req, lang) {
+                return response;
+            }
+            let action = action.to_string();
+            let Some(body) = read_body(req, MAX_ACTION_BODY).await else {
+                return text_response(StatusCode::PAYLOAD_TOO_LARGE, lang.t("http.body_too_large"));
+            };
+            let field = |name: &str| {
+                form_urlencoded::parse(&body)
+                    .find(|(k, _)| k == name)
+                    .map(|(_, v)| v.into_owned())
+                    .unwrap_or_default()
+            };
+            perform(&action, &field("id"), &field("ip"), state, lang)
+        }
+        _ => text_response(StatusCode::NOT_FOUND, lang.t("http.not_found")),
+    }
+}
+
+/// 执行取消、封禁或解除封禁
+fn perform(action: &str, id: &str, ip: &str, state: &AppState, lang: Lang) -> Response<Body> {
+    if action == "/_admin/cancel" {
+        return match id.parse() {
+            Ok(id) if state.monitor.cancel(id) => ok(),
+            _ => text_response(StatusCode::NOT_FOUND, lang.t("admin.no_transfer")),
+        };
+    }
+    let Ok(ip) = ip.trim().parse::<IpAddr>() else {
+        return text_response(StatusCode::BAD_REQUEST, &tr!(lang, "admin.bad_ip", ip = ip));
+    };
+    if action == "/_admin/unban" {
+        state.monitor.unban(ip);
+        return ok();
+    }
+    // 封禁本机地址会把管理页面自己也挡在外面
+    if ip.to_canonical().is_loopback() || ip.is_unspecified() {
+        return text_response(StatusCode::BAD_REQUEST, lang.t("admin.ban_local"));
+    }
+    state.monitor.ban(ip);
+    println!("🚫 {}", tr!(state.lang, "admin.banned", ip = ip));
+    ok()
+}
+
+fn ok() -> Response<Body> {
+    Response::builder()
+        .header("Content-Type", "application/json")
+        .body(Body::from(r#"{"ok":true}"#))
+        .unwrap()
+}
+
+/// 管理页面
+fn page(lang: Lang) -> Response<Body> {
+    let strings: serde_json::Map<String, serde_json::Value> = PAGE_STRINGS
+        .iter()
+        .map(|key| (key.to_string(), lang.t(&format!("admin.{}", key)).into()))
+        .collect();
+    let html = i18n::format(
+        include_str!("page.html"),
+        &[
+            ("lang", &lang.code()),
+            ("title", &lang.t("admin.title")),
+            ("home", &lang.t("listing.home")),
+            ("strings", &serde_json::Value::Object(strings)),
+        ],
+    );
+    Response::builder()
+        .header("Content-Type", "text/html; charset=utf-8")
+        .header("Cache-Control", "no-store")
+        .body(Body::from(html))
+        .unwrap()
+}
+
+/// 以 Server-Sent Events 推送最近的日志和之后的新日志，服务退出时结束
+fn live_log(state: &AppState) -> Response<Body> {
+    let (recent, mut live) = state.logger.subscribe();
+    let closing = state.monitor.closing();
+    let (mut tx, body) = Body::channel();
+    tokio::spawn(async move {
+        for line in recent {
+            if tx.send_data(event(&line)).await.is_err() {
+                return;
+            }
+        }
+        let mut keep_alive = tokio::time::interval(KEEP_ALIVE);
+        keep_alive.tick().await;
+        loop {
+            let chunk = tokio::select! {
+                _ = closing.cancelled() => return,
+                _ = keep_alive.tick() => ": keep-alive\n\n".into(),
+                line = live.recv() => match line {
+                    Ok(line) => event(&line),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return,
+                },
+            };
+            if tx.send_data(chunk).await.is_err() {
+                return;
+            }
+        }
+    });
+    Response::builder()
+        .header("Content-Type", "text/event-stream")
+        .header("Cache-Control", "no-store")
+        .body(body)
+        .unwrap()
+}
+
+fn event(line: &str) -> hyper::body::Bytes {
+    format!("data: {}\n\n", line.replace('\n', " ")).into()
+}
+
+/// 在独立的管理监听器（`--admin-bind`）上提供健康检查、指标和管理页面，其他路径返回 404
+///
+/// 管理监听器上的请求不计入统计，也不出现在连接列表中。
+pub async fn serve_listener(
+    listeners: Vec<Listener>,
+    state: Arc<AppState>,
+    limits: &ConnectionLimits,
+    lang: Lang,
+) {
+    let make_service = make_service_fn(move |conn: &Conn| {
+        let remote = conn.remote_addr();
+        let state = state.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                let state = state.clone();
+                async move {
+                    if let Some(response) = metrics::handle(&req, &state.stats, state.metrics_top) {
+                        return Ok::<_, Infallible>(response);
+                    }
+                    if is_admin_path(req.uri().path()) {
+                        return Ok(handle(req, remote, &state).await);
+                    }
+                    let lang = Lang::for_request(&req, state.lang);
+                    Ok(text_response(StatusCode::NOT_FOUND, lang.t("http.not_found")))
+                }
+            }))
+        }
+    });
+    if let Err(e) = listener::server(listeners, limits, ConnectionCaps::new(limits), None, lang)
+        .serve(make_service)
+        .await
+    {
+        eprintln!("❌ \x1B[91m{}\x1B[0m", tr!(lang, "common.server_error", error = e));
+    }
+}
@@ -0,0 +1,150 @@
+use hyper::{header, Body, Request, Response, StatusCode};
+use std::net::SocketAddr;
+
+use crate::i18n::Lang;
+use crate::util::query_param;
+
+/// 管理令牌对应的 Cookie 名
+const TOKEN_COOKIE: &str = "fs_token";
+
+/// 管理操作（创建分享链接等）的鉴权配置
+///
+/// 未设置 `--admin-token` 时仅允许本机访问管理操作；
+/// 设置后需通过 `Authorization: Bearer`、Cookie 或 `?token=` 提供令牌。
+pub struct AuthConfig {
+    token: Option<String>,
+}
+
+impl AuthConfig {
+    pub fn new(token: Option<String>) -> Self {
+        AuthConfig { token }
+    }
+
+    /// 判断请求是否有权执行管理操作
+    pub fn is_authorized(&self, req: &Request<Body>, remote: SocketAddr) -> bool {
+        match &self.token {
+            None => remote.ip().is_loopback(),
+            Some(expected) => request_token(req)
+                .map(|t| constant_time_eq(t.as_bytes(), expected.as_bytes()))
+                .unwrap_or(false),
+        }
+    }
+
+    /// 若令牌通过查询参数提供，返回需要写入的 Set-Cookie，后续页面内请求无需再带令牌
+    pub fn cookie_for(&self, req: &Request<Body>) -> Option<String> {
+        self.token.as_ref()?;
+        let token = query_param(req.uri().query(), "token")?;
+        Some(format!(
+            "{}={}; Path=/; HttpOnly; SameSite=Strict",
+            TOKEN_COOKIE, token
+        ))
+    }
+}
+
+/// 从请求中取出管理令牌
+fn request_token(req: &Request<Body>) -> Option<String> {
+    if let Some(value) = req.headers().get(header::AUTHORIZATION) {
+        if let Some(token) = value.to_str().ok()?.strip_prefix("Bearer ") {
+            return Some(token.trim().to_string());
+        }
+    }
+    if let Some(cookies) = req.headers().get(header::COOKIE) {
+        for pair in cookies.to_str().unwrap_or("").split(';') {
+            if let Some((name, value)) = pair.trim().split_once('=') {
+                if name == TOKEN_COOKIE {
+                    return Some(value.to_string());
+                }
+            }
+        }
+    }
+    query_param(req.uri().query(), "token")
+}
+
+/// 常量时间比较，避免通过响应时间猜测令牌
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// 401 响应
+pub fn unauthorized(lang: Lang) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::UNAUTHORIZED)
+        .header("Content-Type", "text/plain; charset=utf-8")
+        .header(header::WWW_AUTHENTICATE, "Bearer")
+        .body(Body::from(lang.t("http.unauthorized").to_string()))
+        .unwrap()
+}
+
+/// 拒绝浏览器发起的跨站请求（CSRF）
+///
+/// 未设置令牌时本机浏览器天然通过鉴权，任意网页都能借它向本服务发 POST。
+/// 优先看 `Sec-Fetch-Site`，旧浏览器退而比较 `Origin` 与 `Host`；
+/// 两者都没有的多为命令行等非浏览器客户端，放行。
+pub fn reject_cross_site(req: &Request<Body>, lang: Lang) -> Option<Response<Body>> {
+    if is_same_site(req) {
+        return None;
+    }
+    Some(
+        Response::builder()
+            .status(StatusCode::FORBIDDEN)
+            .header("Content-Type", "text/plain; charset=utf-8")
+            .body(Body::from(lang.t("http.cross_site").to_string()))
+            .unwrap(),
+    )
+}
+
+fn is_same_site(req: &Request<Body>) -> bool {
+    let headers = req.headers();
+    if let Some(site) = headers.get("sec-fetch-site") {
+        return matches!(site.to_str(), Ok("same-origin" | "none"));
+    }
+    let Some(origin) = headers.get(header::ORIGIN) else {
+        return true;
+    };
+    let origin = origin.to_str().unwrap_or("");
+    let Some(origin_host) = origin
+        .strip_prefix("https://")
+        .or_else(|| origin.strip_prefix("http://"))
+    else {
+        // 包括沙箱页面等发出的 `Origin: null`
+        return false;
+    };
+    let host = headers
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| req.uri().authority().map(|a| a.to_string()));
+    host.is_some_and(|host| host.eq_ignore_ascii_case(origin_host))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(headers: &[(&str, &str)]) -> Request<Body> {
+        let mut builder = Request::post("/_api/fs/delete").header("Host", "127.0.0.1:8080");
+        for (name, value) in headers {
+            builder = builder.header(*name, *value);
+        }
+        builder.body(Body::empty()).unwrap()
+    }
+
+    #[test]
+    fn same_site_requests_pass() {
+        assert!(is_same_site(&request(&[])));
+        assert!(is_same_site(&request(&[("Sec-Fetch-Site", "same-origin")])));
+        assert!(is_same_site(&request(&[("Origin", "http://127.0.0.1:8080")])));
+    }
+
+    #[test]
+    fn cross_site_requests_are_refused() {
+        assert!(!is_same_site(&request(&[("Sec-Fetch-Site", "cross-site")])));
+        assert!(!is_same_site(&request(&[("Sec-Fetch-Site", "same-site")])));
+        assert!(!is_same_site(&request(&[("Origin", "https://evil.example")])));
+        assert!(!is_same_site(&request(&[("Origin", "null")])));
+        assert!(reject_cross_site(&request(&[("Origin", "http://localhost:8080")]), Lang::En).is_some());
+    }
+}
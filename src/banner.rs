@@ -0,0 +1,108 @@
+use std::net::{IpAddr, SocketAddr};
+
+use crate::listener::Listener;
+use crate::qr;
+
+/// 当前可执行文件名，用于帮助提示（Windows 下为 file_server.exe，其他平台为 file_server）
+pub fn program_name() -> String {
+    std::env::current_exe()
+        .ok()
+        .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+        .unwrap_or_else(|| env!("CARGO_PKG_NAME").to_string())
+}
+
+fn format_url(scheme: &str, ip: IpAddr, port: u16, path: &str) -> String {
+    match ip {
+        IpAddr::V4(ip) => format!("{}://{}:{}{}", scheme, ip, port, path),
+        IpAddr::V6(ip) => format!("{}://[{}]:{}{}", scheme, ip, port, path),
+    }
+}
+
+/// 局域网中更可能被访问到的地址排在前面：私有 IPv4、其他 IPv4、IPv6
+fn address_rank(ip: &IpAddr) -> u8 {
+    match ip {
+        IpAddr::V4(v4) if v4.is_private() => 0,
+        IpAddr::V4(_) => 1,
+        IpAddr::V6(_) => 2,
+    }
+}
+
+/// 监听地址对应的可访问 IP，第一个为主地址
+///
+/// 监听在 0.0.0.0 / :: 上时枚举本机所有非回环网卡地址（IPv6 链路本地地址需要带网卡编号，
+/// 浏览器基本不支持，因此跳过），最后附上回环地址；监听在具体地址上时只返回该地址。
+pub fn reachable_ips(addr: SocketAddr) -> Vec<IpAddr> {
+    let ip = addr.ip();
+    if !ip.is_unspecified() {
+        return vec![ip];
+    }
+
+    let mut ips: Vec<IpAddr> = if_addrs::get_if_addrs()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|iface| iface.oper_status != if_addrs::IfOperStatus::Down)
+        .map(|iface| iface.ip())
+        .filter(|ip| !ip.is_loopback())
+        .filter(|ip| match ip {
+            IpAddr::V4(_) => true,
+            // IPv4 通配地址只接受 IPv4 连接
+            IpAddr::V6(v6) => addr.is_ipv6() && (v6.segments()[0] & 0xffc0) != 0xfe80,
+        })
+        .collect();
+    ips.sort_by_key(address_rank);
+    ips.dedup();
+
+    let loopback: IpAddr = if addr.is_ipv6() {
+        std::net::Ipv6Addr::LOCALHOST.into()
+    } else {
+        std::net::Ipv4Addr::LOCALHOST.into()
+    };
+    ips.push(loopback);
+    ips
+}
+
+/// 所有监听器对应的访问地址（去重），Unix 套接字显示为 `unix:<路径>`
+pub fn listener_urls(listeners: &[Listener], scheme: &str, path: &str) -> Vec<String> {
+    let mut urls: Vec<String> = Vec::new();
+    for listener in listeners {
+        let candidates = match listener.tcp_addr() {
+            Some(addr) => reachable_ips(addr)
+                .into_iter()
+                .map(|ip| format_url(scheme, ip, addr.port(), path))
+                .collect(),
+            None => vec![listener.to_string()],
+        };
+        for url in candidates {
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+    }
+    urls
+}
+
+/// 打印访问地址，主地址之外的地址逐行列在下方
+pub fn print_urls(label: &str, urls: &[String]) {
+    for (i, url) in urls.iter().enumerate() {
+        if i == 0 {
+            println!("🌐 {}: {}", label, url);
+        } else {
+            println!("   ↳ {}", url);
+        }
+    }
+}
+
+/// 为主地址打印二维码；主地址是回环地址时手机无法访问，不打印
+pub fn print_primary_qr(urls: &[String]) {
+    if let Some(primary) = urls.first() {
+        let Some(host) = primary
+            .strip_prefix("http://")
+            .or_else(|| primary.strip_prefix("https://"))
+        else {
+            return;
+        };
+        if !host.starts_with("127.") && !host.starts_with("[::1]") {
+            qr::print_qr(primary);
+        }
+    }
+}
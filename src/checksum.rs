@@ -0,0 +1,420 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use hyper::{header, Body, Request, Response, StatusCode};
+use md5::Md5;
+use percent_encoding::utf8_percent_encode;
+use sha1::Sha1;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, Read},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+use tokio::sync::Semaphore;
+use walkdir::WalkDir;
+
+use crate::i18n::Lang;
+use crate::tr;
+use crate::util::{query_param, text_response, SEGMENT};
+use crate::AppState;
+
+/// 缓存的摘要数量上限，超出时丢弃任意一项
+const MAX_CACHED: usize = 4096;
+/// 同时计算摘要的文件数，避免大量请求同时读盘
+const MAX_HASHING: usize = 2;
+
+/// 支持的摘要算法
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Algorithm {
+    Sha256,
+    Sha1,
+    Md5,
+    Blake3,
+}
+
+impl Algorithm {
+    pub const ALL: [Algorithm; 4] = [
+        Algorithm::Sha256,
+        Algorithm::Sha1,
+        Algorithm::Md5,
+        Algorithm::Blake3,
+    ];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|algorithm| algorithm.name().eq_ignore_ascii_case(name))
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::Sha256 => "sha256",
+            Algorithm::Sha1 => "sha1",
+            Algorithm::Md5 => "md5",
+            Algorithm::Blake3 => "blake3",
+        }
+    }
+
+    /// 对应的 `*sum` 工具默认使用的清单文件名
+    fn manifest_name(self) -> &'static str {
+        match self {
+            Algorithm::Sha256 => "SHA256SUMS",
+            Algorithm::Sha1 => "SHA1SUMS",
+            Algorithm::Md5 => "MD5SUMS",
+            Algorithm::Blake3 => "B3SUMS",
+        }
+    }
+
+    fn supported() -> String {
+        Self::ALL.map(Algorithm::name).join(", ")
+    }
+}
+
+/// 按算法计算摘要
+pub enum Hasher {
+    Sha256(Sha256),
+    Sha1(Sha1),
+    Md5(Md5),
+    Blake3(Box<blake3::Hasher>),
+}
+
+impl Hasher {
+    pub fn new(algorithm: Algorithm) -> Self {
+        match algorithm {
+            Algorithm::Sha256 => Hasher::Sha256(Sha256::new()),
+            Algorithm::Sha1 => Hasher::Sha1(Sha1::new()),
+            Algorithm::Md5 => Hasher::Md5(Md5::new()),
+            Algorithm::Blake3 => Hasher::Blake3(Box::default()),
+        }
+    }
+
+    pub fn update(&mut self, data: &[u8]) {
+        match self {
+            Hasher::Sha256(h) => h.update(data),
+            Hasher::Sha1(h) => h.update(data),
+            Hasher::Md5(h) => h.update(data),
+            Hasher::Blake3(h) => {
+                h.update(data);
+            }
+        }
+    }
+
+    pub fn finalize(self) -> Vec<u8> {
+        match self {
+            Hasher::Sha256(h) => h.finalize().to_vec(),
+            Hasher::Sha1(h) => h.finalize().to_vec(),
+            Hasher::Md5(h) => h.finalize().to_vec(),
+            Hasher::Blake3(h) => h.finalize().as_bytes().to_vec(),
+        }
+    }
+}
+
+/// 读取整个文件计算摘要
+fn hash_file(path: &Path, algorithm: Algorithm) -> io::Result<Vec<u8>> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = Hasher::new(algorithm);
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hasher.finalize())
+}
+
+struct Cached {
+    modified: Option<SystemTime>,
+    size: u64,
+    digest: Vec<u8>,
+}
+
+/// 文件摘要缓存，按路径和算法保存，文件的修改时间或大小变化后重新计算
+pub struct HashCache {
+    entries: Mutex<HashMap<(PathBuf, Algorithm), Cached>>,
+    permits: Semaphore,
+}
+
+impl Default for HashCache {
+    fn default() -> Self {
+        HashCache {
+            entries: Mutex::new(HashMap::new()),
+            permits: Semaphore::new(MAX_HASHING),
+        }
+    }
+}
+
+impl HashCache {
+    /// 文件的摘要，路径、修改时间和大小都未变时使用缓存
+    pub async fn digest(&self, path: &Path, algorithm: Algorithm) -> io::Result<Vec<u8>> {
+        // 以计算前的状态为准，计算期间文件被修改时下次请求会重新计算
+        let metadata = tokio::fs::metadata(path).await?;
+        let modified = metadata.modified().ok();
+        let key = (path.to_path_buf(), algorithm);
+        let cached = |entries: &HashMap<_, Cached>| {
+            entries
+                .get(&key)
+                .filter(|c| c.modified == modified && c.size == metadata.len())
+                .map(|c| c.digest.clone())
+        };
+        if let Some(digest) = cached(&self.entries.lock().unwrap()) {
+            return Ok(digest);
+        }
+        let _permit = self.permits.acquire().await.unwrap();
+        // 等待期间可能已由其他请求算好
+        if let Some(digest) = cached(&self.entries.lock().unwrap()) {
+            return Ok(digest);
+        }
+        let source = path.to_path_buf();
+        let digest = tokio::task::spawn_blocking(move || hash_file(&source, algorithm))
+            .await
+            .map_err(io::Error::other)??;
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= MAX_CACHED && !entries.contains_key(&key) {
+            if let Some(evicted) = entries.keys().next().cloned() {
+                entries.remove(&evicted);
+            }
+        }
+        entries.insert(
+            key,
+            Cached {
+                modified,
+                size: metadata.len(),
+                digest: digest.clone(),
+            },
+        );
+        Ok(digest)
+    }
+}
+
+/// 文件下载响应的 `Repr-Digest`（RFC 9530）和 `Digest`（RFC 3230）头，摘要无法计算时为空
+pub async fn digest_headers(state: &AppState, path: &Path) -> Vec<(&'static str, String)> {
+    match state.hashes.digest(path, Algorithm::Sha256).await {
+        Ok(digest) => {
+            let encoded = STANDARD.encode(digest);
+            vec![
+                ("Repr-Digest", format!("sha-256=:{}:", encoded)),
+                ("Digest", format!("SHA-256={}", encoded)),
+            ]
+        }
+        Err(_) => Vec::new(),
+    }
+}
+
+/// 处理文件上的 `?hash=<算法>` 和目录上的 `?manifest=<算法>`，没有这两个参数时返回 None
+///
+/// 两者的输出格式与 `sha256sum` 等工具相同，可直接用 `sha256sum -c` 校验。访问规则与路径本身相同，
+/// 由调用方检查；清单中跳过客户端无权访问的文件。
+pub async fn handle_query(
+    req: &Request<Body>,
+    remote: SocketAddr,
+    state: &Arc<AppState>,
+    relative: &str,
+    is_dir: bool,
+    lang: Lang,
+) -> Option<Response<Body>> {
+    let query = req.uri().query();
+    let (name, manifest) = match (query_param(query, "hash"), query_param(query, "manifest")) {
+        (Some(name), _) => (name, false),
+        (None, Some(name)) => (name, true),
+        (None, None) => return None,
+    };
+    let Some(algorithm) = Algorithm::parse(&name) else {
+        return Some(text_response(
+            StatusCode::BAD_REQUEST,
+            &tr!(
+                lang,
+                "checksum.unsupported",
+                name = name,
+                supported = Algorithm::supported()
+            ),
+        ));
+    };
+    let full_path = state.root_dir.join(relative);
+    let response = match (manifest, is_dir) {
+        (_, false) if !full_path.is_file() => {
+            text_response(StatusCode::NOT_FOUND, lang.t("http.not_found"))
+        }
+        (false, false) => match state.hashes.digest(&full_path, algorithm).await {
+            Ok(digest) => {
+                let name = relative.rsplit('/').next().unwrap_or(relative);
+                Response::builder()
+                    .header("Content-Type", "text/plain; charset=utf-8")
+                    .header(header::CACHE_CONTROL, "no-cache")
+                    .body(Body::from(sum_line(&digest, name)))
+                    .unwrap()
+            }
+            Err(e) => text_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &tr!(lang, "checksum.failed", error = e),
+            ),
+        },
+        (false, true) => text_response(StatusCode::BAD_REQUEST, lang.t("checksum.not_a_file")),
+        (true, true) => {
+            let authorized = state.auth.is_authorized(req, remote);
+            manifest_response(state.clone(), relative, algorithm, remote, authorized)
+        }
+        (true, false) => text_response(StatusCode::BAD_REQUEST, lang.t("checksum.not_a_dir")),
+    };
+    Some(response)
+}
+
+/// `sha256sum` 格式的一行；文件名含反斜杠或换行时按 GNU coreutils 的方式转义并在行首加 `\`
+fn sum_line(digest: &[u8], name: &str) -> String {
+    if name.contains(['\\', '\n', '\r']) {
+        let escaped = name
+            .replace('\\', "\\\\")
+            .replace('\n', "\\n")
+            .replace('\r', "\\r");
+        format!("\\{}  {}\n", hex::encode(digest), escaped)
+    } else {
+        format!("{}  {}\n", hex::encode(digest), name)
+    }
+}
+
+/// 逐个计算目录下（递归）文件的摘要，算好一行发送一行
+fn manifest_response(
+    state: Arc<AppState>,
+    relative: &str,
+    algorithm: Algorithm,
+    remote: SocketAddr,
+    authorized: bool,
+) -> Response<Body> {
+    let base = relative.trim_matches('/').to_string();
+    let dir_name = base.rsplit('/').next().filter(|name| !name.is_empty());
+    let file_name = match dir_name {
+        Some(name) => format!("{}.{}", name, algorithm.manifest_name()),
+        None => algorithm.manifest_name().to_string(),
+    };
+    let (mut tx, body) = Body::channel();
+    tokio::spawn(async move {
+        let walker = {
+            let state = state.clone();
+            let base = base.clone();
+            tokio::task::spawn_blocking(move || visible_files(&state, &base, remote, authorized))
+        };
+        let Ok(files) = walker.await else {
+            return;
+        };
+        for path in files {
+            let full_path = state.root_dir.join(&base).join(&path);
+            // 读取失败（例如权限不足或已被删除）的文件不列入清单
+            let Ok(digest) = state.hashes.digest(&full_path, algorithm).await else {
+                continue;
+            };
+            if tx.send_data(sum_line(&digest, &path).into()).await.is_err() {
+                return;
+            }
+        }
+    });
+    Response::builder()
+        .header("Content-Type", "text/plain; charset=utf-8")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!(
+                "inline; filename*=UTF-8''{}",
+                utf8_percent_encode(&file_name, SEGMENT)
+            ),
+        )
+        .header(header::CACHE_CONTROL, "no-cache")
+        .body(body)
+        .unwrap()
+}
+
+/// 目录 `base` 下客户端可以访问的文件，路径相对于 `base`，按名称排序
+///
+/// 跳过被排除、需要授权而客户端未授权、IP 规则不允许的路径，以及禁止列出的目录。
+fn visible_files(
+    state: &AppState,
+    base: &str,
+    remote: SocketAddr,
+    authorized: bool,
+) -> Vec<String> {
+    let root = state.root_dir.join(base);
+    let visible = |relative: &str, is_dir: bool| {
+        let access = state.access.check(relative, is_dir);
+        !access.denied
+            && (!access.auth_required || authorized)
+            && (!is_dir || access.listing)
+            && access
+                .ip_rules
+                .as_ref()
+                .is_none_or(|rules| rules.check(remote.ip()).is_ok())
+    };
+    let relative_to_root = |path: &Path| {
+        let inner = slash_path(path.strip_prefix(&root).unwrap_or(path));
+        if base.is_empty() {
+            inner
+        } else {
+            format!("{}/{}", base, inner)
+        }
+    };
+    WalkDir::new(&root)
+        .min_depth(1)
+        .follow_links(true)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| visible(&relative_to_root(entry.path()), entry.file_type().is_dir()))
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| entry.path().strip_prefix(&root).ok().map(slash_path))
+        .collect()
+}
+
+/// 以 `/` 连接的相对路径（Windows 上不改变文件名中的其他字符）
+fn slash_path(path: &Path) -> String {
+    path.components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TestDir;
+
+    #[test]
+    fn sum_lines_escape_like_coreutils() {
+        let digest = [0xab, 0xcd];
+        assert_eq!(sum_line(&digest, "a b.txt"), "abcd  a b.txt\n");
+        assert_eq!(sum_line(&digest, "dir/a\\b"), "\\abcd  dir/a\\\\b\n");
+        assert_eq!(sum_line(&digest, "a\nb\r"), "\\abcd  a\\nb\\r\n");
+    }
+
+    #[test]
+    fn algorithms_match_known_digests() {
+        assert_eq!(Algorithm::parse("SHA256"), Some(Algorithm::Sha256));
+        assert_eq!(Algorithm::parse("crc32"), None);
+        let expected = [
+            (Algorithm::Sha256, "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"),
+            (Algorithm::Sha1, "a9993e364706816aba3e25717850c26c9cd0d89d"),
+            (Algorithm::Md5, "900150983cd24fb0d6963f7d28e17f72"),
+            (Algorithm::Blake3, "6437b3ac38465133ffb63b75273a8db548c558465d79db03fd359c6cd5bd9d85"),
+        ];
+        for (algorithm, hex) in expected {
+            let mut hasher = Hasher::new(algorithm);
+            hasher.update(b"a");
+            hasher.update(b"bc");
+            assert_eq!(hex::encode(hasher.finalize()), hex, "{}", algorithm.name());
+        }
+    }
+
+    #[tokio::test]
+    async fn cached_digests_follow_file_changes() {
+        let dir = TestDir::new("checksum_cache");
+        let path = dir.join("a.txt");
+        let cache = HashCache::default();
+        fs::write(&path, "abc").unwrap();
+        let first = cache.digest(&path, Algorithm::Md5).await.unwrap();
+        assert_eq!(hex::encode(&first), "900150983cd24fb0d6963f7d28e17f72");
+
+        fs::write(&path, "abcd").unwrap();
+        let second = cache.digest(&path, Algorithm::Md5).await.unwrap();
+        assert_eq!(hex::encode(&second), "e2fc714c4727ee9395f324cd2e7f331f");
+    }
+}
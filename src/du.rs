@@ -0,0 +1,397 @@
+use hyper::{Body, Response, StatusCode};
+use serde::Serialize;
+use std::{
+    collections::HashMap,
+    fs, io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::SystemTime,
+};
+use tokio::sync::Semaphore;
+
+use crate::i18n::Lang;
+use crate::listing::{href_for, ListingRenderer};
+use crate::util::{format_size, text_response};
+use crate::AppState;
+
+/// 缓存的目录数上限，超出时清空重新统计
+const MAX_CACHED_DIRS: usize = 100_000;
+/// 同时遍历的子目录数（所有请求共享）
+const MAX_WALKERS: usize = 4;
+
+/// 根目录所在文件系统的空间
+#[derive(Debug, Clone, Serialize)]
+pub struct DiskSpace {
+    pub total: u64,
+    pub free: u64,
+    pub total_display: String,
+    pub free_display: String,
+}
+
+/// 目录占用中的一项
+#[derive(Debug, Serialize)]
+pub struct UsageEntry {
+    pub name: String,
+    /// 相对根目录的路径（未编码，不带前导 `/`）
+    pub path: String,
+    /// 链接地址（已按路径段编码），目录的链接不带 `?du`
+    pub href: String,
+    pub is_dir: bool,
+    /// 大小（字节），目录为其中所有文件的总大小
+    pub size: u64,
+    pub size_display: String,
+    /// 目录中（递归）的文件数和子目录数，文件为 1 和 0
+    pub files: u64,
+    pub dirs: u64,
+}
+
+/// 待渲染的目录占用（`?du`）
+#[derive(Debug, Serialize)]
+pub struct DiskUsage {
+    /// 当前路径，以 `/` 开头
+    pub path: String,
+    /// 相对根目录的路径（不带前导 `/`），根目录为空字符串
+    pub relative_path: String,
+    /// 当前目录的链接地址（不带 `?du`）
+    pub href: String,
+    /// 上级目录的链接地址，根目录为 None
+    pub parent: Option<String>,
+    /// 按大小从大到小排列
+    pub entries: Vec<UsageEntry>,
+    pub size: u64,
+    pub size_display: String,
+    pub files: u64,
+    pub dirs: u64,
+    /// 无法读取的目录数，大于 0 时统计结果偏小
+    pub errors: u64,
+    /// 客户端无权查看、未计入统计的目录数（需要授权、禁止列出或 IP 规则不允许）
+    pub hidden: u64,
+    pub disk: Option<DiskSpace>,
+    /// 根目录在磁盘上的位置（不输出给 JSON / 模板）
+    #[serde(skip)]
+    pub root: PathBuf,
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+struct Totals {
+    size: u64,
+    files: u64,
+    dirs: u64,
+    errors: u64,
+    hidden: u64,
+}
+
+/// 查看统计结果的客户端，跳过其无权查看的子目录
+#[derive(Clone, Copy)]
+struct Viewer {
+    remote: SocketAddr,
+    authorized: bool,
+}
+
+impl Viewer {
+    /// 与 `?sha256sums` 清单相同：需要授权而未授权、禁止列出或 IP 规则不允许的目录不计入
+    fn can_see(&self, state: &AppState, relative: &str) -> bool {
+        let access = state.access.check(relative, true);
+        (!access.auth_required || self.authorized)
+            && access.listing
+            && access
+                .ip_rules
+                .as_ref()
+                .is_none_or(|rules| rules.check(self.remote.ip()).is_ok())
+    }
+}
+
+/// 目录本身（不递归）的内容
+struct DirContents {
+    modified: SystemTime,
+    size: u64,
+    files: u64,
+    subdirs: Vec<String>,
+}
+
+/// 目录占用缓存：按目录保存其直接包含的文件大小和子目录，目录的修改时间变化后重新读取
+///
+/// 目录中增删、重命名文件会改变目录的修改时间；文件被原地改写时目录的修改时间不变，
+/// 其大小要等目录内容变化后才会更新。
+pub struct UsageCache {
+    dirs: Mutex<HashMap<String, Arc<DirContents>>>,
+    permits: Arc<Semaphore>,
+}
+
+impl Default for UsageCache {
+    fn default() -> Self {
+        UsageCache {
+            dirs: Mutex::new(HashMap::new()),
+            permits: Arc::new(Semaphore::new(MAX_WALKERS)),
+        }
+    }
+}
+
+impl UsageCache {
+    /// 目录 `relative` 本身的内容，跳过被访问规则排除的项，不跟随符号链接
+    ///
+    /// 缓存与客户端无关，授权和 IP 规则在统计时按客户端检查。
+    fn contents(&self, state: &AppState, relative: &str) -> io::Result<Arc<DirContents>> {
+        let path = state.root_dir.join(relative);
+        let modified = fs::metadata(&path)?.modified()?;
+        if let Some(cached) = self
+            .dirs
+            .lock()
+            .unwrap()
+            .get(relative)
+            .filter(|cached| cached.modified == modified)
+        {
+            return Ok(cached.clone());
+        }
+        let mut contents = DirContents {
+            modified,
+            size: 0,
+            files: 0,
+            subdirs: Vec::new(),
+        };
+        for entry in fs::read_dir(&path)?.flatten() {
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            let name = entry.file_name().to_string_lossy().into_owned();
+            if state
+                .access
+                .check(&join(relative, &name), file_type.is_dir())
+                .denied
+            {
+                continue;
+            }
+            if file_type.is_dir() {
+                contents.subdirs.push(name);
+            } else {
+                contents.files += 1;
+                contents.size += entry.metadata().map(|m| m.len()).unwrap_or(0);
+            }
+        }
+        let contents = Arc::new(contents);
+        let mut dirs = self.dirs.lock().unwrap();
+        if dirs.len() >= MAX_CACHED_DIRS {
+            dirs.clear();
+        }
+        dirs.insert(relative.to_string(), contents.clone());
+        Ok(contents)
+    }
+
+    /// 递归统计目录，跳过 `viewer` 无权查看的子目录；`cancelled` 置位时提前结束并返回 None
+    fn totals(
+        &self,
+        state: &AppState,
+        relative: &str,
+        viewer: Viewer,
+        cancelled: &AtomicBool,
+    ) -> Option<Totals> {
+        let mut totals = Totals::default();
+        let mut stack = vec![relative.to_string()];
+        while let Some(dir) = stack.pop() {
+            if cancelled.load(Ordering::Relaxed) {
+                return None;
+            }
+            match self.contents(state, &dir) {
+                Ok(contents) => {
+                    totals.size += contents.size;
+                    totals.files += contents.files;
+                    for subdir in contents.subdirs.iter().map(|name| join(&dir, name)) {
+                        if viewer.can_see(state, &subdir) {
+                            totals.dirs += 1;
+                            stack.push(subdir);
+                        } else {
+                            totals.hidden += 1;
+                        }
+                    }
+                }
+                Err(_) => totals.errors += 1,
+            }
+        }
+        Some(totals)
+    }
+}
+
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// 请求结束（完成、客户端断开或服务退出）时通知后台统计停止
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// 处理目录上的 `?du`：统计各子项（递归）的大小，按所选渲染器或 JSON 输出
+///
+/// 各子目录并发统计；客户端断开或服务退出时停止。当前目录的访问规则由调用方检查，
+/// 统计时跳过被排除的路径，以及客户端无权查看的子目录（计入 `hidden`）。
+pub async fn handle(
+    state: &Arc<AppState>,
+    relative: &str,
+    remote: SocketAddr,
+    authorized: bool,
+    renderer: &dyn ListingRenderer,
+    lang: Lang,
+) -> Response<Body> {
+    let relative = relative.trim_matches('/').to_string();
+    let viewer = Viewer { remote, authorized };
+    let cancel = CancelOnDrop(Arc::new(AtomicBool::new(false)));
+    let listing = tokio::task::spawn_blocking({
+        let (state, relative) = (state.clone(), relative.clone());
+        move || read_entries(&state, &relative, viewer)
+    })
+    .await
+    .unwrap_or_else(|e| Err(io::Error::other(e)));
+    let Ok((mut entries, hidden)) = listing else {
+        return text_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            lang.t("http.read_dir_failed"),
+        );
+    };
+
+    let tasks = entries.iter().filter(|entry| entry.is_dir).map(|entry| {
+        let (state, path, cancelled) = (state.clone(), entry.path.clone(), cancel.0.clone());
+        async move {
+            let _permit = state.usage.permits.clone().acquire_owned().await.ok()?;
+            tokio::task::spawn_blocking(move || {
+                state.usage.totals(&state, &path, viewer, &cancelled)
+            })
+                .await
+                .ok()
+                .flatten()
+        }
+    });
+    let closing = state.monitor.closing();
+    let results = tokio::select! {
+        results = futures_util::future::join_all(tasks) => results,
+        _ = closing.cancelled() => {
+            return text_response(StatusCode::SERVICE_UNAVAILABLE, lang.t("http.shutting_down"));
+        }
+    };
+
+    let (mut errors, mut hidden) = (0, hidden);
+    for (entry, totals) in entries.iter_mut().filter(|entry| entry.is_dir).zip(results) {
+        let totals = totals.unwrap_or_default();
+        entry.size = totals.size;
+        entry.size_display = format_size(totals.size);
+        entry.files = totals.files;
+        entry.dirs = totals.dirs;
+        errors += totals.errors;
+        hidden += totals.hidden;
+    }
+    entries.sort_by(|a, b| b.size.cmp(&a.size).then_with(|| a.name.cmp(&b.name)));
+
+    let size = entries.iter().map(|e| e.size).sum();
+    let usage = DiskUsage {
+        path: format!("/{}", relative),
+        href: href_for(&relative),
+        parent: (!relative.is_empty())
+            .then(|| href_for(relative.rsplit_once('/').map(|(p, _)| p).unwrap_or(""))),
+        size,
+        size_display: format_size(size),
+        files: entries.iter().map(|e| e.files).sum(),
+        dirs: entries.iter().map(|e| e.dirs + e.is_dir as u64).sum(),
+        entries,
+        errors,
+        hidden,
+        disk: disk_space(&state.root_dir),
+        relative_path: relative,
+        root: state.root_dir.clone(),
+    };
+    renderer.render_usage(&usage, lang)
+}
+
+/// 目录的直接子项和其中客户端无权查看的子目录数，目录的大小留待统计
+fn read_entries(
+    state: &AppState,
+    relative: &str,
+    viewer: Viewer,
+) -> io::Result<(Vec<UsageEntry>, u64)> {
+    let (mut entries, mut hidden) = (Vec::new(), 0);
+    for entry in fs::read_dir(state.root_dir.join(relative))?.flatten() {
+        let Ok(file_type) = entry.file_type() else {
+            continue;
+        };
+        let name = entry.file_name().to_string_lossy().into_owned();
+        let path = join(relative, &name);
+        let is_dir = file_type.is_dir();
+        if state.access.check(&path, is_dir).denied {
+            continue;
+        }
+        if is_dir && !viewer.can_see(state, &path) {
+            hidden += 1;
+            continue;
+        }
+        let size = if is_dir {
+            0
+        } else {
+            entry.metadata().map(|m| m.len()).unwrap_or(0)
+        };
+        entries.push(UsageEntry {
+            href: href_for(&path),
+            name,
+            path,
+            is_dir,
+            size,
+            size_display: format_size(size),
+            files: u64::from(!is_dir),
+            dirs: 0,
+        });
+    }
+    Ok((entries, hidden))
+}
+
+/// 路径所在文件系统的总空间和可用空间，无法获取时为 None
+pub fn disk_space(path: &Path) -> Option<DiskSpace> {
+    let (total, free) = filesystem_space(path)?;
+    Some(DiskSpace {
+        total,
+        free,
+        total_display: format_size(total),
+        free_display: format_size(free),
+    })
+}
+
+#[cfg(unix)]
+#[allow(clippy::unnecessary_cast)]
+fn filesystem_space(path: &Path) -> Option<(u64, u64)> {
+    use std::os::unix::ffi::OsStrExt;
+    let path = std::ffi::CString::new(path.as_os_str().as_bytes()).ok()?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(path.as_ptr(), &mut stat) } != 0 {
+        return None;
+    }
+    let block = stat.f_frsize as u64;
+    Some((stat.f_blocks as u64 * block, stat.f_bavail as u64 * block))
+}
+
+#[cfg(windows)]
+fn filesystem_space(path: &Path) -> Option<(u64, u64)> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+    let path: Vec<u16> = path
+        .as_os_str()
+        .encode_wide()
+        .chain(std::iter::once(0))
+        .collect();
+    let (mut free, mut total) = (0u64, 0u64);
+    let ok =
+        unsafe { GetDiskFreeSpaceExW(path.as_ptr(), &mut free, &mut total, std::ptr::null_mut()) };
+    (ok != 0).then_some((total, free))
+}
+
+#[cfg(not(any(unix, windows)))]
+fn filesystem_space(_path: &Path) -> Option<(u64, u64)> {
+    None
+}
@@ -0,0 +1,836 @@
+use chrono::Utc;
+use hyper::{header, Body, Method, Request, Response, StatusCode};
+use serde::Serialize;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{self, Read},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use walkdir::WalkDir;
+
+use crate::access::AccessRules;
+use crate::auth;
+use crate::checksum::{Algorithm, Hasher};
+use crate::i18n::Lang;
+use crate::listing::href_for;
+use crate::util::{
+    format_size, format_time, html_escape, normalize_relative, parse_size, read_body, text_response,
+};
+use crate::{log_request, tr, AppState, DuplicatesArgs};
+
+/// 重复文件页面路径
+pub const DUPLICATES_PAGE: &str = "/_tools/duplicates";
+/// 取消正在进行的查找
+const CANCEL_PATH: &str = "/_tools/duplicates/cancel";
+
+/// 比较文件内容使用的摘要算法
+const ALGORITHM: Algorithm = Algorithm::Blake3;
+/// 先只比较文件开头这么多字节，不同的文件大多在这一步就能区分
+const PARTIAL_SIZE: u64 = 64 * 1024;
+/// 开始查找请求体的大小上限
+const MAX_ACTION_BODY: usize = 4096;
+/// 页面中最多列出的重复组数，完整结果见 JSON
+const MAX_PAGE_SETS: usize = 500;
+/// 命令行模式下输出进度的间隔
+const CLI_PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 查找的阶段
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Phase {
+    /// 遍历目录，按大小分组
+    Scanning,
+    /// 计算同样大小的文件开头部分的摘要
+    Partial,
+    /// 计算开头相同的文件的完整摘要
+    Full,
+    Done,
+    Cancelled,
+    Failed,
+}
+
+impl Phase {
+    fn label(self, lang: Lang) -> &'static str {
+        match self {
+            Phase::Scanning => lang.t("duplicates.phase_scanning"),
+            Phase::Partial => lang.t("duplicates.phase_partial"),
+            Phase::Full => lang.t("duplicates.phase_full"),
+            Phase::Done => lang.t("duplicates.phase_done"),
+            Phase::Cancelled => lang.t("duplicates.phase_cancelled"),
+            Phase::Failed => lang.t("duplicates.phase_failed"),
+        }
+    }
+
+    fn running(self) -> bool {
+        matches!(self, Phase::Scanning | Phase::Partial | Phase::Full)
+    }
+}
+
+/// 查找进度，由后台任务更新，页面和 JSON 随时读取
+pub struct Progress {
+    phase: Mutex<Phase>,
+    /// 已遍历的文件数
+    files: AtomicU64,
+    /// 与其他文件大小相同、需要比较内容的文件数
+    candidates: AtomicU64,
+    /// 当前阶段已读取的字节数
+    hashed: AtomicU64,
+    /// 当前阶段需要读取的字节数
+    to_hash: AtomicU64,
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Progress {
+            phase: Mutex::new(Phase::Scanning),
+            files: AtomicU64::new(0),
+            candidates: AtomicU64::new(0),
+            hashed: AtomicU64::new(0),
+            to_hash: AtomicU64::new(0),
+        }
+    }
+}
+
+impl Progress {
+    fn set_phase(&self, phase: Phase, to_hash: u64) {
+        *self.phase.lock().unwrap() = phase;
+        self.hashed.store(0, Ordering::Relaxed);
+        self.to_hash.store(to_hash, Ordering::Relaxed);
+    }
+
+    fn phase(&self) -> Phase {
+        *self.phase.lock().unwrap()
+    }
+
+    fn snapshot(&self) -> ProgressSnapshot {
+        ProgressSnapshot {
+            phase: self.phase(),
+            files: self.files.load(Ordering::Relaxed),
+            candidates: self.candidates.load(Ordering::Relaxed),
+            hashed: self.hashed.load(Ordering::Relaxed),
+            to_hash: self.to_hash.load(Ordering::Relaxed),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct ProgressSnapshot {
+    pub phase: Phase,
+    pub files: u64,
+    pub candidates: u64,
+    pub hashed: u64,
+    pub to_hash: u64,
+}
+
+/// 内容相同的一组文件
+#[derive(Debug, Serialize)]
+pub struct DuplicateSet {
+    /// 单个文件的大小
+    pub size: u64,
+    pub size_display: String,
+    /// 内容的摘要（十六进制）
+    pub digest: String,
+    /// 以 `/` 开头、相对根目录的路径，按名称排列
+    pub paths: Vec<String>,
+    /// 只保留一份时可以腾出的空间
+    pub wasted: u64,
+    pub wasted_display: String,
+}
+
+/// 查找结果
+#[derive(Debug, Serialize)]
+pub struct DuplicateReport {
+    /// 扫描的目录，以 `/` 开头
+    pub path: String,
+    pub algorithm: &'static str,
+    /// 小于该大小的文件不参与比较
+    pub min_size: u64,
+    /// 遍历的文件数
+    pub files: u64,
+    /// 按可腾出的空间从大到小排列
+    pub sets: Vec<DuplicateSet>,
+    pub wasted: u64,
+    pub wasted_display: String,
+    /// 无法读取的目录和文件数，大于 0 时结果可能不完整
+    pub errors: u64,
+}
+
+/// 在根目录下的 `start` 中查找内容相同的文件，`cancelled` 置位时提前结束并返回 None
+///
+/// 先按大小分组，再比较开头部分的摘要，最后只对仍然相同的文件计算完整摘要。
+/// 跳过被访问规则排除的路径、符号链接和空文件；同一文件的多个硬链接只计一次。
+pub fn find_duplicates(
+    root: &Path,
+    start: &str,
+    access: &AccessRules,
+    min_size: u64,
+    progress: &Progress,
+    cancelled: &AtomicBool,
+) -> Option<DuplicateReport> {
+    let mut errors = 0;
+    let mut by_size: HashMap<u64, Vec<String>> = HashMap::new();
+    let mut seen = HashSet::new();
+    let walker = WalkDir::new(root.join(start))
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|entry| {
+            entry.depth() == 0
+                || !access
+                    .check(
+                        &relative_path(root, entry.path()),
+                        entry.file_type().is_dir(),
+                    )
+                    .denied
+        });
+    for entry in walker {
+        if cancelled.load(Ordering::Relaxed) {
+            return None;
+        }
+        let entry = match entry {
+            Ok(entry) => entry,
+            Err(_) => {
+                errors += 1;
+                continue;
+            }
+        };
+        if !entry.file_type().is_file() {
+            continue;
+        }
+        progress.files.fetch_add(1, Ordering::Relaxed);
+        let Ok(metadata) = entry.metadata() else {
+            errors += 1;
+            continue;
+        };
+        if metadata.len() == 0
+            || metadata.len() < min_size
+            || file_id(&metadata).is_some_and(|id| !seen.insert(id))
+        {
+            continue;
+        }
+        by_size
+            .entry(metadata.len())
+            .or_default()
+            .push(relative_path(root, entry.path()));
+    }
+    let files = progress.files.load(Ordering::Relaxed);
+
+    let groups: Vec<(u64, Vec<String>)> = by_size
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .collect();
+    progress.candidates.store(
+        groups.iter().map(|(_, paths)| paths.len() as u64).sum(),
+        Ordering::Relaxed,
+    );
+
+    // 开头部分相同的文件，较小的文件此时已比较了全部内容
+    let to_hash = groups
+        .iter()
+        .map(|(size, paths)| (*size).min(PARTIAL_SIZE) * paths.len() as u64)
+        .sum();
+    progress.set_phase(Phase::Partial, to_hash);
+    let mut candidates = Vec::new();
+    for (size, paths) in groups {
+        let limit = size.min(PARTIAL_SIZE);
+        for (digest, paths) in
+            group_by_digest(root, paths, limit, progress, cancelled, &mut errors)?
+        {
+            candidates.push((size, digest, paths));
+        }
+    }
+
+    let to_hash = candidates
+        .iter()
+        .filter(|(size, _, _)| *size > PARTIAL_SIZE)
+        .map(|(size, _, paths)| size * paths.len() as u64)
+        .sum();
+    progress.set_phase(Phase::Full, to_hash);
+    let mut sets = Vec::new();
+    for (size, digest, paths) in candidates {
+        let groups = if size > PARTIAL_SIZE {
+            group_by_digest(root, paths, size, progress, cancelled, &mut errors)?
+        } else {
+            vec![(digest, paths)]
+        };
+        for (digest, paths) in groups {
+            let wasted = size * (paths.len() as u64 - 1);
+            sets.push(DuplicateSet {
+                size,
+                size_display: format_size(size),
+                digest: hex::encode(digest),
+                paths: paths.into_iter().map(|p| format!("/{}", p)).collect(),
+                wasted,
+                wasted_display: format_size(wasted),
+            });
+        }
+    }
+    sets.sort_by(|a, b| b.wasted.cmp(&a.wasted).then_with(|| a.paths.cmp(&b.paths)));
+
+    let wasted = sets.iter().map(|set| set.wasted).sum();
+    Some(DuplicateReport {
+        path: format!("/{}", start),
+        algorithm: ALGORITHM.name(),
+        min_size,
+        files,
+        sets,
+        wasted,
+        wasted_display: format_size(wasted),
+        errors,
+    })
+}
+
+/// 按前 `limit` 字节的摘要把 `paths` 分组，只返回多于一个文件的组
+fn group_by_digest(
+    root: &Path,
+    paths: Vec<String>,
+    limit: u64,
+    progress: &Progress,
+    cancelled: &AtomicBool,
+    errors: &mut u64,
+) -> Option<Vec<(Vec<u8>, Vec<String>)>> {
+    let mut groups: Vec<(Vec<u8>, Vec<String>)> = Vec::new();
+    for path in paths {
+        match hash_prefix(&root.join(&path), limit, progress, cancelled) {
+            Ok(digest) => match groups.iter_mut().find(|(d, _)| *d == digest) {
+                Some((_, group)) => group.push(path),
+                None => groups.push((digest, vec![path])),
+            },
+            Err(_) if cancelled.load(Ordering::Relaxed) => return None,
+            Err(_) => *errors += 1,
+        }
+    }
+    groups.retain(|(_, paths)| paths.len() > 1);
+    Some(groups)
+}
+
+/// 计算文件前 `limit` 字节的摘要，读取过程中可以取消
+fn hash_prefix(
+    path: &Path,
+    limit: u64,
+    progress: &Progress,
+    cancelled: &AtomicBool,
+) -> io::Result<Vec<u8>> {
+    let mut file = fs::File::open(path)?.take(limit);
+    let mut hasher = Hasher::new(ALGORITHM);
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "cancelled"));
+        }
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        progress.hashed.fetch_add(n as u64, Ordering::Relaxed);
+    }
+    Ok(hasher.finalize())
+}
+
+/// 相对根目录、以 `/` 分隔的路径
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// 标识同一个文件：Unix 上同一文件的硬链接有相同的设备号和 inode，其他平台不区分
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+/// 一次后台查找
+pub struct Job {
+    path: String,
+    min_size: u64,
+    started: i64,
+    progress: Progress,
+    cancelled: AtomicBool,
+    report: Mutex<Option<Arc<DuplicateReport>>>,
+}
+
+/// 查找任务的状态（JSON）
+#[derive(Serialize)]
+struct JobStatus<'a> {
+    path: &'a str,
+    min_size: u64,
+    started: i64,
+    progress: ProgressSnapshot,
+    report: Option<&'a DuplicateReport>,
+}
+
+/// 重复文件查找：同一时间只运行一个后台任务，保留最近一次的结果
+#[derive(Default)]
+pub struct DuplicateFinder {
+    job: Mutex<Option<Arc<Job>>>,
+}
+
+impl DuplicateFinder {
+    fn current(&self) -> Option<Arc<Job>> {
+        self.job.lock().unwrap().clone()
+    }
+
+    /// 开始新的查找，已有任务在运行时返回 None
+    fn start(&self, path: String, min_size: u64) -> Option<Arc<Job>> {
+        let mut current = self.job.lock().unwrap();
+        if current
+            .as_ref()
+            .is_some_and(|job| job.progress.phase().running())
+        {
+            return None;
+        }
+        let job = Arc::new(Job {
+            path,
+            min_size,
+            started: Utc::now().timestamp(),
+            progress: Progress::default(),
+            cancelled: AtomicBool::new(false),
+            report: Mutex::new(None),
+        });
+        *current = Some(job.clone());
+        Some(job)
+    }
+}
+
+/// 在后台运行查找，服务退出时取消；开始和结束记入访问日志
+fn spawn_job(state: Arc<AppState>, job: Arc<Job>, client: std::net::IpAddr) {
+    log_request(
+        &state.log_mode,
+        state.logger.clone(),
+        format!("DUP scan {} [{}]", job.path, client),
+    );
+    let closing = state.monitor.closing();
+    let watcher = tokio::spawn({
+        let job = job.clone();
+        async move {
+            closing.cancelled().await;
+            job.cancelled.store(true, Ordering::Relaxed);
+        }
+    });
+    tokio::spawn(async move {
+        let task = {
+            let (state, job) = (state.clone(), job.clone());
+            tokio::task::spawn_blocking(move || {
+                let start = job.path.trim_start_matches('/');
+                find_duplicates(
+                    &state.root_dir,
+                    start,
+                    &state.access,
+                    job.min_size,
+                    &job.progress,
+                    &job.cancelled,
+                )
+            })
+        };
+        let outcome = task.await;
+        watcher.abort();
+        let phase = match outcome {
+            Ok(Some(report)) => {
+                log_request(
+                    &state.log_mode,
+                    state.logger.clone(),
+                    format!(
+                        "DUP done {}: {} set(s), {} wasted",
+                        job.path,
+                        report.sets.len(),
+                        report.wasted_display
+                    ),
+                );
+                *job.report.lock().unwrap() = Some(Arc::new(report));
+                Phase::Done
+            }
+            Ok(None) => {
+                log_request(
+                    &state.log_mode,
+                    state.logger.clone(),
+                    format!("DUP cancelled {}", job.path),
+                );
+                Phase::Cancelled
+            }
+            Err(_) => Phase::Failed,
+        };
+        job.progress.set_phase(phase, 0);
+    });
+}
+
+/// 是否为重复文件页面或其操作的路径
+pub fn is_duplicates_path(path: &str) -> bool {
+    path == DUPLICATES_PAGE || path.starts_with("/_tools/duplicates/")
+}
+
+/// 处理重复文件查找请求，需要管理权限
+///
+/// - `GET /_tools/duplicates`：最近一次查找的进度与结果，`?format=json` 或 `Accept: application/json` 时返回 JSON
+/// - `POST /_tools/duplicates`（`path`，可选 `min_size`）：在后台开始查找
+/// - `POST /_tools/duplicates/cancel`：取消正在进行的查找
+pub async fn handle(
+    req: Request<Body>,
+    remote: SocketAddr,
+    state: Arc<AppState>,
+) -> Response<Body> {
+    let lang = Lang::for_request(&req, state.lang);
+    if !state.auth.is_authorized(&req, remote) {
+        return auth::unauthorized(lang);
+    }
+    if req.method() == Method::POST {
+        if let Some(response) = auth::reject_cross_site(&req, lang) {
+            return response;
+        }
+    }
+    let path = req.uri().path().to_string();
+    match (req.method().clone(), path.as_str()) {
+        (Method::GET, DUPLICATES_PAGE) => {
+            let job = state.duplicates.current();
+            let mut response = if crate::wants_json(&req) {
+                status_response(StatusCode::OK, job.as_deref())
+            } else {
+                let path = crate::util::query_param(req.uri().query(), "path");
+                page(job.as_deref(), path.as_deref(), lang)
+            };
+            if let Some(cookie) = state.auth.cookie_for(&req) {
+                response
+                    .headers_mut()
+                    .insert(header::SET_COOKIE, cookie.parse().unwrap());
+            }
+            response
+        }
+        (Method::POST, DUPLICATES_PAGE) => {
+            let json = crate::wants_json(&req);
+            let Some(body) = read_body(req, MAX_ACTION_BODY).await else {
+                return text_response(StatusCode::PAYLOAD_TOO_LARGE, lang.t("http.body_too_large"));
+            };
+            let field = |name: &str| {
+                form_urlencoded::parse(&body)
+                    .find(|(k, _)| k == name)
+                    .map(|(_, v)| v.into_owned())
+                    .filter(|v| !v.trim().is_empty())
+            };
+            let Some(relative) = normalize_relative(&field("path").unwrap_or_default()) else {
+                return text_response(StatusCode::BAD_REQUEST, lang.t("http.bad_path"));
+            };
+            let min_size = match field("min_size").as_deref().map(parse_size).transpose() {
+                Ok(size) => size.unwrap_or(0),
+                Err(e) => {
+                    return text_response(
+                        StatusCode::BAD_REQUEST,
+                        &tr!(lang, "duplicates.bad_min_size", error = e),
+                    )
+                }
+            };
+            let full_path = state.root_dir.join(&relative);
+            if !full_path.exists() || state.access.check(&relative, full_path.is_dir()).denied {
+                return text_response(StatusCode::NOT_FOUND, lang.t("http.not_found"));
+            }
+            if !full_path.is_dir() {
+                return text_response(StatusCode::BAD_REQUEST, lang.t("duplicates.not_a_dir"));
+            }
+            let Some(job) = state.duplicates.start(format!("/{}", relative), min_size) else {
+                return text_response(StatusCode::CONFLICT, lang.t("duplicates.busy"));
+            };
+            spawn_job(state.clone(), job.clone(), remote.ip());
+            if json {
+                status_response(StatusCode::ACCEPTED, Some(&job))
+            } else {
+                see_other()
+            }
+        }
+        (Method::POST, CANCEL_PATH) => {
+            if let Some(job) = state.duplicates.current() {
+                job.cancelled.store(true, Ordering::Relaxed);
+            }
+            see_other()
+        }
+        _ => text_response(StatusCode::NOT_FOUND, lang.t("http.not_found")),
+    }
+}
+
+/// 查找任务的状态，还没有开始过查找时为 `null`
+fn status_response(status: StatusCode, job: Option<&Job>) -> Response<Body> {
+    let report = job.and_then(|job| job.report.lock().unwrap().clone());
+    let body = job.map(|job| JobStatus {
+        path: &job.path,
+        min_size: job.min_size,
+        started: job.started,
+        progress: job.progress.snapshot(),
+        report: report.as_deref(),
+    });
+    Response::builder()
+        .status(status)
+        .header("Content-Type", "application/json")
+        .header(header::CACHE_CONTROL, "no-store")
+        .body(Body::from(serde_json::to_string(&body).unwrap()))
+        .unwrap()
+}
+
+fn see_other() -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::SEE_OTHER)
+        .header(header::LOCATION, DUPLICATES_PAGE)
+        .body(Body::empty())
+        .unwrap()
+}
+
+/// 重复文件页面：开始查找的表单、当前进度和最近一次的结果
+fn page(job: Option<&Job>, path: Option<&str>, lang: Lang) -> Response<Body> {
+    let running = job.is_some_and(|job| job.progress.phase().running());
+    let mut status = String::new();
+    let mut rows = String::new();
+    if let Some(job) = job {
+        let progress = job.progress.snapshot();
+        status = format!(
+            "<p>{} {} — {} ({})</p>",
+            if running { "⏳" } else { "📋" },
+            html_escape(&job.path),
+            progress.phase.label(lang),
+            format_time(job.started),
+        );
+        status += &format!(
+            r#"<p class="summary">{}</p>"#,
+            html_escape(&tr!(
+                lang,
+                "duplicates.progress",
+                files = progress.files,
+                candidates = progress.candidates
+            ))
+        );
+        if running && progress.phase != Phase::Scanning {
+            status += &format!(
+                r#"<p><progress value="{}" max="{}"></progress> {} / {}</p>"#,
+                progress.hashed,
+                progress.to_hash.max(1),
+                format_size(progress.hashed),
+                format_size(progress.to_hash),
+            );
+        }
+        if running {
+            status += &format!(
+                r#"<form method="post" action="{}"><button>{}</button></form>"#,
+                CANCEL_PATH,
+                lang.t("duplicates.cancel")
+            );
+        }
+        if let Some(report) = job.report.lock().unwrap().as_ref() {
+            status += &format!(
+                r#"<p class="summary">{}</p>"#,
+                html_escape(&tr!(
+                    lang,
+                    "duplicates.summary",
+                    sets = report.sets.len(),
+                    wasted = &report.wasted_display,
+                    files = report.files
+                ))
+            );
+            if report.errors > 0 {
+                status += &format!(
+                    "<p>⚠️ {}</p>",
+                    html_escape(&tr!(lang, "duplicates.errors", count = report.errors))
+                );
+            }
+            if report.sets.len() > MAX_PAGE_SETS {
+                status += &format!(
+                    r#"<p class="summary">{}</p>"#,
+                    html_escape(&tr!(lang, "duplicates.truncated", count = MAX_PAGE_SETS))
+                );
+            }
+            for set in report.sets.iter().take(MAX_PAGE_SETS) {
+                let links: Vec<String> = set
+                    .paths
+                    .iter()
+                    .map(|p| {
+                        format!(
+                            r#"<a href="{}">{}</a>"#,
+                            html_escape(&href_for(p)),
+                            html_escape(p)
+                        )
+                    })
+                    .collect();
+                rows += &format!(
+                    "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                    links.join("<br>"),
+                    set.size_display,
+                    set.paths.len(),
+                    set.wasted_display,
+                );
+            }
+            if report.sets.is_empty() {
+                rows = format!(
+                    r#"<tr><td colspan="4" style="text-align:center; color:#666">{}</td></tr>"#,
+                    lang.t("duplicates.none")
+                );
+            }
+        }
+    }
+
+    let path = path
+        .map(str::to_string)
+        .or_else(|| job.map(|job| job.path.clone()))
+        .unwrap_or_else(|| "/".to_string());
+    let table = if rows.is_empty() {
+        String::new()
+    } else {
+        format!(
+            "<table>\n<tr><th>{}</th><th>{}</th><th>{}</th><th>{}</th></tr>\n{}</table>",
+            lang.t("duplicates.col_paths"),
+            lang.t("duplicates.col_size"),
+            lang.t("duplicates.col_copies"),
+            lang.t("duplicates.col_wasted"),
+            rows
+        )
+    };
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+    <meta charset="utf-8">
+    {refresh}<title>🧬 {title}</title>
+    <style>
+        body {{ font-family: -apple-system, 'Segoe UI', Roboto, sans-serif; margin: 40px; }}
+        table {{ border-collapse: collapse; width: 100%; }}
+        th, td {{ border-bottom: 1px solid #e9ecef; padding: 8px; text-align: left; vertical-align: top; }}
+        td:first-child {{ word-break: break-all; }}
+        .summary {{ color: #666; }}
+    </style>
+</head>
+<body>
+    <h2>🧬 {title}</h2>
+    <p><a href="/">🏠 {home}</a></p>
+    <form method="post" action="{action}">
+        <label>{label_path} <input name="path" value="{path}"></label>
+        <label>{label_min_size} <input name="min_size" placeholder="1M" size="6"></label>
+        <button{start_disabled}>{start}</button>
+    </form>
+    {status}
+    {table}
+</body>
+</html>"#,
+        lang = lang.code(),
+        refresh = if running {
+            "<meta http-equiv=\"refresh\" content=\"2\">\n    "
+        } else {
+            ""
+        },
+        title = lang.t("duplicates.page_title"),
+        home = lang.t("listing.home"),
+        action = DUPLICATES_PAGE,
+        label_path = lang.t("duplicates.label_path"),
+        label_min_size = lang.t("duplicates.label_min_size"),
+        path = html_escape(&path),
+        start_disabled = if running { " disabled" } else { "" },
+        start = lang.t("duplicates.start"),
+        status = status,
+        table = table,
+    );
+    Response::builder()
+        .header("Content-Type", "text/html; charset=utf-8")
+        .header(header::CACHE_CONTROL, "no-store")
+        .body(Body::from(html))
+        .unwrap()
+}
+
+/// `duplicates` 子命令：查找根目录（或其中的目录）下的重复文件，结果以 JSON 输出到标准输出，进度输出到标准错误
+pub async fn run_duplicates_command(
+    cmd: &DuplicatesArgs,
+    root_dir: &Path,
+    access: AccessRules,
+    lang: Lang,
+) {
+    let full = match &cmd.path {
+        Some(path) => {
+            let full = PathBuf::from(path);
+            if full.is_absolute() {
+                full
+            } else {
+                std::env::current_dir().unwrap().join(full)
+            }
+        }
+        None => root_dir.to_path_buf(),
+    };
+    let relative = full
+        .canonicalize()
+        .ok()
+        .and_then(|p| {
+            let root = root_dir.canonicalize().ok()?;
+            p.strip_prefix(&root).ok().map(|r| r.to_path_buf())
+        })
+        .and_then(|r| normalize_relative(&r.to_string_lossy().replace('\\', "/")));
+    let Some(relative) = relative.filter(|r| root_dir.join(r).is_dir()) else {
+        let path = cmd
+            .path
+            .clone()
+            .unwrap_or_else(|| root_dir.display().to_string());
+        eprintln!(
+            "❌ \x1B[91m{}\x1B[0m",
+            tr!(lang, "duplicates.cli_not_in_root", path = path)
+        );
+        std::process::exit(1);
+    };
+    let min_size = match cmd.min_size.as_deref().map(parse_size).transpose() {
+        Ok(size) => size.unwrap_or(0),
+        Err(e) => {
+            eprintln!("❌ \x1B[91m{}\x1B[0m", e);
+            std::process::exit(1);
+        }
+    };
+
+    let progress = Arc::new(Progress::default());
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let mut task = tokio::task::spawn_blocking({
+        let (root_dir, progress, cancelled) =
+            (root_dir.to_path_buf(), progress.clone(), cancelled.clone());
+        move || {
+            find_duplicates(
+                &root_dir, &relative, &access, min_size, &progress, &cancelled,
+            )
+        }
+    });
+    let mut interval = tokio::time::interval(CLI_PROGRESS_INTERVAL);
+    let report = loop {
+        tokio::select! {
+            outcome = &mut task => break outcome.ok().flatten(),
+            _ = tokio::signal::ctrl_c() => cancelled.store(true, Ordering::Relaxed),
+            _ = interval.tick() => {
+                let p = progress.snapshot();
+                eprint!(
+                    "\r🧬 {}: {}  {} / {}   ",
+                    p.phase.label(lang),
+                    tr!(lang, "duplicates.progress", files = p.files, candidates = p.candidates),
+                    format_size(p.hashed),
+                    format_size(p.to_hash),
+                );
+            }
+        }
+    };
+    eprintln!();
+    let Some(report) = report else {
+        eprintln!("❌ \x1B[91m{}\x1B[0m", Phase::Cancelled.label(lang));
+        std::process::exit(1);
+    };
+    println!("{}", serde_json::to_string_pretty(&report).unwrap());
+    eprintln!(
+        "✅ {}",
+        tr!(
+            lang,
+            "duplicates.summary",
+            sets = report.sets.len(),
+            wasted = &report.wasted_display,
+            files = report.files
+        )
+    );
+}
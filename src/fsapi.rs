@@ -0,0 +1,1194 @@
+use hyper::{header, Body, Method, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashSet,
+    fs, io,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use crate::access::{AccessRules, RULES_FILE};
+use crate::auth;
+use crate::i18n::Lang;
+use crate::trash::{Trash, TrashItem};
+use crate::upload::{
+    digest_field, header_digests, sanitize_file_name, unique_path_by, verified_note, write_temp,
+    FileDigest, UploadError,
+};
+use crate::util::{
+    copy_tree, move_tree, normalize_relative, query_param, read_body, remove_tree, text_response,
+};
+use crate::{log_request, tr, AppState};
+
+/// 文件管理接口路径前缀
+pub const FS_API_PREFIX: &str = "/_api/fs/";
+
+/// 请求体的大小上限
+const MAX_REQUEST_BODY: usize = 64 * 1024;
+
+/// 流式返回进度时的报告间隔
+const PROGRESS_INTERVAL: Duration = Duration::from_millis(250);
+
+/// 是否为文件管理接口的路径
+pub fn is_fs_api_path(path: &str) -> bool {
+    path.starts_with(FS_API_PREFIX)
+}
+
+#[derive(Clone, Copy)]
+enum Op {
+    Mkdir,
+    Rename,
+    Move,
+    Copy,
+    Delete,
+}
+
+impl Op {
+    fn parse(name: &str) -> Option<Op> {
+        match name {
+            "mkdir" => Some(Op::Mkdir),
+            "rename" => Some(Op::Rename),
+            "move" => Some(Op::Move),
+            "copy" => Some(Op::Copy),
+            "delete" => Some(Op::Delete),
+            _ => None,
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Op::Mkdir => "mkdir",
+            Op::Rename => "rename",
+            Op::Move => "move",
+            Op::Copy => "copy",
+            Op::Delete => "delete",
+        }
+    }
+}
+
+/// 目标已存在时的处理方式
+#[derive(Clone, Copy, Default, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Conflict {
+    /// 返回 409，不做任何修改
+    #[default]
+    Fail,
+    /// 先删除已存在的目标
+    Overwrite,
+    /// 追加 ` (1)`、` (2)` ... 保留两者
+    Rename,
+}
+
+/// 请求体，路径均为以 `/` 开头、相对根目录的路径（不做 URL 解码）
+#[derive(Deserialize)]
+struct FsRequest {
+    /// mkdir 为新目录，rename 为要重命名的文件或目录
+    path: Option<String>,
+    /// move / copy / delete 的源路径，可以同时指定多个
+    #[serde(default)]
+    paths: Vec<String>,
+    /// rename 的新名称
+    name: Option<String>,
+    /// move / copy 的目标目录
+    to: Option<String>,
+    #[serde(default)]
+    conflict: Conflict,
+}
+
+/// 校验通过、等待执行的一项操作
+struct Step {
+    /// 源路径（相对根目录），mkdir 时为 None
+    from: Option<String>,
+    /// 目标路径（相对根目录），delete 时为 None
+    to: Option<String>,
+    /// 目标已存在，执行前先删除
+    replace: bool,
+}
+
+/// 已处理和总共的文件与目录数
+#[derive(Default)]
+struct Progress {
+    done: AtomicU64,
+    total: AtomicU64,
+}
+
+/// 执行结果：完成的路径，以及出错时的原因
+struct Outcome {
+    paths: Vec<String>,
+    error: Option<String>,
+}
+
+/// 处理 `POST /_api/fs/{mkdir,rename,move,copy,delete}`，需要 `--allow-write` 和管理权限
+///
+/// 请求体为 JSON，例如 `{"paths": ["/a.txt", "/docs"], "to": "/backup", "conflict": "rename"}`。
+/// 所有路径先全部校验（存在性、访问规则、`write off`、目标冲突），通过后才开始修改。
+/// 成功时返回 `{"ok": true, "paths": [...]}`；请求头带 `Accept: application/x-ndjson` 时
+/// 逐行返回进度 `{"done": n, "total": m}`，最后一行为结果。每一项修改都记入访问日志。
+pub async fn handle(
+    req: Request<Body>,
+    remote: SocketAddr,
+    state: Arc<AppState>,
+) -> Response<Body> {
+    let lang = Lang::for_request(&req, state.lang);
+    if !state.allow_write {
+        return text_response(StatusCode::FORBIDDEN, lang.t("fs.disabled"));
+    }
+    if !state.auth.is_authorized(&req, remote) {
+        return auth::unauthorized(lang);
+    }
+    if let Some(response) = auth::reject_cross_site(&req, lang) {
+        return response;
+    }
+    let Some(op) = req
+        .uri()
+        .path()
+        .strip_prefix(FS_API_PREFIX)
+        .and_then(Op::parse)
+    else {
+        return text_response(StatusCode::NOT_FOUND, lang.t("http.not_found"));
+    };
+    if req.method() != Method::POST {
+        return text_response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            lang.t("http.method_not_allowed"),
+        );
+    }
+    // 表单和 text/plain 请求无需预检即可跨站发出，只接受 JSON
+    let is_json = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.split(';').next().unwrap_or("").trim() == "application/json");
+    if !is_json {
+        return text_response(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            lang.t("http.json_required"),
+        );
+    }
+    let stream = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("application/x-ndjson"));
+    let Some(body) = read_body(req, MAX_REQUEST_BODY).await else {
+        return text_response(StatusCode::PAYLOAD_TOO_LARGE, lang.t("http.body_too_large"));
+    };
+    let request: FsRequest = match serde_json::from_slice(&body) {
+        Ok(request) => request,
+        Err(e) => return text_response(StatusCode::BAD_REQUEST, &format!("400 - {}", e)),
+    };
+    let planner = Planner::new(&state, remote.ip(), lang);
+    match planner.plan(op, request) {
+        Ok(steps) => run(op, steps, state, remote.ip(), stream, lang).await,
+        Err(Rejected(status, message)) => text_response(status, &message),
+    }
+}
+
+/// 处理上传：`PUT /dir/name` 把请求体写入单个文件，已存在时覆盖；
+/// `POST /dir/` 以 multipart/form-data 上传多个文件到目录，同名文件按 `?conflict=` 处理（默认返回 409）
+///
+/// 需要 `--allow-write` 和管理权限，路径校验与文件管理接口相同。文件先写入同目录下的临时文件，
+/// 完成后再替换目标；被覆盖的旧内容保存为历史版本（`--keep-versions`），未开启时移入回收站。
+/// 成功时返回 `{"ok": true, "paths": [...]}`，PUT 新建文件时状态码为 201。
+pub async fn upload(
+    req: Request<Body>,
+    path: &str,
+    remote: SocketAddr,
+    state: Arc<AppState>,
+) -> Response<Body> {
+    let lang = Lang::for_request(&req, state.lang);
+    if !state.allow_write {
+        return text_response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            lang.t("http.method_not_allowed"),
+        );
+    }
+    if !state.auth.is_authorized(&req, remote) {
+        return auth::unauthorized(lang);
+    }
+    if let Some(response) = auth::reject_cross_site(&req, lang) {
+        return response;
+    }
+    let result = if req.method() == Method::PUT {
+        put(req, path, &state, remote.ip(), lang).await
+    } else {
+        post(req, path, &state, remote.ip(), lang).await
+    };
+    match result {
+        Ok((paths, created)) => Response::builder()
+            .status(if created {
+                StatusCode::CREATED
+            } else {
+                StatusCode::OK
+            })
+            .header("Content-Type", "application/json")
+            .body(Body::from(
+                serde_json::json!({ "ok": true, "paths": paths }).to_string(),
+            ))
+            .unwrap(),
+        Err(Rejected(status, message)) => text_response(status, &message),
+    }
+}
+
+/// 以请求体写入单个文件，返回文件路径和是否为新建
+async fn put(
+    req: Request<Body>,
+    path: &str,
+    state: &Arc<AppState>,
+    client: IpAddr,
+    lang: Lang,
+) -> Result<(Vec<String>, bool), Rejected> {
+    let planner = Planner::new(state, client, lang);
+    let Some(relative) = normalize_relative(path) else {
+        return Err(planner.bad_path());
+    };
+    let (dir, name) = split(&relative);
+    if name.is_empty() {
+        return Err(planner.reject(StatusCode::FORBIDDEN, tr!(lang, "fs.root")));
+    }
+    let (relative, replace) = planner.upload_target(dir, name, Conflict::Overwrite)?;
+    let expected = header_digests(req.headers()).map_err(|e| upload_error(e, lang))?;
+    let total = content_length(&req);
+    let body = state
+        .monitor
+        .track_upload(req.into_body(), &display(&relative), client, total);
+    let path = receive(body, state, client, relative, replace, &expected, lang).await?;
+    Ok((vec![path], !replace))
+}
+
+/// 逐个保存 multipart 请求中的文件字段，返回各文件的路径
+///
+/// 文件字段之前的摘要字段（见 [`digest_field`]）用于校验该文件；请求头中的摘要针对整个
+/// multipart 请求体而不是其中的文件，因此不接受。
+async fn post(
+    req: Request<Body>,
+    path: &str,
+    state: &Arc<AppState>,
+    client: IpAddr,
+    lang: Lang,
+) -> Result<(Vec<String>, bool), Rejected> {
+    let planner = Planner::new(state, client, lang);
+    let conflict = match query_param(req.uri().query(), "conflict") {
+        Some(value) => serde_json::from_value(serde_json::Value::String(value))
+            .map_err(|e| planner.reject(StatusCode::BAD_REQUEST, e.to_string()))?,
+        None => Conflict::Fail,
+    };
+    let Some(dir) = normalize_relative(path) else {
+        return Err(planner.bad_path());
+    };
+    let dir = planner.resolve(&dir)?;
+    if !header_digests(req.headers())
+        .map_err(|e| upload_error(e, lang))?
+        .is_empty()
+    {
+        return Err(upload_error(UploadError::MultipartDigest, lang));
+    }
+    let content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    let boundary =
+        multer::parse_boundary(content_type).map_err(|e| upload_error(e.into(), lang))?;
+    let total = content_length(&req);
+    let body = state
+        .monitor
+        .track_upload(req.into_body(), &display(&dir), client, total);
+    let mut multipart = multer::Multipart::new(body, boundary);
+
+    let mut paths = Vec::new();
+    let mut expected = Vec::new();
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| upload_error(e.into(), lang))?
+    {
+        let Some(name) = field.file_name() else {
+            if let Some(digest) = digest_field(field)
+                .await
+                .map_err(|e| upload_error(e, lang))?
+            {
+                expected.push(digest);
+            }
+            continue;
+        };
+        let name =
+            sanitize_file_name(name).ok_or_else(|| upload_error(UploadError::BadName, lang))?;
+        let (relative, replace) = planner.upload_target(&dir, &name, conflict)?;
+        let expected = std::mem::take(&mut expected);
+        paths.push(receive(field, state, client, relative, replace, &expected, lang).await?);
+    }
+    if paths.is_empty() {
+        return Err(planner.reject(StatusCode::BAD_REQUEST, tr!(lang, "receive.no_files")));
+    }
+    Ok((paths, false))
+}
+
+/// 写入临时文件并校验摘要后替换目标，返回文件路径
+async fn receive<S, E>(
+    stream: S,
+    state: &Arc<AppState>,
+    client: IpAddr,
+    relative: String,
+    replace: bool,
+    expected: &[FileDigest],
+    lang: Lang,
+) -> Result<String, Rejected>
+where
+    S: futures_util::Stream<Item = Result<hyper::body::Bytes, E>>,
+    UploadError: From<E>,
+{
+    let (dir, name) = split(&relative);
+    let max_size = Some(state.max_upload_size);
+    match write_temp(stream, &state.root_dir.join(dir), name, max_size, expected).await {
+        Ok((temp, _, _)) => {
+            finish_upload(state, client, relative, replace, temp, expected, lang).await
+        }
+        Err(e) => Err(upload_failed(state, client, &relative, e, lang)),
+    }
+}
+
+/// 校验上传目标：目录 `dir` 中名为 `name` 的文件，返回目标路径和是否覆盖已有文件
+///
+/// 断点续传（tus）在创建上传和上传完成时都会校验一次。
+pub fn check_upload(
+    state: &AppState,
+    client: IpAddr,
+    lang: Lang,
+    dir: &str,
+    name: &str,
+    conflict: Conflict,
+) -> Result<(String, bool), Rejected> {
+    let planner = Planner::new(state, client, lang);
+    let Some(dir) = normalize_relative(dir) else {
+        return Err(planner.bad_path());
+    };
+    planner.upload_target(&dir, name, conflict)
+}
+
+/// 把上传完成的临时文件放到目标位置并记入访问日志（包括已校验的摘要），返回文件路径
+pub async fn finish_upload(
+    state: &Arc<AppState>,
+    client: IpAddr,
+    relative: String,
+    replace: bool,
+    temp: PathBuf,
+    verified: &[FileDigest],
+    lang: Lang,
+) -> Result<String, Rejected> {
+    let task = tokio::task::spawn_blocking({
+        let (state, relative) = (state.clone(), relative.clone());
+        move || commit(&state, &relative, &temp, replace)
+    });
+    match task.await.unwrap_or_else(|e| Err(io::Error::other(e))) {
+        Ok(note) => {
+            let path = display(&relative);
+            log_request(
+                &state.log_mode,
+                state.logger.clone(),
+                format!(
+                    "FS upload {}{}{} [{}]",
+                    path,
+                    note,
+                    verified_note(verified),
+                    client
+                ),
+            );
+            Ok(path)
+        }
+        Err(e) => Err(upload_failed(
+            state,
+            client,
+            &relative,
+            UploadError::Io(e),
+            lang,
+        )),
+    }
+}
+
+/// 上传失败时记入访问日志，返回给客户端的错误
+fn upload_failed(
+    state: &AppState,
+    client: IpAddr,
+    relative: &str,
+    e: UploadError,
+    lang: Lang,
+) -> Rejected {
+    let Rejected(status, message) = upload_error(e, lang);
+    log_request(
+        &state.log_mode,
+        state.logger.clone(),
+        format!(
+            "{} FS upload {} [{}] - {}",
+            status.as_u16(),
+            display(relative),
+            client,
+            message
+        ),
+    );
+    Rejected(status, message)
+}
+
+/// 用上传完成的临时文件替换目标，返回日志中的附注
+///
+/// 被覆盖的旧内容保存为历史版本，未开启历史版本时移入回收站；失败时删除临时文件。
+/// 临时文件可能位于数据目录下，跨文件系统时改为复制。
+fn commit(state: &AppState, relative: &str, temp: &Path, replace: bool) -> io::Result<String> {
+    let result = keep_previous(state, relative, replace)
+        .and_then(|note| move_tree(temp, &state.root_dir.join(relative)).map(|_| note));
+    if result.is_err() {
+        let _ = fs::remove_file(temp);
+    }
+    result
+}
+
+/// 保留即将被覆盖的文件，返回日志中的附注
+fn keep_previous(state: &AppState, relative: &str, replace: bool) -> io::Result<String> {
+    let root = &state.root_dir;
+    match (replace, &state.versions, &state.trash) {
+        (true, Some(versions), _) => {
+            let version = versions.snapshot(root, relative)?;
+            Ok(format!(" (version {})", version.id))
+        }
+        (true, None, Some(trash)) => {
+            let item = trash.put(root, relative)?;
+            purge_trash(state);
+            Ok(format!(" (trash {})", item.id))
+        }
+        _ => Ok(String::new()),
+    }
+}
+
+fn upload_error(e: UploadError, lang: Lang) -> Rejected {
+    let status = match e {
+        UploadError::TooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+        UploadError::Io(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        _ => StatusCode::BAD_REQUEST,
+    };
+    Rejected(status, format!("{} - {}", status.as_u16(), e.message(lang)))
+}
+
+fn content_length(req: &Request<Body>) -> Option<u64> {
+    req.headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse().ok())
+}
+
+/// 校验失败时返回给客户端的状态码和消息
+#[derive(Debug)]
+pub struct Rejected(pub StatusCode, pub String);
+
+/// 校验请求中的路径，生成操作步骤
+struct Planner<'a> {
+    root: &'a Path,
+    access: &'a AccessRules,
+    client: IpAddr,
+    lang: Lang,
+}
+
+impl<'a> Planner<'a> {
+    fn new(state: &'a AppState, client: IpAddr, lang: Lang) -> Self {
+        Planner {
+            root: &state.root_dir,
+            access: &state.access,
+            client,
+            lang,
+        }
+    }
+
+    fn plan(&self, op: Op, request: FsRequest) -> Result<Vec<Step>, Rejected> {
+        let conflict = request.conflict;
+        match op {
+            Op::Mkdir => {
+                let Some(relative) = normalize_relative(&request.path.unwrap_or_default()) else {
+                    return Err(self.bad_path());
+                };
+                let (parent, name) = split(&relative);
+                if name.is_empty() {
+                    return Err(self.reject(StatusCode::FORBIDDEN, tr!(self.lang, "fs.root")));
+                }
+                // 已存在的目录不会被覆盖
+                let conflict = if conflict == Conflict::Overwrite {
+                    Conflict::Fail
+                } else {
+                    conflict
+                };
+                let (to, _) = self.target(parent, name, true, conflict, &HashSet::new())?;
+                Ok(vec![Step {
+                    from: None,
+                    to: Some(to),
+                    replace: false,
+                }])
+            }
+            Op::Rename => {
+                let from = self.source(&request.path.unwrap_or_default())?;
+                let is_dir = self.root.join(&from).is_dir();
+                let name = request.name.unwrap_or_default();
+                let (to, replace) =
+                    self.target(split(&from).0, &name, is_dir, conflict, &HashSet::new())?;
+                self.check_overlap(&from, &to, replace)?;
+                Ok(vec![Step {
+                    from: Some(from),
+                    to: Some(to),
+                    replace,
+                }])
+            }
+            Op::Move | Op::Copy => {
+                let Some(dir) = request.to else {
+                    return Err(self.bad_path());
+                };
+                let mut steps = Vec::new();
+                // 同名的源（例如 `a/x.txt` 和 `b/x.txt`）会落到同一个目标，后一个按目标已存在处理
+                let mut planned = HashSet::new();
+                for path in self.sources(request.path, request.paths)? {
+                    let from = self.source(&path)?;
+                    let is_dir = self.root.join(&from).is_dir();
+                    let (to, replace) =
+                        self.target(&dir, split(&from).1, is_dir, conflict, &planned)?;
+                    self.check_overlap(&from, &to, replace)?;
+                    planned.insert(to.clone());
+                    steps.push(Step {
+                        from: Some(from),
+                        to: Some(to),
+                        replace,
+                    });
+                }
+                Ok(steps)
+            }
+            Op::Delete => self
+                .sources(request.path, request.paths)?
+                .iter()
+                .map(|path| {
+                    Ok(Step {
+                        from: Some(self.source(path)?),
+                        to: None,
+                        replace: false,
+                    })
+                })
+                .collect(),
+        }
+    }
+
+    /// `path` 与 `paths` 合并后的源路径列表，不能为空
+    fn sources(&self, path: Option<String>, paths: Vec<String>) -> Result<Vec<String>, Rejected> {
+        let paths: Vec<String> = path.into_iter().chain(paths).collect();
+        if paths.is_empty() {
+            return Err(self.reject(StatusCode::BAD_REQUEST, tr!(self.lang, "fs.no_paths")));
+        }
+        Ok(paths)
+    }
+
+    /// 解析路径并检查目录 IP 规则和写权限，被排除的路径按不存在处理
+    fn resolve(&self, path: &str) -> Result<String, Rejected> {
+        let Some(relative) = normalize_relative(path) else {
+            return Err(self.bad_path());
+        };
+        let full = self.root.join(&relative);
+        let access = self.access.check(&relative, full.is_dir());
+        if access.denied {
+            return Err(self.not_found(&relative));
+        }
+        if access
+            .ip_rules
+            .is_some_and(|rules| rules.check(self.client).is_err())
+        {
+            return Err(Rejected(
+                StatusCode::FORBIDDEN,
+                self.lang.t("http.forbidden").to_string(),
+            ));
+        }
+        if !access.writable {
+            return Err(self.reject(
+                StatusCode::FORBIDDEN,
+                tr!(self.lang, "fs.read_only", path = display(&relative)),
+            ));
+        }
+        Ok(relative)
+    }
+
+    /// 要修改的已有文件或目录，不能是根目录，也不能包含数据目录等保留路径
+    fn source(&self, path: &str) -> Result<String, Rejected> {
+        let relative = self.resolve(path)?;
+        if relative.is_empty() {
+            return Err(self.reject(StatusCode::FORBIDDEN, tr!(self.lang, "fs.root")));
+        }
+        if fs::symlink_metadata(self.root.join(&relative)).is_err() {
+            return Err(self.not_found(&relative));
+        }
+        // 源本身是符号链接时只操作链接，但所在目录不能经符号链接指到根目录之外
+        self.check_inside(split(&relative).0)?;
+        if self.access.contains_reserved(&relative) {
+            return Err(self.reject(
+                StatusCode::FORBIDDEN,
+                tr!(self.lang, "fs.reserved", path = display(&relative)),
+            ));
+        }
+        self.check_subtree(&relative)?;
+        Ok(relative)
+    }
+
+    /// 目录中的每一项都要能修改：不能含有被排除的内容、`write off` 或限制了 IP 的子目录
+    fn check_subtree(&self, relative: &str) -> Result<(), Rejected> {
+        let full = self.root.join(relative);
+        if !fs::symlink_metadata(&full).is_ok_and(|m| m.is_dir()) {
+            return Ok(());
+        }
+        for entry in walkdir::WalkDir::new(&full).min_depth(1) {
+            let entry = entry
+                .map_err(|e| self.reject(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+            // 规则文件随目录一起移动或删除，其中的规则仍按下面的检查生效
+            if entry.file_name() == RULES_FILE {
+                continue;
+            }
+            let child = entry.path().strip_prefix(&full).unwrap().to_string_lossy();
+            let child = join(relative, &child.replace('\\', "/"));
+            let access = self.access.check(&child, entry.file_type().is_dir());
+            if access.denied {
+                return Err(self.reject(
+                    StatusCode::FORBIDDEN,
+                    tr!(self.lang, "fs.protected", path = display(relative)),
+                ));
+            }
+            if access
+                .ip_rules
+                .is_some_and(|rules| rules.check(self.client).is_err())
+            {
+                return Err(Rejected(
+                    StatusCode::FORBIDDEN,
+                    self.lang.t("http.forbidden").to_string(),
+                ));
+            }
+            if !access.writable {
+                return Err(self.reject(
+                    StatusCode::FORBIDDEN,
+                    tr!(self.lang, "fs.read_only", path = display(&child)),
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// 已存在的目录 `dir` 解析符号链接后仍在根目录之中，否则写入会落到根目录之外
+    fn check_inside(&self, dir: &str) -> Result<(), Rejected> {
+        let inside = match (fs::canonicalize(self.root), fs::canonicalize(self.root.join(dir))) {
+            (Ok(root), Ok(full)) => full.starts_with(root),
+            _ => false,
+        };
+        if !inside {
+            return Err(self.reject(
+                StatusCode::FORBIDDEN,
+                tr!(self.lang, "fs.outside", path = display(dir)),
+            ));
+        }
+        Ok(())
+    }
+
+    /// 目录 `dir` 中名为 `name` 的目标，按 `conflict` 处理已存在的目标，返回目标路径和是否需要先删除
+    ///
+    /// `planned` 为同一请求中已分配的目标，与其同名时同样按已存在处理；
+    /// 覆盖同一请求中刚放入的内容没有意义，此时即使指定了 `overwrite` 也返回 409。
+    fn target(
+        &self,
+        dir: &str,
+        name: &str,
+        is_dir: bool,
+        conflict: Conflict,
+        planned: &HashSet<String>,
+    ) -> Result<(String, bool), Rejected> {
+        // 名称不能包含路径分隔符，也不能被清理（隐藏文件、非法字符等）
+        if sanitize_file_name(name).as_deref() != Some(name) {
+            return Err(self.reject(
+                StatusCode::BAD_REQUEST,
+                tr!(self.lang, "fs.bad_name", name = name),
+            ));
+        }
+        let dir = self.resolve(dir)?;
+        let full_dir = self.root.join(&dir);
+        if !full_dir.is_dir() {
+            return Err(self.reject(
+                StatusCode::NOT_FOUND,
+                tr!(self.lang, "fs.not_dir", path = display(&dir)),
+            ));
+        }
+        self.check_inside(&dir)?;
+        let mut relative = join(&dir, name);
+        let full = full_dir.join(name);
+        // 与排除规则匹配的名称创建后会立即不可见
+        if self.access.check(&relative, is_dir).denied {
+            return Err(self.reject(
+                StatusCode::BAD_REQUEST,
+                tr!(self.lang, "fs.bad_name", name = name),
+            ));
+        }
+        let clashes = planned.contains(&relative);
+        if !clashes && fs::symlink_metadata(&full).is_err() {
+            return Ok((relative, false));
+        }
+        match conflict {
+            Conflict::Fail | Conflict::Overwrite if clashes => Err(self.reject(
+                StatusCode::CONFLICT,
+                tr!(self.lang, "fs.exists", path = display(&relative)),
+            )),
+            Conflict::Fail => Err(self.reject(
+                StatusCode::CONFLICT,
+                tr!(self.lang, "fs.exists", path = display(&relative)),
+            )),
+            Conflict::Overwrite => {
+                let relative = self.source(&relative)?;
+                Ok((relative, true))
+            }
+            Conflict::Rename => {
+                let unique = unique_path_by(&full_dir, name, |path| {
+                    let name = path.file_name().unwrap().to_string_lossy();
+                    fs::symlink_metadata(path).is_ok() || planned.contains(&join(&dir, &name))
+                });
+                let name = unique.file_name().unwrap().to_string_lossy();
+                relative = join(&dir, &name);
+                Ok((relative, false))
+            }
+        }
+    }
+
+    /// 上传的目标文件，已存在的同名目录不会被覆盖
+    fn upload_target(
+        &self,
+        dir: &str,
+        name: &str,
+        conflict: Conflict,
+    ) -> Result<(String, bool), Rejected> {
+        let (relative, replace) = self.target(dir, name, false, conflict, &HashSet::new())?;
+        if replace && self.root.join(&relative).is_dir() {
+            return Err(self.reject(
+                StatusCode::CONFLICT,
+                tr!(self.lang, "fs.exists", path = display(&relative)),
+            ));
+        }
+        Ok((relative, replace))
+    }
+
+    /// 不能把目录移动或复制到自身之中，覆盖时目标也不能是源路径本身或其上级目录
+    fn check_overlap(&self, from: &str, to: &str, replace: bool) -> Result<(), Rejected> {
+        let inside = |child: &str, parent: &str| child.starts_with(&format!("{}/", parent));
+        if inside(to, from) || (replace && (to == from || inside(from, to))) {
+            return Err(self.reject(
+                StatusCode::BAD_REQUEST,
+                tr!(self.lang, "fs.into_itself", path = display(from)),
+            ));
+        }
+        Ok(())
+    }
+
+    fn bad_path(&self) -> Rejected {
+        Rejected(
+            StatusCode::BAD_REQUEST,
+            self.lang.t("http.bad_path").to_string(),
+        )
+    }
+
+    fn not_found(&self, relative: &str) -> Rejected {
+        self.reject(
+            StatusCode::NOT_FOUND,
+            tr!(self.lang, "fs.not_found", path = display(relative)),
+        )
+    }
+
+    fn reject(&self, status: StatusCode, message: String) -> Rejected {
+        Rejected(status, format!("{} - {}", status.as_u16(), message))
+    }
+}
+
+/// 在后台线程中执行，直接返回结果或流式返回进度
+async fn run(
+    op: Op,
+    steps: Vec<Step>,
+    state: Arc<AppState>,
+    client: IpAddr,
+    stream: bool,
+    lang: Lang,
+) -> Response<Body> {
+    let progress = Arc::new(Progress::default());
+    let mut task = tokio::task::spawn_blocking({
+        let (state, progress) = (state.clone(), progress.clone());
+        move || execute(op, &steps, &state, client, &progress)
+    });
+    if !stream {
+        let outcome = Outcome::from(task.await);
+        if let Some(error) = outcome.error {
+            let message = tr!(lang, "fs.failed", error = error);
+            return text_response(
+                StatusCode::INTERNAL_SERVER_ERROR,
+                &format!("500 - {}", message),
+            );
+        }
+        return Response::builder()
+            .header("Content-Type", "application/json")
+            .body(Body::from(outcome.json(lang).to_string()))
+            .unwrap();
+    }
+
+    let (mut tx, body) = Body::channel();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(PROGRESS_INTERVAL);
+        // 客户端断开后操作继续执行，只是不再发送进度
+        let mut connected = true;
+        let result = loop {
+            tokio::select! {
+                result = &mut task => break result,
+                _ = ticker.tick(), if connected => {
+                    let line = serde_json::json!({
+                        "done": progress.done.load(Ordering::Relaxed),
+                        "total": progress.total.load(Ordering::Relaxed),
+                    });
+                    connected = tx.send_data(format!("{}\n", line).into()).await.is_ok();
+                }
+            }
+        };
+        let json = Outcome::from(result).json(lang);
+        let _ = tx.send_data(format!("{}\n", json).into()).await;
+    });
+    Response::builder()
+        .header("Content-Type", "application/x-ndjson")
+        .header("Cache-Control", "no-store")
+        .body(body)
+        .unwrap()
+}
+
+impl Outcome {
+    /// 后台任务异常退出时按出错处理
+    fn from(result: Result<Outcome, tokio::task::JoinError>) -> Outcome {
+        result.unwrap_or_else(|e| Outcome {
+            paths: Vec::new(),
+            error: Some(e.to_string()),
+        })
+    }
+
+    fn json(&self, lang: Lang) -> serde_json::Value {
+        match &self.error {
+            Some(error) => serde_json::json!({
+                "ok": false,
+                "paths": self.paths,
+                "error": tr!(lang, "fs.failed", error = error),
+            }),
+            None => serde_json::json!({ "ok": true, "paths": self.paths }),
+        }
+    }
+}
+
+/// 依次执行各步骤，遇到错误即停止；每一项修改都记入访问日志
+///
+/// 启用回收站时，删除和覆盖的文件移入回收站，日志中附带回收站中的 ID 以便恢复。
+fn execute(
+    op: Op,
+    steps: &[Step],
+    state: &AppState,
+    client: IpAddr,
+    progress: &Progress,
+) -> Outcome {
+    let root = &state.root_dir;
+    let trash = state.trash.as_ref();
+    let total: u64 = steps
+        .iter()
+        .map(|step| match (op, &step.from) {
+            (Op::Copy, Some(from)) => count(&root.join(from)),
+            (Op::Delete, Some(from)) if trash.is_none() => count(&root.join(from)),
+            _ => 1,
+        })
+        .sum();
+    progress.total.store(total, Ordering::Relaxed);
+
+    let audit = |line: String| log_request(&state.log_mode, state.logger.clone(), line);
+    let mut paths = Vec::new();
+    let mut trashed_any = false;
+    for step in steps {
+        let (from, to) = (
+            step.from.as_deref().map(display),
+            step.to.as_deref().map(display),
+        );
+        let description = match (&from, &to) {
+            (Some(from), Some(to)) => format!("{} -> {}", from, to),
+            (Some(path), None) | (None, Some(path)) => path.clone(),
+            (None, None) => String::new(),
+        };
+        match apply(op, step, root, trash, progress) {
+            Ok(trashed) => {
+                trashed_any |= trashed.is_some();
+                // 历史版本随文件转到新路径
+                if let (Op::Rename | Op::Move, Some(versions), Some(from), Some(to)) =
+                    (op, &state.versions, &step.from, &step.to)
+                {
+                    let _ = versions.rename(from, to);
+                }
+                let note = trashed
+                    .map(|item| format!(" (trash {})", item.id))
+                    .unwrap_or_default();
+                audit(format!(
+                    "FS {} {}{} [{}]",
+                    op.name(),
+                    description,
+                    note,
+                    client
+                ));
+                paths.extend(to.or(from));
+            }
+            Err(e) => {
+                audit(format!(
+                    "500 FS {} {} [{}] - {}",
+                    op.name(),
+                    description,
+                    client,
+                    e
+                ));
+                return Outcome {
+                    paths,
+                    error: Some(e.to_string()),
+                };
+            }
+        }
+    }
+    if trashed_any {
+        purge_trash(state);
+    }
+    Outcome { paths, error: None }
+}
+
+/// 回收站超过大小上限时立即清除最早删除的项
+fn purge_trash(state: &AppState) {
+    if let Some(trash) = &state.trash {
+        for item in trash.purge_expired().unwrap_or_default() {
+            log_request(
+                &state.log_mode,
+                state.logger.clone(),
+                format!("FS purge /{} ({}) [auto]", item.path, item.id),
+            );
+        }
+    }
+}
+
+/// 执行一个步骤，返回移入回收站的项（删除或被覆盖的目标）
+fn apply(
+    op: Op,
+    step: &Step,
+    root: &Path,
+    trash: Option<&Trash>,
+    progress: &Progress,
+) -> io::Result<Option<TrashItem>> {
+    // 删除或覆盖：有回收站时移入回收站，否则直接删除
+    let discard = |relative: &str, done: &AtomicU64| match trash {
+        Some(trash) => trash.put(root, relative).map(|item| {
+            done.fetch_add(1, Ordering::Relaxed);
+            Some(item)
+        }),
+        None => remove_tree(&root.join(relative), done).map(|_| None),
+    };
+    let mut trashed = None;
+    if let (true, Some(to)) = (step.replace, &step.to) {
+        trashed = discard(to, &AtomicU64::new(0))?;
+    }
+    let (from, to) = (
+        step.from.as_ref().map(|p| root.join(p)),
+        step.to.as_ref().map(|p| root.join(p)),
+    );
+    match (op, from, to) {
+        (Op::Mkdir, _, Some(to)) => {
+            fs::create_dir(to)?;
+            progress.done.fetch_add(1, Ordering::Relaxed);
+        }
+        (Op::Rename | Op::Move, Some(from), Some(to)) => match fs::rename(&from, &to) {
+            Ok(()) => {
+                progress.done.fetch_add(1, Ordering::Relaxed);
+            }
+            // 跨文件系统时改为复制后删除
+            Err(e) if e.kind() == io::ErrorKind::CrossesDevices => {
+                progress
+                    .total
+                    .fetch_add(count(&from) * 2 - 1, Ordering::Relaxed);
+                copy_tree(&from, &to, &progress.done)?;
+                remove_tree(&from, &progress.done)?;
+            }
+            Err(e) => return Err(e),
+        },
+        (Op::Copy, Some(from), Some(to)) => copy_tree(&from, &to, &progress.done)?,
+        (Op::Delete, _, _) => trashed = discard(step.from.as_deref().unwrap(), &progress.done)?,
+        _ => unreachable!(),
+    }
+    Ok(trashed)
+}
+
+/// 文件与目录总数（含自身，不跟随符号链接）
+fn count(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path).into_iter().count() as u64
+}
+
+/// 拆分为所在目录和名称
+fn split(relative: &str) -> (&str, &str) {
+    relative.rsplit_once('/').unwrap_or(("", relative))
+}
+
+fn join(dir: &str, name: &str) -> String {
+    if dir.is_empty() {
+        name.to_string()
+    } else {
+        format!("{}/{}", dir, name)
+    }
+}
+
+/// 返回给客户端和写入日志的路径，以 `/` 开头
+fn display(relative: &str) -> String {
+    format!("/{}", relative)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TestDir;
+
+    /// 根目录：`docs/a.txt`、`docs/locked/b.txt`（`write off`）、`docs/.env`、`lan/c.txt`（限制 IP）
+    fn fixture(name: &str) -> (TestDir, AccessRules) {
+        let root = TestDir::new(name);
+        fs::create_dir_all(root.join("docs/locked")).unwrap();
+        fs::create_dir_all(root.join("lan")).unwrap();
+        fs::write(root.join("docs/a.txt"), "a").unwrap();
+        fs::write(root.join("docs/locked/b.txt"), "b").unwrap();
+        fs::write(root.join("docs/locked").join(RULES_FILE), "write off\n").unwrap();
+        fs::write(root.join("lan/c.txt"), "c").unwrap();
+        fs::write(root.join("lan").join(RULES_FILE), "allow-ip 192.168.1.0/24\n").unwrap();
+        let access = AccessRules::new(&root, &[], false, false, Lang::En).unwrap();
+        (root, access)
+    }
+
+    fn plan(root: &Path, access: &AccessRules, op: &str, request: &str) -> Result<Vec<Step>, Rejected> {
+        let planner = Planner {
+            root,
+            access,
+            client: "127.0.0.1".parse().unwrap(),
+            lang: Lang::En,
+        };
+        planner.plan(Op::parse(op).unwrap(), serde_json::from_str(request).unwrap())
+    }
+
+    fn status_of(result: Result<Vec<Step>, Rejected>) -> StatusCode {
+        match result {
+            Ok(_) => StatusCode::OK,
+            Err(Rejected(status, _)) => status,
+        }
+    }
+
+    #[test]
+    fn nested_write_off_protects_parent() {
+        let (root, access) = fixture("fsapi_nested_write_off");
+        let delete = |path: &str| plan(&root, &access, "delete", &format!(r#"{{"paths": ["{}"]}}"#, path));
+
+        assert_eq!(status_of(delete("/docs/a.txt")), StatusCode::OK);
+        assert_eq!(status_of(delete("/docs/locked/b.txt")), StatusCode::FORBIDDEN);
+        assert_eq!(status_of(delete("/docs/locked")), StatusCode::FORBIDDEN);
+        // 上级目录中含有只读的子目录时也不能整体删除或移动
+        let Err(Rejected(status, message)) = delete("/docs") else {
+            panic!("deleting /docs should be refused");
+        };
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(message.contains("/docs/locked"), "{}", message);
+        let moved = plan(&root, &access, "move", r#"{"paths": ["/docs"], "to": "/lan"}"#);
+        assert_eq!(status_of(moved), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn excluded_children_protect_parent() {
+        let (root, access) = fixture("fsapi_excluded_children");
+        fs::remove_file(root.join("docs/locked").join(RULES_FILE)).unwrap();
+        let delete = || plan(&root, &access, "delete", r#"{"path": "/docs"}"#);
+        assert_eq!(status_of(delete()), StatusCode::OK);
+
+        fs::write(root.join("docs/.env"), "SECRET=1").unwrap();
+        let Err(Rejected(status, message)) = delete() else {
+            panic!("deleting a directory with hidden files should be refused");
+        };
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(!message.contains(".env"), "{}", message);
+        // 隐藏文件本身按不存在处理
+        let direct = plan(&root, &access, "delete", r#"{"path": "/docs/.env"}"#);
+        assert_eq!(status_of(direct), StatusCode::NOT_FOUND);
+    }
+
+    #[test]
+    fn ip_restricted_subtrees_are_checked() {
+        let (root, access) = fixture("fsapi_ip_rules");
+        let request = r#"{"paths": ["/lan/c.txt"], "to": "/docs"}"#;
+        assert_eq!(status_of(plan(&root, &access, "copy", request)), StatusCode::FORBIDDEN);
+
+        fs::create_dir(root.join("outer")).unwrap();
+        fs::rename(root.join("lan"), root.join("outer/lan")).unwrap();
+        let request = r#"{"path": "/outer"}"#;
+        assert_eq!(status_of(plan(&root, &access, "delete", request)), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn overwritten_directories_are_checked() {
+        let (root, access) = fixture("fsapi_overwrite");
+        fs::create_dir_all(root.join("other/docs")).unwrap();
+        let request = r#"{"paths": ["/other/docs"], "to": "/", "conflict": "overwrite"}"#;
+        assert_eq!(status_of(plan(&root, &access, "move", request)), StatusCode::FORBIDDEN);
+        let request = r#"{"paths": ["/other/docs"], "to": "/", "conflict": "rename"}"#;
+        assert_eq!(status_of(plan(&root, &access, "move", request)), StatusCode::OK);
+    }
+
+    #[test]
+    fn root_and_missing_paths_are_refused() {
+        let (root, access) = fixture("fsapi_root");
+        assert_eq!(status_of(plan(&root, &access, "delete", r#"{"path": "/"}"#)), StatusCode::FORBIDDEN);
+        assert_eq!(status_of(plan(&root, &access, "delete", r#"{"paths": []}"#)), StatusCode::BAD_REQUEST);
+        assert_eq!(status_of(plan(&root, &access, "delete", r#"{"path": "/nope"}"#)), StatusCode::NOT_FOUND);
+        assert_eq!(status_of(plan(&root, &access, "delete", r#"{"path": "/../x"}"#)), StatusCode::BAD_REQUEST);
+        let request = r#"{"paths": ["/docs"], "to": "/docs/locked"}"#;
+        assert_eq!(status_of(plan(&root, &access, "copy", request)), StatusCode::FORBIDDEN);
+    }
+
+    #[test]
+    fn same_named_sources_do_not_share_a_target() {
+        let (root, access) = fixture("fsapi_same_name");
+        fs::create_dir_all(root.join("other")).unwrap();
+        fs::create_dir_all(root.join("dst")).unwrap();
+        fs::write(root.join("other/a.txt"), "other").unwrap();
+        let request = |conflict: &str| {
+            format!(
+                r#"{{"paths": ["/docs/a.txt", "/other/a.txt"], "to": "/dst", "conflict": "{}"}}"#,
+                conflict
+            )
+        };
+
+        assert_eq!(status_of(plan(&root, &access, "move", &request("fail"))), StatusCode::CONFLICT);
+        assert_eq!(status_of(plan(&root, &access, "copy", &request("overwrite"))), StatusCode::CONFLICT);
+        let steps = plan(&root, &access, "move", &request("rename")).unwrap();
+        let targets: Vec<_> = steps.iter().map(|step| step.to.as_deref().unwrap()).collect();
+        assert_eq!(targets, ["dst/a.txt", "dst/a (1).txt"]);
+
+        // 已存在的同名文件和同一请求中的目标一起避开
+        fs::write(root.join("dst/a.txt"), "old").unwrap();
+        let steps = plan(&root, &access, "copy", &request("rename")).unwrap();
+        let targets: Vec<_> = steps.iter().map(|step| step.to.as_deref().unwrap()).collect();
+        assert_eq!(targets, ["dst/a (1).txt", "dst/a (2).txt"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn symlinks_cannot_lead_outside_the_root() {
+        let (root, access) = fixture("fsapi_symlink");
+        let outside = TestDir::new("fsapi_symlink_outside");
+        fs::write(outside.join("x.txt"), "x").unwrap();
+        std::os::unix::fs::symlink(&*outside, root.join("link")).unwrap();
+        let status = |op: &str, request: &str| status_of(plan(&root, &access, op, request));
+
+        assert_eq!(status("mkdir", r#"{"path": "/link/new"}"#), StatusCode::FORBIDDEN);
+        assert_eq!(status("copy", r#"{"paths": ["/docs/a.txt"], "to": "/link"}"#), StatusCode::FORBIDDEN);
+        assert_eq!(status("delete", r#"{"path": "/link/x.txt"}"#), StatusCode::FORBIDDEN);
+        assert_eq!(status("rename", r#"{"path": "/link/x.txt", "name": "y.txt"}"#), StatusCode::FORBIDDEN);
+        // 链接本身仍可以修改
+        assert_eq!(status("rename", r#"{"path": "/link", "name": "moved"}"#), StatusCode::OK);
+        assert_eq!(status("delete", r#"{"path": "/link"}"#), StatusCode::OK);
+    }
+}
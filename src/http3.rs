@@ -0,0 +1,151 @@
+use h3::{error::Code, server::RequestStream};
+use hyper::body::{Buf, Bytes, HttpBody};
+use hyper::{Body, Method, Request, Version};
+use quinn::{crypto::rustls::QuicServerConfig, Endpoint, IdleTimeout, TransportConfig};
+use std::{net::SocketAddr, sync::Arc, time::Duration};
+
+use crate::limit::ConnectionBudget;
+use crate::listener::ConnectionCaps;
+use crate::tls::{TlsIdentity, QUIC_ALPN};
+use crate::AppState;
+
+/// HTTP/3 不允许出现的逐跳头
+const HOP_HEADERS: &[&str] = &["connection", "keep-alive", "transfer-encoding", "upgrade"];
+
+/// 在每个 TCP 监听地址的同一 UDP 端口上绑定 QUIC 端点
+///
+/// `idle_timeout` 为 0 时连接不会因空闲而关闭。
+pub fn bind(
+    addrs: &[SocketAddr],
+    identity: &TlsIdentity,
+    idle_timeout: Duration,
+) -> Result<Vec<Endpoint>, String> {
+    let crypto = QuicServerConfig::try_from(identity.server_config(QUIC_ALPN)?)
+        .map_err(|e| e.to_string())?;
+    let mut transport = TransportConfig::default();
+    transport.max_idle_timeout(
+        Some(idle_timeout)
+            .filter(|t| !t.is_zero())
+            .and_then(|t| IdleTimeout::try_from(t).ok()),
+    );
+    let mut config = quinn::ServerConfig::with_crypto(Arc::new(crypto));
+    config.transport_config(Arc::new(transport));
+    addrs
+        .iter()
+        .map(|addr| Endpoint::server(config.clone(), *addr).map_err(|e| format!("{}: {}", addr, e)))
+        .collect()
+}
+
+/// 接受 QUIC 连接并处理其中的 HTTP/3 请求，直到端点关闭
+///
+/// 每个连接与 TCP 连接一样占用 `caps` 中的名额：超过单 IP 上限时拒绝，
+/// 总数达到上限时等待已有连接关闭后再完成握手。
+pub async fn serve(endpoint: Endpoint, state: Arc<AppState>, caps: ConnectionCaps) {
+    while let Some(incoming) = endpoint.accept().await {
+        let (state, caps) = (state.clone(), caps.clone());
+        tokio::spawn(async move {
+            let remote = incoming.remote_address();
+            // 名额随任务一直持有到连接结束
+            let Some(_slot) = caps.acquire(remote).await else {
+                incoming.refuse();
+                return;
+            };
+            let Ok(conn) = incoming.await else {
+                return;
+            };
+            let Ok(mut conn) =
+                h3::server::Connection::<_, Bytes>::new(h3_quinn::Connection::new(conn)).await
+            else {
+                return;
+            };
+            let _connection = state.stats.connection();
+            let entry = state.monitor.connection(remote, "h3");
+            // 同一连接上的请求共享连接带宽预算，与 TCP 连接一致
+            let budget = state.limits.connection();
+            while let Ok(Some(resolver)) = conn.accept().await {
+                entry.request();
+                let state = state.clone();
+                let budget = budget.clone();
+                tokio::spawn(async move {
+                    if let Ok((req, stream)) = resolver.resolve_request().await {
+                        let _ = respond(req, stream, remote, budget, state).await;
+                    }
+                });
+            }
+        });
+    }
+}
+
+/// 把 HTTP/3 请求转换为 hyper 请求，交给与 TCP 相同的处理流程，再把响应写回流中
+async fn respond(
+    req: http::Request<()>,
+    stream: RequestStream<h3_quinn::BidiStream<Bytes>, Bytes>,
+    remote: SocketAddr,
+    budget: ConnectionBudget,
+    state: Arc<AppState>,
+) -> Result<(), h3::error::StreamError> {
+    let (mut send, mut recv) = stream.split();
+
+    // 请求体在后台转发，流出错时中止 Body，避免把不完整的上传当作完整内容
+    let (mut tx, body) = Body::channel();
+    tokio::spawn(async move {
+        loop {
+            match recv.recv_data().await {
+                Ok(Some(mut chunk)) => {
+                    if tx
+                        .send_data(chunk.copy_to_bytes(chunk.remaining()))
+                        .await
+                        .is_err()
+                    {
+                        return;
+                    }
+                }
+                Ok(None) => return,
+                Err(_) => {
+                    tx.abort();
+                    return;
+                }
+            }
+        }
+    });
+
+    let (parts, ()) = req.into_parts();
+    let mut builder = Request::builder()
+        .method(parts.method.as_str())
+        .uri(parts.uri.to_string())
+        .version(Version::HTTP_3);
+    for (name, value) in &parts.headers {
+        builder = builder.header(name.as_str(), value.as_bytes());
+    }
+    let Ok(req) = builder.body(body) else {
+        let response = http::Response::builder().status(400).body(()).unwrap();
+        send.send_response(response).await?;
+        return send.finish().await;
+    };
+    let is_head = req.method() == Method::HEAD;
+
+    let (parts, mut body) = crate::serve_request(req, remote, budget, state)
+        .await
+        .into_parts();
+    let mut head = http::Response::builder().status(parts.status.as_u16());
+    for (name, value) in parts
+        .headers
+        .iter()
+        .filter(|(name, _)| !HOP_HEADERS.contains(&name.as_str()))
+    {
+        head = head.header(name.as_str(), value.as_bytes());
+    }
+    send.send_response(head.body(()).unwrap()).await?;
+    if !is_head {
+        while let Some(chunk) = body.data().await {
+            match chunk {
+                Ok(bytes) => send.send_data(bytes).await?,
+                Err(_) => {
+                    send.stop_stream(Code::H3_INTERNAL_ERROR);
+                    return Ok(());
+                }
+            }
+        }
+    }
+    send.finish().await
+}
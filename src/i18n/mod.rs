@@ -0,0 +1,182 @@
+use hyper::{header, Body, Request};
+use serde_json::Value;
+use std::{collections::HashMap, fmt, str::FromStr, sync::LazyLock};
+
+/// 内置消息目录，嵌套的 JSON 对象展开为 `section.key` 形式的键
+const CATALOGS: [(Lang, &str); 2] = [
+    (Lang::ZhCn, include_str!("zh-CN.json")),
+    (Lang::En, include_str!("en.json")),
+];
+
+static MESSAGES: LazyLock<HashMap<Lang, HashMap<String, String>>> = LazyLock::new(|| {
+    CATALOGS
+        .iter()
+        .map(|(lang, source)| {
+            let value: Value = serde_json::from_str(source).expect("消息目录格式错误");
+            let mut messages = HashMap::new();
+            flatten("", &value, &mut messages);
+            (*lang, messages)
+        })
+        .collect()
+});
+
+fn flatten(prefix: &str, value: &Value, out: &mut HashMap<String, String>) {
+    match value {
+        Value::Object(map) => {
+            for (key, value) in map {
+                let key = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                flatten(&key, value, out);
+            }
+        }
+        Value::String(s) => {
+            out.insert(prefix.to_string(), s.clone());
+        }
+        other => {
+            out.insert(prefix.to_string(), other.to_string());
+        }
+    }
+}
+
+/// 界面语言
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Lang {
+    ZhCn,
+    En,
+}
+
+impl Lang {
+    /// BCP 47 语言标签，用于 HTML 的 `lang` 属性和 `Content-Language`
+    pub fn code(self) -> &'static str {
+        match self {
+            Lang::ZhCn => "zh-CN",
+            Lang::En => "en",
+        }
+    }
+
+    /// 解析语言标签或 locale 名称，例如 `zh-CN`、`zh_CN.UTF-8`、`en-US`
+    pub fn parse(tag: &str) -> Option<Lang> {
+        let tag = tag.trim().to_ascii_lowercase();
+        let tag = tag.split('.').next().unwrap_or_default();
+        let primary = tag.split(['-', '_']).next().unwrap_or_default();
+        match primary {
+            "zh" => Some(Lang::ZhCn),
+            "en" => Some(Lang::En),
+            _ => None,
+        }
+    }
+
+    /// 根据 `LC_ALL` / `LC_MESSAGES` / `LANG` 环境变量确定默认语言
+    ///
+    /// 未设置或为 `C` / `POSIX` 时使用中文；设置为尚未支持的语言时使用英文。
+    pub fn from_env() -> Lang {
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty());
+        match locale {
+            None => Lang::ZhCn,
+            Some(locale) if locale == "C" || locale.starts_with("C.") || locale == "POSIX" => {
+                Lang::ZhCn
+            }
+            Some(locale) => Lang::parse(&locale).unwrap_or(Lang::En),
+        }
+    }
+
+    /// 按 `Accept-Language` 请求头协商语言，没有支持的语言时使用 `fallback`
+    pub fn negotiate(accept_language: Option<&str>, fallback: Lang) -> Lang {
+        let Some(header) = accept_language else {
+            return fallback;
+        };
+        let mut candidates: Vec<(f32, Lang)> = header
+            .split(',')
+            .filter_map(|part| {
+                let mut params = part.split(';');
+                let lang = Lang::parse(params.next()?)?;
+                let q = params
+                    .filter_map(|p| p.trim().strip_prefix("q="))
+                    .find_map(|q| q.trim().parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                (q > 0.0).then_some((q, lang))
+            })
+            .collect();
+        // 稳定排序，相同权重时保持请求头中的顺序
+        candidates.sort_by(|a, b| b.0.total_cmp(&a.0));
+        candidates.first().map(|(_, lang)| *lang).unwrap_or(fallback)
+    }
+
+    /// 请求应使用的语言
+    pub fn for_request(req: &Request<Body>, fallback: Lang) -> Lang {
+        let header = req
+            .headers()
+            .get(header::ACCEPT_LANGUAGE)
+            .and_then(|v| v.to_str().ok());
+        Lang::negotiate(header, fallback)
+    }
+
+    /// 查找消息，当前语言缺失时回退到中文目录，仍缺失时返回键本身
+    pub fn t(self, key: &str) -> &str {
+        [self, Lang::ZhCn]
+            .iter()
+            .find_map(|lang| MESSAGES.get(lang).and_then(|m| m.get(key)))
+            .map(|s| s.as_str())
+            .unwrap_or(key)
+    }
+}
+
+impl FromStr for Lang {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Lang::parse(s).ok_or_else(|| format!("unsupported language: {} (zh-CN, en)", s))
+    }
+}
+
+impl fmt::Display for Lang {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.code())
+    }
+}
+
+/// 将消息中的 `{name}` 占位符替换为对应参数
+pub fn format(template: &str, args: &[(&str, &dyn fmt::Display)]) -> String {
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        match after.find('}') {
+            Some(end) => {
+                let name = &after[..end];
+                match args.iter().find(|(n, _)| *n == name) {
+                    Some((_, value)) => out.push_str(&value.to_string()),
+                    None => out.push_str(&rest[start..start + end + 2]),
+                }
+                rest = &after[end + 1..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+/// 取出翻译后的消息：`tr!(lang, "banner.started")`、`tr!(lang, "banner.port", port = 8080)`
+#[macro_export]
+macro_rules! tr {
+    ($lang:expr, $key:expr) => {
+        $lang.t($key).to_string()
+    };
+    ($lang:expr, $key:expr, $($name:ident = $value:expr),+ $(,)?) => {
+        $crate::i18n::format(
+            $lang.t($key),
+            &[$((stringify!($name), &$value as &dyn std::fmt::Display)),+],
+        )
+    };
+}
@@ -0,0 +1,117 @@
+use hyper::{header, Body, Request};
+use ipnet::IpNet;
+use std::net::{IpAddr, SocketAddr};
+
+use crate::listener::UNIX_PEER;
+
+/// 解析 CIDR，例如 `192.168.0.0/16`、`fd00::/8`；单个 IP 视为 /32 或 /128
+pub fn parse_net(s: &str) -> Result<IpNet, String> {
+    let s = s.trim();
+    if s.contains('/') {
+        s.parse::<IpNet>()
+            .map(|net| net.trunc())
+            .map_err(|_| format!("invalid CIDR: {}", s))
+    } else {
+        s.parse::<IpAddr>()
+            .map(IpNet::from)
+            .map_err(|_| format!("invalid IP address: {}", s))
+    }
+}
+
+/// 一组 IP 允许 / 拒绝规则
+#[derive(Debug, Clone, Default)]
+pub struct IpRules {
+    pub allow: Vec<IpNet>,
+    pub deny: Vec<IpNet>,
+}
+
+/// 请求被 IP 规则拒绝的原因
+pub enum Rejection {
+    /// 匹配了拒绝规则
+    Denied(IpNet),
+    /// 设置了允许列表但地址不在其中
+    NotAllowed,
+}
+
+impl IpRules {
+    /// 拒绝规则优先；允许列表非空时只放行其中的地址
+    ///
+    /// Unix 套接字连接没有 IP 地址，由套接字文件权限控制访问，不受 IP 规则限制。
+    pub fn check(&self, ip: IpAddr) -> Result<(), Rejection> {
+        if ip == UNIX_PEER.ip() {
+            return Ok(());
+        }
+        let ip = ip.to_canonical();
+        if let Some(net) = self.deny.iter().find(|net| net.contains(&ip)) {
+            return Err(Rejection::Denied(*net));
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|net| net.contains(&ip)) {
+            return Err(Rejection::NotAllowed);
+        }
+        Ok(())
+    }
+}
+
+/// 根据受信任的代理解析真实的客户端地址
+///
+/// 只有直接连接的对端属于 `trusted` 时才读取 `Forwarded`（优先）或 `X-Forwarded-For`，
+/// 从右向左跳过受信任的代理，取第一个不受信任的地址。无法解析的条目之后的内容一律不信任。
+pub fn client_addr(req: &Request<Body>, remote: SocketAddr, trusted: &[IpNet]) -> SocketAddr {
+    let is_trusted = |ip: IpAddr| trusted.iter().any(|net| net.contains(&ip.to_canonical()));
+    if !is_trusted(remote.ip()) {
+        return remote;
+    }
+    let mut client = remote.ip();
+    for hop in forwarded_chain(req).into_iter().rev() {
+        let Some(ip) = hop else {
+            break;
+        };
+        client = ip;
+        if !is_trusted(ip) {
+            break;
+        }
+    }
+    SocketAddr::new(client, remote.port())
+}
+
+/// 转发链中的各个地址，从最初的客户端到最近的代理
+fn forwarded_chain(req: &Request<Body>) -> Vec<Option<IpAddr>> {
+    let values = |name| {
+        req.headers()
+            .get_all(name)
+            .iter()
+            .filter_map(|v| v.to_str().ok())
+            .flat_map(|v| v.split(','))
+            .map(str::trim)
+            .collect::<Vec<_>>()
+    };
+    let forwarded = values(header::FORWARDED);
+    if !forwarded.is_empty() {
+        return forwarded
+            .into_iter()
+            .map(|element| {
+                element
+                    .split(';')
+                    .filter_map(|pair| pair.trim().split_once('='))
+                    .find(|(name, _)| name.eq_ignore_ascii_case("for"))
+                    .and_then(|(_, value)| parse_node(value))
+            })
+            .collect();
+    }
+    values(header::HeaderName::from_static("x-forwarded-for"))
+        .into_iter()
+        .map(parse_node)
+        .collect()
+}
+
+/// 解析转发头中的节点：`1.2.3.4`、`1.2.3.4:80`、`"[2001:db8::1]:80"`、`2001:db8::1`
+fn parse_node(value: &str) -> Option<IpAddr> {
+    let value = value.trim().trim_matches('"');
+    if let Some(rest) = value.strip_prefix('[') {
+        return rest.split_once(']')?.0.parse().ok();
+    }
+    value
+        .parse()
+        .ok()
+        .or_else(|| value.parse::<SocketAddr>().ok().map(|addr| addr.ip()))
+}
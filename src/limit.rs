@@ -0,0 +1,292 @@
+use futures_util::StreamExt;
+use hyper::{header, Body, Request, Response, StatusCode};
+use std::{
+    collections::HashMap,
+    net::IpAddr,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::fs::File;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio_util::io::ReaderStream;
+
+/// 下载名额用尽时建议客户端等待的秒数
+pub const BUSY_RETRY_AFTER: u64 = 5;
+
+/// 超过该数量的客户端记录时提前清理已回满的令牌桶
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+/// 定期清理已回满的令牌桶的间隔
+const CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+/// 令牌不足时最多等待的时间，速率极低时按此计算
+const MAX_WAIT: Duration = Duration::from_secs(3600);
+
+/// 令牌桶
+///
+/// 令牌以 `rate` 每秒的速度补充，最多积累 `capacity` 个。
+struct TokenBucket {
+    capacity: f64,
+    rate: f64,
+    tokens: f64,
+    last: Instant,
+}
+
+impl TokenBucket {
+    fn new(rate: f64, capacity: f64) -> Self {
+        TokenBucket {
+            capacity,
+            rate,
+            tokens: capacity,
+            last: Instant::now(),
+        }
+    }
+
+    fn refill(&mut self) {
+        let now = Instant::now();
+        let elapsed = now.duration_since(self.last).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * self.rate).min(self.capacity);
+        self.last = now;
+    }
+
+    /// 取出一个令牌，不足时返回需要等待的时间
+    fn try_take(&mut self) -> Result<(), Duration> {
+        self.refill();
+        if self.tokens >= 1.0 {
+            self.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(self.wait(1.0 - self.tokens))
+        }
+    }
+
+    /// 预支 `amount` 个令牌（允许透支），返回发送前需要等待的时间
+    fn reserve(&mut self, amount: f64) -> Duration {
+        self.refill();
+        self.tokens -= amount;
+        if self.tokens >= 0.0 {
+            Duration::ZERO
+        } else {
+            self.wait(-self.tokens)
+        }
+    }
+
+    /// 补充 `missing` 个令牌所需的时间，不超过 `MAX_WAIT`（速率极低时结果可能无法表示）
+    fn wait(&self, missing: f64) -> Duration {
+        Duration::try_from_secs_f64(missing / self.rate)
+            .map_or(MAX_WAIT, |wait| wait.min(MAX_WAIT))
+    }
+
+    fn is_full(&mut self) -> bool {
+        self.refill();
+        self.tokens >= self.capacity
+    }
+}
+
+/// 请求频率、带宽和并发下载限制
+pub struct Limits {
+    /// 每个客户端 IP 的请求令牌桶：(每秒请求数, 突发上限)
+    rate: Option<(f64, f64)>,
+    clients: Mutex<Clients>,
+    /// 所有下载共享的带宽（字节/秒）
+    bandwidth: Option<Arc<Mutex<TokenBucket>>>,
+    /// 每个连接的带宽（字节/秒）
+    conn_bandwidth: Option<u64>,
+    downloads: Option<Arc<Semaphore>>,
+}
+
+/// 各客户端 IP 的请求令牌桶
+struct Clients {
+    buckets: HashMap<IpAddr, TokenBucket>,
+    next_cleanup: Instant,
+    /// 记录数超过该值时提前清理，之后按剩余数量加倍，使每次请求分摊的清理开销为常数
+    cleanup_at: usize,
+}
+
+impl Clients {
+    fn new() -> Self {
+        Clients {
+            buckets: HashMap::new(),
+            next_cleanup: Instant::now() + CLEANUP_INTERVAL,
+            cleanup_at: MAX_TRACKED_CLIENTS,
+        }
+    }
+
+    /// 到了清理时间或记录过多时，移除已回满（近期没有请求）的令牌桶
+    fn cleanup(&mut self) {
+        let now = Instant::now();
+        if now < self.next_cleanup && self.buckets.len() <= self.cleanup_at {
+            return;
+        }
+        self.buckets.retain(|_, bucket| !bucket.is_full());
+        self.next_cleanup = now + CLEANUP_INTERVAL;
+        self.cleanup_at = MAX_TRACKED_CLIENTS.max(self.buckets.len() * 2);
+    }
+}
+
+/// 单个连接的带宽预算，通过请求扩展传给文件传输
+#[derive(Clone)]
+pub struct ConnectionBudget(Option<Arc<Mutex<TokenBucket>>>);
+
+/// 占用的下载名额，响应体发送完毕或连接断开时释放
+pub struct DownloadSlot(Option<OwnedSemaphorePermit>);
+
+impl Limits {
+    pub fn new(
+        rate: Option<f64>,
+        burst: Option<u32>,
+        bandwidth: Option<u64>,
+        conn_bandwidth: Option<u64>,
+        max_downloads: Option<usize>,
+    ) -> Self {
+        Limits {
+            rate: rate
+                .filter(|r| *r > 0.0)
+                .map(|r| (r, burst.map(f64::from).unwrap_or(r).max(1.0))),
+            clients: Mutex::new(Clients::new()),
+            bandwidth: bandwidth
+                .filter(|b| *b > 0)
+                .map(|b| Arc::new(Mutex::new(TokenBucket::new(b as f64, b as f64)))),
+            conn_bandwidth: conn_bandwidth.filter(|b| *b > 0),
+            downloads: max_downloads.map(|n| Arc::new(Semaphore::new(n))),
+        }
+    }
+
+    /// 记录客户端的一次请求，超过频率限制时返回建议等待的时间
+    ///
+    /// IPv4 映射的 IPv6 地址与对应的 IPv4 地址共用同一个令牌桶。
+    pub fn check_rate(&self, ip: IpAddr) -> Result<(), Duration> {
+        let Some((rate, burst)) = self.rate else {
+            return Ok(());
+        };
+        let mut clients = self.clients.lock().unwrap();
+        clients.cleanup();
+        clients
+            .buckets
+            .entry(ip.to_canonical())
+            .or_insert_with(|| TokenBucket::new(rate, burst))
+            .try_take()
+    }
+
+    /// 为新连接创建带宽预算
+    pub fn connection(&self) -> ConnectionBudget {
+        ConnectionBudget(
+            self.conn_bandwidth
+                .map(|b| Arc::new(Mutex::new(TokenBucket::new(b as f64, b as f64)))),
+        )
+    }
+
+    /// 申请一个下载名额，名额用尽时返回 None
+    pub fn download_slot(&self) -> Option<DownloadSlot> {
+        match &self.downloads {
+            None => Some(DownloadSlot(None)),
+            Some(semaphore) => semaphore.clone().try_acquire_owned().ok().map(|p| DownloadSlot(Some(p))),
+        }
+    }
+
+    /// 文件响应体，按全局和连接带宽限速，并在发送期间占用下载名额
+    pub fn file_body(&self, file: File, req: &Request<Body>, slot: DownloadSlot) -> Body {
+        let buckets: Vec<_> = self
+            .bandwidth
+            .iter()
+            .chain(req.extensions().get::<ConnectionBudget>().and_then(|c| c.0.as_ref()))
+            .cloned()
+            .collect();
+        let stream = ReaderStream::new(file);
+        if buckets.is_empty() && slot.0.is_none() {
+            return Body::wrap_stream(stream);
+        }
+        let stream = stream.then(move |chunk| {
+            let _slot = &slot;
+            let wait = match &chunk {
+                Ok(bytes) => buckets
+                    .iter()
+                    .map(|bucket| bucket.lock().unwrap().reserve(bytes.len() as f64))
+                    .max()
+                    .unwrap_or_default(),
+                Err(_) => Duration::ZERO,
+            };
+            async move {
+                if !wait.is_zero() {
+                    tokio::time::sleep(wait).await;
+                }
+                chunk
+            }
+        });
+        Body::wrap_stream(stream)
+    }
+}
+
+/// `Retry-After` 使用的整秒数（至少 1 秒）
+pub fn retry_after_secs(wait: Duration) -> u64 {
+    wait.as_secs_f64().ceil().max(1.0) as u64
+}
+
+/// 429 响应
+pub fn too_many_requests(retry_after: u64, message: &str) -> Response<Body> {
+    Response::builder()
+        .status(StatusCode::TOO_MANY_REQUESTS)
+        .header("Content-Type", "text/plain; charset=utf-8")
+        .header(header::RETRY_AFTER, retry_after)
+        .body(Body::from(message.to_string()))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limits(rate: f64, burst: u32) -> Limits {
+        Limits::new(Some(rate), Some(burst), None, None, None)
+    }
+
+    #[test]
+    fn mapped_addresses_share_a_bucket() {
+        let limits = limits(0.001, 1);
+        let v4: IpAddr = "192.0.2.1".parse().unwrap();
+        let mapped: IpAddr = "::ffff:192.0.2.1".parse().unwrap();
+        assert!(limits.check_rate(mapped).is_ok());
+        assert!(limits.check_rate(v4).is_err());
+        assert!(limits.check_rate("192.0.2.2".parse().unwrap()).is_ok());
+        assert_eq!(limits.clients.lock().unwrap().buckets.len(), 2);
+    }
+
+    /// 从 `count` 个不同的 IP 各发一次请求
+    fn flood(limits: &Limits, count: usize) {
+        for i in 0..count as u32 {
+            let _ = limits.check_rate(IpAddr::from(i.to_be_bytes()));
+        }
+    }
+
+    #[test]
+    fn refilled_buckets_are_removed() {
+        // 令牌补充极快，每个桶在下次请求时都已回满
+        let limits = limits(1e12, 1);
+        flood(&limits, MAX_TRACKED_CLIENTS + 10);
+        let clients = limits.clients.lock().unwrap();
+        assert!(clients.buckets.len() <= MAX_TRACKED_CLIENTS);
+        assert_eq!(clients.cleanup_at, MAX_TRACKED_CLIENTS);
+    }
+
+    #[test]
+    fn cleanup_threshold_grows_with_active_clients() {
+        // 没有回满的令牌桶时，清理后阈值随记录数加倍，不会每次请求都遍历所有记录
+        let limits = limits(0.001, 1);
+        flood(&limits, MAX_TRACKED_CLIENTS + 10);
+        let clients = limits.clients.lock().unwrap();
+        assert_eq!(clients.buckets.len(), MAX_TRACKED_CLIENTS + 10);
+        assert_eq!(clients.cleanup_at, (MAX_TRACKED_CLIENTS + 1) * 2);
+    }
+
+    #[test]
+    fn tiny_rates_wait_at_most_max_wait() {
+        let limits = limits(1e-300, 1);
+        let ip: IpAddr = "192.0.2.1".parse().unwrap();
+        assert!(limits.check_rate(ip).is_ok());
+        assert_eq!(limits.check_rate(ip), Err(MAX_WAIT));
+
+        let mut bucket = TokenBucket::new(1e-300, 0.0);
+        assert_eq!(bucket.reserve(1.0), MAX_WAIT);
+        let mut bucket = TokenBucket::new(2.0, 0.0);
+        let wait = bucket.reserve(1.0);
+        assert!(wait > Duration::from_millis(450) && wait <= Duration::from_millis(500));
+    }
+}
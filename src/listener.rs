@@ -0,0 +1,903 @@
+use hyper::server::{accept::Accept, Builder};
+use hyper::Server;
+use socket2::{Domain, Protocol, Socket, Type};
+use std::{
+    collections::HashMap,
+    fmt,
+    future::Future,
+    io,
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Duration,
+};
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf};
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::{rustls::ServerConfig, server::TlsStream, TlsAcceptor};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::{Instant, Sleep};
+use tokio_util::sync::PollSemaphore;
+#[cfg(unix)]
+use tokio::net::{UnixListener, UnixStream};
+
+use crate::i18n::Lang;
+use crate::tr;
+use crate::util::{parse_duration, parse_size};
+
+/// 自动选择端口时最多尝试的端口数
+const MAX_PORT_ATTEMPTS: u16 = 20;
+
+/// Unix 套接字连接没有 IP 地址，以未指定地址表示（不会被当作本机访问）
+pub const UNIX_PEER: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 0);
+
+/// `--bind` 中的一个监听地址
+#[derive(Debug, Clone, PartialEq)]
+pub enum BindSpec {
+    /// 只给出 IP，端口从 `--port` 开始自动选择
+    Ip(IpAddr),
+    /// 给出完整地址，端口固定（0 表示由系统分配临时端口）
+    Addr(SocketAddr),
+    /// Unix 域套接字路径，写作 `unix:/path/to.sock`
+    Unix(std::path::PathBuf),
+}
+
+impl std::str::FromStr for BindSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(path) = s.strip_prefix("unix:") {
+            return Ok(BindSpec::Unix(path.into()));
+        }
+        if let Ok(addr) = s.parse::<SocketAddr>() {
+            return Ok(BindSpec::Addr(addr));
+        }
+        s.trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .map(BindSpec::Ip)
+            .map_err(|_| format!("invalid bind address: {}", s))
+    }
+}
+
+/// 已绑定的监听器，套接字从绑定起一直保留到服务结束，不存在先探测再重新绑定的竞争
+pub enum Listener {
+    Tcp(TcpListener),
+    /// `owned` 为 true 时套接字文件由本进程创建，退出时删除
+    #[cfg(unix)]
+    Unix {
+        listener: UnixListener,
+        path: std::path::PathBuf,
+        owned: bool,
+    },
+}
+
+impl Listener {
+    /// TCP 监听器的本地地址
+    pub fn tcp_addr(&self) -> Option<SocketAddr> {
+        match self {
+            Listener::Tcp(l) => l.local_addr().ok(),
+            #[cfg(unix)]
+            Listener::Unix { .. } => None,
+        }
+    }
+
+    fn poll_accept(&self, cx: &mut Context<'_>) -> Poll<io::Result<(ConnIo, SocketAddr)>> {
+        match self {
+            Listener::Tcp(l) => l.poll_accept(cx).map_ok(|(stream, remote)| {
+                let _ = stream.set_nodelay(true);
+                (ConnIo::Tcp(stream), remote)
+            }),
+            #[cfg(unix)]
+            Listener::Unix { listener, .. } => listener
+                .poll_accept(cx)
+                .map_ok(|(stream, _)| (ConnIo::Unix(stream), UNIX_PEER)),
+        }
+    }
+}
+
+impl fmt::Display for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Listener::Tcp(l) => match l.local_addr() {
+                Ok(addr) => write!(f, "{}", addr),
+                Err(_) => write!(f, "tcp"),
+            },
+            #[cfg(unix)]
+            Listener::Unix { path, .. } => write!(f, "unix:{}", path.display()),
+        }
+    }
+}
+
+/// 退出时删除自己创建的 Unix 套接字文件
+#[cfg(unix)]
+impl Drop for Listener {
+    fn drop(&mut self) {
+        if let Listener::Unix {
+            path, owned: true, ..
+        } = self
+        {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// 绑定所有监听地址
+///
+/// - 由 systemd 套接字激活启动（`LISTEN_FDS`）时直接使用继承的套接字；
+/// - 未指定 `--bind` 时按 `--public` 监听 0.0.0.0 或 127.0.0.1；
+/// - 只给出 IP 的地址从 `port` 开始依次尝试，所有这类地址使用同一个端口。
+pub fn bind_all(specs: &[BindSpec], port: u16, public: bool, lang: Lang) -> io::Result<Vec<Listener>> {
+    if let Some(listeners) = systemd_listeners()? {
+        return Ok(listeners);
+    }
+
+    let default_spec;
+    let specs = if specs.is_empty() {
+        let ip: IpAddr = if public {
+            Ipv4Addr::UNSPECIFIED.into() // 允许局域网访问
+        } else {
+            Ipv4Addr::LOCALHOST.into() // 仅允许本机访问
+        };
+        default_spec = [BindSpec::Ip(ip)];
+        &default_spec[..]
+    } else {
+        specs
+    };
+
+    // 同时监听 IPv4 和 IPv6 时，IPv6 套接字只接受 IPv6 连接，避免与 IPv4 通配地址冲突
+    let v6_only = specs.iter().any(|s| match s {
+        BindSpec::Ip(ip) => ip.is_ipv4(),
+        BindSpec::Addr(addr) => addr.is_ipv4(),
+        BindSpec::Unix(_) => false,
+    });
+
+    // 先为只给出 IP 的地址统一选定端口，再按命令行中的顺序排列监听器
+    let ips: Vec<IpAddr> = specs
+        .iter()
+        .filter_map(|s| match s {
+            BindSpec::Ip(ip) => Some(*ip),
+            _ => None,
+        })
+        .collect();
+    let mut auto = if ips.is_empty() {
+        Vec::new()
+    } else {
+        bind_auto_port(&ips, port, v6_only, lang)?
+    }
+    .into_iter();
+
+    let mut listeners = Vec::new();
+    for spec in specs {
+        match spec {
+            BindSpec::Addr(addr) => listeners.push(Listener::Tcp(bind_tcp(*addr, v6_only)?)),
+            BindSpec::Unix(path) => listeners.push(bind_unix(path, lang)?),
+            BindSpec::Ip(_) => listeners.extend(auto.next()),
+        }
+    }
+    Ok(listeners)
+}
+
+/// 从 `start_port` 开始寻找所有地址都可用的端口，端口号不会越过 65535
+fn bind_auto_port(
+    ips: &[IpAddr],
+    start_port: u16,
+    v6_only: bool,
+    lang: Lang,
+) -> io::Result<Vec<Listener>> {
+    if start_port == 0 {
+        return ips
+            .iter()
+            .map(|ip| bind_tcp(SocketAddr::new(*ip, 0), v6_only).map(Listener::Tcp))
+            .collect();
+    }
+
+    let mut last_err = None;
+    for offset in 0..MAX_PORT_ATTEMPTS {
+        let Some(port) = start_port.checked_add(offset) else {
+            break;
+        };
+        let bound: io::Result<Vec<Listener>> = ips
+            .iter()
+            .map(|ip| bind_tcp(SocketAddr::new(*ip, port), v6_only).map(Listener::Tcp))
+            .collect();
+        match bound {
+            Ok(listeners) => return Ok(listeners),
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+                if let Some(next) = port.checked_add(1) {
+                    println!(
+                        "\x1B[93m🟡 {}\x1B[0m",
+                        tr!(lang, "startup.port_in_use", port = port, next = next)
+                    );
+                }
+                last_err = Some(e);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| {
+        io::Error::new(io::ErrorKind::AddrInUse, lang.t("startup.no_free_port").to_string())
+    }))
+}
+
+fn bind_tcp(addr: SocketAddr, v6_only: bool) -> io::Result<TcpListener> {
+    let socket = Socket::new(Domain::for_address(addr), Type::STREAM, Some(Protocol::TCP))?;
+    if addr.is_ipv6() {
+        socket.set_only_v6(v6_only)?;
+    }
+    // Windows 上 SO_REUSEADDR 允许多个进程绑定同一端口，因此只在其他平台设置
+    #[cfg(not(windows))]
+    socket.set_reuse_address(true)?;
+    socket.bind(&addr.into())?;
+    socket.listen(1024)?;
+    socket.set_nonblocking(true)?;
+    TcpListener::from_std(socket.into())
+}
+
+#[cfg(unix)]
+fn bind_unix(path: &std::path::Path, lang: Lang) -> io::Result<Listener> {
+    use std::os::unix::fs::FileTypeExt;
+
+    // 只清理残留的套接字文件，不删除同名的普通文件
+    if let Ok(meta) = std::fs::symlink_metadata(path) {
+        if !meta.file_type().is_socket() {
+            return Err(io::Error::new(
+                io::ErrorKind::AlreadyExists,
+                tr!(lang, "startup.unix_not_socket", path = path.display()),
+            ));
+        }
+        std::fs::remove_file(path)?;
+    }
+    Ok(Listener::Unix {
+        listener: UnixListener::bind(path)?,
+        path: path.to_path_buf(),
+        owned: true,
+    })
+}
+
+#[cfg(not(unix))]
+fn bind_unix(_path: &std::path::Path, lang: Lang) -> io::Result<Listener> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        lang.t("startup.unix_unsupported").to_string(),
+    ))
+}
+
+/// systemd 套接字激活：`LISTEN_PID` 为当前进程时，从文件描述符 3 开始继承 `LISTEN_FDS` 个套接字
+#[cfg(unix)]
+fn systemd_listeners() -> io::Result<Option<Vec<Listener>>> {
+    use std::os::unix::io::FromRawFd;
+
+    const SD_LISTEN_FDS_START: i32 = 3;
+
+    let pid_matches = std::env::var("LISTEN_PID")
+        .ok()
+        .and_then(|p| p.parse::<u32>().ok())
+        .is_some_and(|p| p == std::process::id());
+    let count = std::env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|n| n.parse::<i32>().ok())
+        .unwrap_or(0);
+    if !pid_matches || count <= 0 {
+        return Ok(None);
+    }
+    // 避免子进程误用这些描述符
+    std::env::remove_var("LISTEN_PID");
+    std::env::remove_var("LISTEN_FDS");
+    std::env::remove_var("LISTEN_FDNAMES");
+
+    let mut listeners = Vec::new();
+    for fd in SD_LISTEN_FDS_START..SD_LISTEN_FDS_START + count {
+        // SAFETY: systemd 保证这些描述符在进程启动时已打开且归本进程所有
+        let socket = unsafe { Socket::from_raw_fd(fd) };
+        socket.set_nonblocking(true)?;
+        let local = socket.local_addr()?;
+        if local.as_socket().is_some() {
+            listeners.push(Listener::Tcp(TcpListener::from_std(socket.into())?));
+        } else {
+            let std_listener: std::os::unix::net::UnixListener = socket.into();
+            // 套接字文件由 systemd 管理，退出时不能删除
+            listeners.push(Listener::Unix {
+                listener: UnixListener::from_std(std_listener)?,
+                path: local.as_pathname().map(|p| p.to_path_buf()).unwrap_or_default(),
+                owned: false,
+            });
+        }
+    }
+    Ok(Some(listeners))
+}
+
+#[cfg(not(unix))]
+fn systemd_listeners() -> io::Result<Option<Vec<Listener>>> {
+    Ok(None)
+}
+
+/// 连接层面的限制，防止慢速客户端或大量连接耗尽服务
+#[derive(clap::Args, Debug, Clone)]
+pub struct ConnectionLimits {
+    /// 读取请求头的超时时间（含 keep-alive 连接等待下一个请求），0 表示不限制
+    #[arg(long, default_value = "10s", value_parser = parse_duration, global = true)]
+    pub header_timeout: Duration,
+
+    /// 连接超过该时间没有收发任何数据时关闭（处理请求期间不计时），0 表示不限制
+    #[arg(long, default_value = "60s", value_parser = parse_duration, global = true)]
+    pub idle_timeout: Duration,
+
+    /// 请求头大小上限（至少 8K），超过时返回 431
+    #[arg(long, default_value = "64K", value_parser = parse_header_size, global = true)]
+    pub max_header_size: usize,
+
+    /// 同时打开的连接数上限，达到上限时暂停接受新连接
+    #[arg(long, value_name = "N", global = true)]
+    pub max_connections: Option<usize>,
+
+    /// 每个客户端 IP 同时打开的连接数上限，超过时直接关闭新连接
+    #[arg(long, value_name = "N", global = true)]
+    pub max_connections_per_ip: Option<usize>,
+}
+
+/// hyper 的读缓冲区不能小于 8 KiB
+const MIN_HEADER_SIZE: usize = 8 * 1024;
+
+fn parse_header_size(s: &str) -> Result<usize, String> {
+    let size = parse_size(s)?;
+    if size < MIN_HEADER_SIZE as u64 {
+        return Err(format!("header size must be at least 8K: {}", s));
+    }
+    usize::try_from(size).map_err(|_| format!("size too large: {}", s))
+}
+
+/// 按连接限制创建 HTTP 服务
+///
+/// 明文连接上 HTTP/1.1 与 HTTP/2（h2c，需客户端直接发送 HTTP/2 前言）自动识别；
+/// 提供 `tls` 时所有 TCP 连接先完成 TLS 握手，由 ALPN 协商 HTTP/2 或 HTTP/1.1。
+/// `caps` 为连接数名额，可与 QUIC 端点共用。
+pub fn server(
+    listeners: Vec<Listener>,
+    limits: &ConnectionLimits,
+    caps: ConnectionCaps,
+    tls: Option<Arc<ServerConfig>>,
+    lang: Lang,
+) -> Builder<Incoming> {
+    let mut incoming = Incoming::new(listeners, limits, caps, lang);
+    incoming.tls = tls.map(TlsAcceptor::from);
+    let builder = Server::builder(incoming)
+        .http1_max_buf_size(limits.max_header_size);
+    match enabled_timeout(limits.header_timeout) {
+        Some(timeout) => builder.http1_header_read_timeout(timeout),
+        None => builder,
+    }
+}
+
+/// 启用的超时：0 表示不限制，大到无法计算截止时间的超时也按不限制处理（否则计时时溢出）
+fn enabled_timeout(timeout: Duration) -> Option<Duration> {
+    Some(timeout).filter(|t| !t.is_zero() && Instant::now().checked_add(*t).is_some())
+}
+
+/// 每个 IP 当前打开的连接数
+type IpCounts = Arc<Mutex<HashMap<IpAddr, usize>>>;
+
+/// `--max-connections` 与 `--max-connections-per-ip` 的名额，TCP 与 QUIC 连接共用
+#[derive(Clone)]
+pub struct ConnectionCaps {
+    slots: Option<Arc<Semaphore>>,
+    per_ip: Option<(usize, IpCounts)>,
+}
+
+/// 一个连接占用的名额，连接关闭时释放
+pub struct ConnectionSlot {
+    _permit: Option<OwnedSemaphorePermit>,
+    _ip_guard: Option<IpGuard>,
+}
+
+impl ConnectionCaps {
+    pub fn new(limits: &ConnectionLimits) -> Self {
+        ConnectionCaps {
+            slots: limits.max_connections.map(|n| Arc::new(Semaphore::new(n))),
+            per_ip: limits
+                .max_connections_per_ip
+                .map(|n| (n, Arc::new(Mutex::new(HashMap::new())))),
+        }
+    }
+
+    /// 为新连接取得名额：超过单 IP 上限时返回 None，连接总数达到上限时等待已有连接关闭
+    pub async fn acquire(&self, remote: SocketAddr) -> Option<ConnectionSlot> {
+        let ip_guard = self.register(remote)?;
+        let permit = match &self.slots {
+            Some(slots) => Some(slots.clone().acquire_owned().await.ok()?),
+            None => None,
+        };
+        Some(ConnectionSlot {
+            _permit: permit,
+            _ip_guard: ip_guard,
+        })
+    }
+
+    /// 登记新连接的 IP，超过单 IP 上限时返回 None
+    fn register(&self, remote: SocketAddr) -> Option<Option<IpGuard>> {
+        let Some((max, counts)) = &self.per_ip else {
+            return Some(None);
+        };
+        if remote == UNIX_PEER {
+            return Some(None);
+        }
+        let ip = remote.ip().to_canonical();
+        let mut map = counts.lock().unwrap();
+        let count = map.entry(ip).or_insert(0);
+        if *count >= *max {
+            return None;
+        }
+        *count += 1;
+        Some(Some(IpGuard {
+            ip,
+            counts: counts.clone(),
+        }))
+    }
+}
+
+/// 同时从多个监听器接受连接，供 `Server::builder` 使用
+pub struct Incoming {
+    listeners: Vec<Listener>,
+    next: usize,
+    /// 因文件描述符耗尽等错误暂停接受连接，避免空转
+    backoff: Option<Pin<Box<tokio::time::Sleep>>>,
+    /// 连接数名额，用尽时不再接受连接，新连接留在系统的等待队列中
+    slots: Option<PollSemaphore>,
+    /// 已取得、尚未分配给连接的名额
+    permit: Option<OwnedSemaphorePermit>,
+    /// 与 `slots` 共用同一组名额，另含每个 IP 当前的连接数
+    caps: ConnectionCaps,
+    idle_timeout: Option<Duration>,
+    /// 启用 TLS 时用于 TCP 连接的握手（Unix 套接字保持明文）
+    tls: Option<TlsAcceptor>,
+    lang: Lang,
+}
+
+impl Incoming {
+    pub fn new(
+        listeners: Vec<Listener>,
+        limits: &ConnectionLimits,
+        caps: ConnectionCaps,
+        lang: Lang,
+    ) -> Self {
+        Incoming {
+            listeners,
+            next: 0,
+            backoff: None,
+            slots: caps.slots.clone().map(PollSemaphore::new),
+            permit: None,
+            caps,
+            idle_timeout: enabled_timeout(limits.idle_timeout),
+            tls: None,
+            lang,
+        }
+    }
+}
+
+impl Accept for Incoming {
+    type Conn = Conn;
+    type Error = io::Error;
+
+    fn poll_accept(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Conn, Self::Error>>> {
+        if let Some(sleep) = self.backoff.as_mut() {
+            if sleep.as_mut().poll(cx).is_pending() {
+                return Poll::Pending;
+            }
+            self.backoff = None;
+        }
+
+        // 连接数达到上限时等待已有连接关闭
+        if self.permit.is_none() {
+            if let Some(slots) = self.slots.as_mut() {
+                match slots.poll_acquire(cx) {
+                    Poll::Ready(Some(permit)) => self.permit = Some(permit),
+                    Poll::Ready(None) => return Poll::Ready(None),
+                    Poll::Pending => return Poll::Pending,
+                }
+            }
+        }
+
+        // 轮流检查各监听器，避免某个监听器上的大量连接饿死其他监听器
+        let count = self.listeners.len();
+        for i in 0..count {
+            let index = (self.next + i) % count;
+            match self.listeners[index].poll_accept(cx) {
+                Poll::Ready(Ok((io, remote))) => {
+                    self.next = (index + 1) % count;
+                    let Some(ip_guard) = self.caps.register(remote) else {
+                        // 超过单 IP 连接上限，关闭连接后继续接受其他连接
+                        drop(io);
+                        cx.waker().wake_by_ref();
+                        return Poll::Pending;
+                    };
+                    let local = match &io {
+                        ConnIo::Tcp(stream) => stream.local_addr().ok(),
+                        _ => None,
+                    };
+                    // TLS 握手在连接的首次读写中进行，不阻塞接受其他连接
+                    let io = match (&self.tls, io) {
+                        (Some(acceptor), ConnIo::Tcp(stream)) => {
+                            ConnIo::Tls(Box::new(TlsIo::Handshaking(acceptor.accept(stream))))
+                        }
+                        (_, io) => io,
+                    };
+                    let idle = self.idle_timeout.map(|timeout| IdleTimer {
+                        timeout,
+                        sleep: Box::pin(tokio::time::sleep(timeout)),
+                        paused: false,
+                    });
+                    return Poll::Ready(Some(Ok(Conn {
+                        io,
+                        remote,
+                        local,
+                        idle,
+                        activity: Activity::default(),
+                        _slot: ConnectionSlot {
+                            _permit: self.permit.take(),
+                            _ip_guard: ip_guard,
+                        },
+                    })));
+                }
+                Poll::Ready(Err(e)) if is_connection_error(&e) => {
+                    // 单个连接在握手期间被重置，继续接受其他连接
+                    cx.waker().wake_by_ref();
+                    return Poll::Pending;
+                }
+                Poll::Ready(Err(e)) => {
+                    eprintln!("❌ \x1B[91m{}\x1B[0m", tr!(self.lang, "common.accept_failed", error = e));
+                    let mut sleep = Box::pin(tokio::time::sleep(Duration::from_secs(1)));
+                    let _ = sleep.as_mut().poll(cx);
+                    self.backoff = Some(sleep);
+                    return Poll::Pending;
+                }
+                Poll::Pending => {}
+            }
+        }
+        Poll::Pending
+    }
+}
+
+/// 连接关闭时减少对应 IP 的连接数
+struct IpGuard {
+    ip: IpAddr,
+    counts: IpCounts,
+}
+
+impl Drop for IpGuard {
+    fn drop(&mut self) {
+        let mut map = self.counts.lock().unwrap();
+        if let Some(count) = map.get_mut(&self.ip) {
+            *count -= 1;
+            if *count == 0 {
+                map.remove(&self.ip);
+            }
+        }
+    }
+}
+
+/// 连接上正在处理的请求数，大于 0 时暂停空闲计时，服务端计算响应的时间不算作空闲
+#[derive(Clone, Default)]
+pub struct Activity(Arc<AtomicUsize>);
+
+impl Activity {
+    /// 开始处理一个请求，返回值释放前连接不会因空闲而关闭
+    pub fn begin(&self) -> Busy {
+        self.0.fetch_add(1, Ordering::Relaxed);
+        Busy(self.0.clone())
+    }
+
+    fn is_busy(&self) -> bool {
+        self.0.load(Ordering::Relaxed) > 0
+    }
+}
+
+/// 处理中的请求，释放时减少连接上的请求数
+pub struct Busy(Arc<AtomicUsize>);
+
+impl Drop for Busy {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// 连接空闲计时器，每次成功收发数据后重新计时
+struct IdleTimer {
+    timeout: Duration,
+    sleep: Pin<Box<Sleep>>,
+    /// 上次检查时有请求正在处理，处理完成后从头计时
+    paused: bool,
+}
+
+impl IdleTimer {
+    fn reset(&mut self) {
+        // 截止时间无法表示时保持原来的截止时间（这样的超时由 `enabled_timeout` 排除，不会走到这里）
+        if let Some(deadline) = Instant::now().checked_add(self.timeout) {
+            self.sleep.as_mut().reset(deadline);
+        }
+    }
+}
+
+fn is_connection_error(e: &io::Error) -> bool {
+    matches!(
+        e.kind(),
+        io::ErrorKind::ConnectionRefused
+            | io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+    )
+}
+
+enum ConnIo {
+    Tcp(TcpStream),
+    Tls(Box<TlsIo>),
+    #[cfg(unix)]
+    Unix(UnixStream),
+}
+
+/// TLS 连接：握手完成前处于 `Handshaking`，之后的读写都经过 `Streaming`
+enum TlsIo {
+    Handshaking(tokio_rustls::Accept<TcpStream>),
+    Streaming(TlsStream<TcpStream>),
+}
+
+impl TlsIo {
+    /// 推进握手，完成后对 TLS 流执行 `f`；握手失败的错误按读写错误返回
+    fn poll_with<T>(
+        &mut self,
+        cx: &mut Context<'_>,
+        f: impl FnOnce(Pin<&mut TlsStream<TcpStream>>, &mut Context<'_>) -> Poll<io::Result<T>>,
+    ) -> Poll<io::Result<T>> {
+        if let TlsIo::Handshaking(accept) = self {
+            match Pin::new(accept).poll(cx) {
+                Poll::Ready(Ok(stream)) => *self = TlsIo::Streaming(stream),
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e)),
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+        match self {
+            TlsIo::Streaming(stream) => f(Pin::new(stream), cx),
+            TlsIo::Handshaking(_) => unreachable!(),
+        }
+    }
+}
+
+/// 一个已接受的连接（TCP、TLS 或 Unix 套接字）
+pub struct Conn {
+    io: ConnIo,
+    remote: SocketAddr,
+    /// 本地 TCP 地址，Unix 套接字连接为 None
+    local: Option<SocketAddr>,
+    idle: Option<IdleTimer>,
+    activity: Activity,
+    _slot: ConnectionSlot,
+}
+
+impl Conn {
+    /// 客户端地址；Unix 套接字连接为 [`UNIX_PEER`]
+    pub fn remote_addr(&self) -> SocketAddr {
+        self.remote
+    }
+
+    /// 连接所在的本地 TCP 地址
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.local
+    }
+
+    /// 连接上的请求处理状态，服务在处理请求期间持有 [`Activity::begin`] 的返回值
+    pub fn activity(&self) -> Activity {
+        self.activity.clone()
+    }
+
+    /// 传输方式：http / https / unix
+    pub fn transport(&self) -> &'static str {
+        match self.io {
+            ConnIo::Tcp(_) => "http",
+            ConnIo::Tls(_) => "https",
+            #[cfg(unix)]
+            ConnIo::Unix(_) => "unix",
+        }
+    }
+
+    /// 根据一次读写的结果更新空闲计时：成功时或有请求正在处理时重新计时，等待中且已超时时返回错误
+    fn track<T>(&mut self, cx: &mut Context<'_>, poll: Poll<io::Result<T>>) -> Poll<io::Result<T>> {
+        let Some(idle) = self.idle.as_mut() else {
+            return poll;
+        };
+        match poll {
+            Poll::Ready(Ok(value)) => {
+                idle.reset();
+                Poll::Ready(Ok(value))
+            }
+            Poll::Pending if self.activity.is_busy() || idle.paused => {
+                idle.paused = self.activity.is_busy();
+                idle.reset();
+                // 处理中也要登记唤醒：到时只是再检查一次，处理完成后的那次检查才开始计算空闲，
+                // 否则响应发出后没有新的读写时连接不会再被唤醒，也就不会因空闲而关闭
+                let _ = idle.sleep.as_mut().poll(cx);
+                Poll::Pending
+            }
+            Poll::Pending if idle.sleep.as_mut().poll(cx).is_ready() => {
+                Poll::Ready(Err(io::ErrorKind::TimedOut.into()))
+            }
+            other => other,
+        }
+    }
+}
+
+impl AsyncRead for Conn {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let poll = match &mut self.io {
+            ConnIo::Tcp(s) => Pin::new(s).poll_read(cx, buf),
+            ConnIo::Tls(s) => s.poll_with(cx, |s, cx| s.poll_read(cx, buf)),
+            #[cfg(unix)]
+            ConnIo::Unix(s) => Pin::new(s).poll_read(cx, buf),
+        };
+        self.track(cx, poll)
+    }
+}
+
+impl AsyncWrite for Conn {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let poll = match &mut self.io {
+            ConnIo::Tcp(s) => Pin::new(s).poll_write(cx, buf),
+            ConnIo::Tls(s) => s.poll_with(cx, |s, cx| s.poll_write(cx, buf)),
+            #[cfg(unix)]
+            ConnIo::Unix(s) => Pin::new(s).poll_write(cx, buf),
+        };
+        self.track(cx, poll)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let poll = match &mut self.io {
+            ConnIo::Tcp(s) => Pin::new(s).poll_flush(cx),
+            ConnIo::Tls(s) => s.poll_with(cx, |s, cx| s.poll_flush(cx)),
+            #[cfg(unix)]
+            ConnIo::Unix(s) => Pin::new(s).poll_flush(cx),
+        };
+        self.track(cx, poll)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match &mut self.io {
+            ConnIo::Tcp(s) => Pin::new(s).poll_shutdown(cx),
+            ConnIo::Tls(s) => s.poll_with(cx, |s, cx| s.poll_shutdown(cx)),
+            #[cfg(unix)]
+            ConnIo::Unix(s) => Pin::new(s).poll_shutdown(cx),
+        }
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[io::IoSlice<'_>],
+    ) -> Poll<io::Result<usize>> {
+        let poll = match &mut self.io {
+            ConnIo::Tcp(s) => Pin::new(s).poll_write_vectored(cx, bufs),
+            ConnIo::Tls(s) => s.poll_with(cx, |s, cx| s.poll_write_vectored(cx, bufs)),
+            #[cfg(unix)]
+            ConnIo::Unix(s) => Pin::new(s).poll_write_vectored(cx, bufs),
+        };
+        self.track(cx, poll)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        match &self.io {
+            ConnIo::Tcp(s) => s.is_write_vectored(),
+            // hyper 在握手完成之前就会查询，TLS 连接统一按不支持处理
+            ConnIo::Tls(_) => false,
+            #[cfg(unix)]
+            ConnIo::Unix(s) => s.is_write_vectored(),
+        }
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use tokio::io::AsyncReadExt;
+
+    /// 空闲超时为 `timeout` 的 Unix 套接字连接，以及连接的另一端
+    fn idle_conn(timeout: Duration) -> (Conn, UnixStream) {
+        let (io, peer) = UnixStream::pair().unwrap();
+        let conn = Conn {
+            io: ConnIo::Unix(io),
+            remote: UNIX_PEER,
+            local: None,
+            idle: Some(IdleTimer {
+                timeout,
+                sleep: Box::pin(tokio::time::sleep(timeout)),
+                paused: false,
+            }),
+            activity: Activity::default(),
+            _slot: ConnectionSlot {
+                _permit: None,
+                _ip_guard: None,
+            },
+        };
+        (conn, peer)
+    }
+
+    #[test]
+    fn huge_timeouts_are_disabled() {
+        assert_eq!(enabled_timeout(Duration::ZERO), None);
+        assert_eq!(enabled_timeout(Duration::from_secs(u64::MAX)), None);
+        let minute = Duration::from_secs(60);
+        assert_eq!(enabled_timeout(minute), Some(minute));
+    }
+
+    #[tokio::test]
+    async fn unix_sockets_only_replace_stale_sockets() {
+        let dir = crate::util::TestDir::new("listener_unix");
+        let path = dir.join("sock");
+        // 上次运行留下的套接字文件
+        drop(std::os::unix::net::UnixListener::bind(&path).unwrap());
+        assert!(bind_unix(&path, Lang::En).is_ok());
+
+        let file = dir.join("file");
+        std::fs::write(&file, "keep").unwrap();
+        let err = bind_unix(&file, Lang::En).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::AlreadyExists);
+        assert!(err.to_string().contains("not a socket"));
+        assert_eq!(std::fs::read_to_string(&file).unwrap(), "keep");
+    }
+
+    #[tokio::test]
+    async fn idle_connections_time_out() {
+        let (mut conn, _peer) = idle_conn(Duration::from_millis(100));
+        let mut buf = [0u8; 16];
+        let read = tokio::time::timeout(Duration::from_secs(2), conn.read(&mut buf)).await;
+        assert_eq!(read.unwrap().unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+
+    #[tokio::test]
+    async fn idle_timer_pauses_while_a_request_is_handled() {
+        let (mut conn, _peer) = idle_conn(Duration::from_millis(100));
+        let busy = conn.activity().begin();
+        let mut buf = [0u8; 16];
+        // 处理请求期间等待的时间远超空闲超时，连接仍保持
+        let read = tokio::time::timeout(Duration::from_millis(400), conn.read(&mut buf)).await;
+        assert!(read.is_err());
+
+        // 请求处理完成后从头开始计时
+        drop(busy);
+        let started = std::time::Instant::now();
+        let read = tokio::time::timeout(Duration::from_secs(2), conn.read(&mut buf)).await;
+        assert_eq!(read.unwrap().unwrap_err().kind(), io::ErrorKind::TimedOut);
+        assert!(started.elapsed() >= Duration::from_millis(90));
+    }
+
+    #[tokio::test]
+    async fn idle_timer_wakes_a_read_left_waiting_after_a_request() {
+        let (mut conn, _peer) = idle_conn(Duration::from_millis(100));
+        let busy = conn.activity().begin();
+        let mut buf = [0u8; 16];
+        // 读取在处理请求期间开始等待，请求处理完成后不再有别的事件唤醒它
+        let release = async {
+            tokio::time::sleep(Duration::from_millis(250)).await;
+            drop(busy);
+            std::future::pending::<()>().await
+        };
+        let read = tokio::time::timeout(Duration::from_secs(2), async {
+            tokio::select! {
+                biased;
+                read = conn.read(&mut buf) => read,
+                _ = release => unreachable!(),
+            }
+        })
+        .await;
+        assert_eq!(read.unwrap().unwrap_err().kind(), io::ErrorKind::TimedOut);
+    }
+}
@@ -0,0 +1,35 @@
+use hyper::{Body, Response, StatusCode};
+use serde::Serialize;
+
+use super::{Listing, ListingRenderer};
+use crate::du::DiskUsage;
+use crate::i18n::Lang;
+
+/// JSON 格式的目录列表，供脚本和前端调用
+///
+/// 请求带 `?format=json` 或 `Accept: application/json` 时使用，与启动时选择的渲染器无关。
+pub struct JsonRenderer;
+
+impl ListingRenderer for JsonRenderer {
+    fn render(&self, listing: &Listing, _lang: Lang) -> Response<Body> {
+        json_response(listing)
+    }
+
+    fn render_usage(&self, usage: &DiskUsage, _lang: Lang) -> Response<Body> {
+        json_response(usage)
+    }
+}
+
+fn json_response<T: Serialize>(value: &T) -> Response<Body> {
+    match serde_json::to_string(value) {
+        Ok(json) => Response::builder()
+            .header("Content-Type", "application/json; charset=utf-8")
+            .header("Access-Control-Allow-Origin", "*")
+            .body(Body::from(json))
+            .unwrap(),
+        Err(e) => Response::builder()
+            .status(StatusCode::INTERNAL_SERVER_ERROR)
+            .body(Body::from(e.to_string()))
+            .unwrap(),
+    }
+}
@@ -0,0 +1,225 @@
+mod json;
+mod plain;
+mod template;
+
+pub use json::JsonRenderer;
+pub use plain::PlainRenderer;
+pub use template::TemplateRenderer;
+
+use hyper::{Body, Response};
+use percent_encoding::utf8_percent_encode;
+use serde::Serialize;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+use tokio::fs;
+
+use crate::access::AccessRules;
+use crate::du::{DiskSpace, DiskUsage};
+use crate::i18n::Lang;
+use crate::util::{format_size, SEGMENT};
+
+/// 主题静态资源的路径前缀（保留路径，不会映射到根目录下的文件）
+pub const ASSET_PREFIX: &str = "/_theme/";
+
+/// 页面配色
+#[derive(Clone, Copy, Debug, PartialEq, Eq, clap::ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Theme {
+    /// 跟随系统设置
+    Auto,
+    Light,
+    Dark,
+}
+
+/// 主题提供的静态资源
+pub enum Asset {
+    /// 编译进程序的内置资源
+    Embedded(&'static str),
+    /// 模板目录中的文件
+    File(PathBuf),
+}
+
+/// 目录列表渲染器
+///
+/// 请求处理流程（路径解析、文件传输、错误处理）只有一份，
+/// 不同的输出风格只需实现本 trait。
+pub trait ListingRenderer: Send + Sync {
+    /// 将目录内容按 `lang` 渲染为完整的 HTTP 响应
+    fn render(&self, listing: &Listing, lang: Lang) -> Response<Body>;
+
+    /// 将目录占用（`?du`）渲染为完整的 HTTP 响应，默认为简单的表格
+    fn render_usage(&self, usage: &DiskUsage, lang: Lang) -> Response<Body> {
+        plain::usage_page(usage, lang)
+    }
+
+    /// 查找 `/_theme/` 下的静态资源，`name` 为去掉前缀后的路径
+    fn asset(&self, _name: &str) -> Option<Asset> {
+        None
+    }
+}
+
+/// 目录中的一项
+#[derive(Debug, Serialize)]
+pub struct Entry {
+    /// 文件名
+    pub name: String,
+    /// 相对根目录的路径（未编码，不带前导 `/`）
+    pub path: String,
+    /// 链接地址（已按路径段编码）
+    pub href: String,
+    pub is_dir: bool,
+    /// 文件大小（字节），目录为 0
+    pub size: u64,
+    /// 便于阅读的文件大小，目录为空字符串
+    pub size_display: String,
+    /// 修改时间（Unix 秒），无法获取时为 None
+    pub modified: Option<u64>,
+}
+
+/// 面包屑导航中的一级目录
+#[derive(Debug, Serialize)]
+pub struct Breadcrumb {
+    pub name: String,
+    pub href: String,
+}
+
+/// 目录统计信息
+#[derive(Debug, Serialize)]
+pub struct ListingStats {
+    pub dir_count: usize,
+    pub file_count: usize,
+    /// 本目录下文件的总大小（不递归）
+    pub total_size: u64,
+    pub total_size_display: String,
+}
+
+/// 待渲染的目录
+#[derive(Debug, Serialize)]
+pub struct Listing {
+    /// 当前路径，以 `/` 开头
+    pub path: String,
+    /// 相对根目录的路径（不带前导 `/`），根目录为空字符串
+    pub relative_path: String,
+    /// 上级目录的链接地址，根目录为 None
+    pub parent: Option<String>,
+    /// 从根目录到当前目录的各级目录（不含根目录本身）
+    pub breadcrumbs: Vec<Breadcrumb>,
+    /// 目录在前、文件在后，各自按名称排序
+    pub entries: Vec<Entry>,
+    pub stats: ListingStats,
+    /// 当前客户端能否通过文件管理接口修改本目录，由调用方设置
+    pub writable: bool,
+    /// 是否保留历史版本（--keep-versions），由调用方设置
+    pub history: bool,
+    /// 根目录所在文件系统的空间，由调用方设置
+    pub disk: Option<DiskSpace>,
+    /// 根目录在磁盘上的位置（不输出给 JSON / 模板，避免泄露服务器路径）
+    #[serde(skip)]
+    pub root: PathBuf,
+}
+
+impl Listing {
+    /// 读取 `root` 下 `relative_path` 目录的内容，跳过被 `access` 排除的项
+    pub async fn read(root: &Path, relative_path: &str, access: &AccessRules) -> io::Result<Listing> {
+        let relative_path = relative_path.trim_matches('/').to_string();
+        let mut dir = fs::read_dir(root.join(&relative_path)).await?;
+
+        let mut entries = Vec::new();
+        while let Some(entry) = dir.next_entry().await? {
+            let name = entry.file_name().to_string_lossy().to_string();
+            // 跟随符号链接，与直接访问时的行为一致
+            let metadata = match fs::metadata(entry.path()).await {
+                Ok(m) => m,
+                Err(_) => match entry.metadata().await {
+                    Ok(m) => m,
+                    Err(_) => continue,
+                },
+            };
+            let is_dir = metadata.is_dir();
+            let size = if is_dir { 0 } else { metadata.len() };
+            let path = if relative_path.is_empty() {
+                name.clone()
+            } else {
+                format!("{}/{}", relative_path, name)
+            };
+            if access.check(&path, is_dir).denied {
+                continue;
+            }
+            entries.push(Entry {
+                href: href_for(&path),
+                name,
+                path,
+                is_dir,
+                size,
+                size_display: if is_dir { String::new() } else { format_size(size) },
+                modified: metadata
+                    .modified()
+                    .ok()
+                    .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                    .map(|d| d.as_secs()),
+            });
+        }
+
+        // 排序：目录在前，文件在后
+        entries.sort_by(|a, b| b.is_dir.cmp(&a.is_dir).then_with(|| a.name.cmp(&b.name)));
+
+        let dir_count = entries.iter().filter(|e| e.is_dir).count();
+        let total_size = entries.iter().map(|e| e.size).sum();
+        let stats = ListingStats {
+            dir_count,
+            file_count: entries.len() - dir_count,
+            total_size,
+            total_size_display: format_size(total_size),
+        };
+
+        let mut breadcrumbs = Vec::new();
+        let mut prefix = String::new();
+        for segment in relative_path.split('/').filter(|s| !s.is_empty()) {
+            if !prefix.is_empty() {
+                prefix.push('/');
+            }
+            prefix.push_str(segment);
+            breadcrumbs.push(Breadcrumb {
+                name: segment.to_string(),
+                href: href_for(&prefix),
+            });
+        }
+
+        let parent = if relative_path.is_empty() {
+            None
+        } else {
+            Some(href_for(
+                relative_path.rsplit_once('/').map(|(p, _)| p).unwrap_or(""),
+            ))
+        };
+
+        Ok(Listing {
+            path: format!("/{}", relative_path),
+            parent,
+            breadcrumbs,
+            entries,
+            stats,
+            writable: false,
+            history: false,
+            disk: None,
+            relative_path,
+            root: root.to_path_buf(),
+        })
+    }
+}
+
+/// 相对路径对应的链接地址
+pub fn href_for(relative_path: &str) -> String {
+    let mut href = String::new();
+    for segment in relative_path.split('/').filter(|s| !s.is_empty()) {
+        href.push('/');
+        href.extend(utf8_percent_encode(segment, SEGMENT));
+    }
+    if href.is_empty() {
+        href.push('/');
+    }
+    href
+}
@@ -0,0 +1,83 @@
+use hyper::{Body, Response};
+
+use super::{Listing, ListingRenderer};
+use crate::du::DiskUsage;
+use crate::i18n::Lang;
+use crate::tr;
+use crate::util::html_escape;
+
+/// 简洁的目录列表（默认）
+pub struct PlainRenderer;
+
+impl ListingRenderer for PlainRenderer {
+    fn render(&self, listing: &Listing, lang: Lang) -> Response<Body> {
+        let full_path = listing.root.join(&listing.relative_path);
+        let title = html_escape(&tr!(lang, "listing.index_of", path = full_path.display()));
+        let mut html = format!(
+            "<html lang=\"{}\"><head><meta charset='utf-8'><title>{}</title></head><body><h3>📁 {}</h3><ul>",
+            lang.code(),
+            title,
+            title
+        );
+
+        for entry in &listing.entries {
+            let icon = if entry.is_dir { "📁" } else { "📄" };
+            html += &format!(
+                "<li>{} <a href=\"{}\">{}</a></li>",
+                icon,
+                entry.href,
+                html_escape(&entry.name)
+            );
+        }
+
+        html += "</ul></body></html>";
+
+        Response::builder()
+            .header("Content-Type", "text/html; charset=utf-8")
+            .header("Content-Language", lang.code())
+            .body(Body::from(html))
+            .unwrap()
+    }
+}
+
+/// 简洁的目录占用表格，未自定义目录占用页面的渲染器都使用它
+pub(super) fn usage_page(usage: &DiskUsage, lang: Lang) -> Response<Body> {
+    let full_path = usage.root.join(&usage.relative_path);
+    let title = html_escape(&tr!(lang, "du.title", path = full_path.display()));
+    let mut html = format!(
+        "<html lang=\"{}\"><head><meta charset='utf-8'><title>{}</title></head><body><h3>📏 {}</h3><p>{}</p><table>",
+        lang.code(),
+        title,
+        title,
+        html_escape(&tr!(
+            lang,
+            "du.summary",
+            size = usage.size_display,
+            files = usage.files,
+            dirs = usage.dirs
+        ))
+    );
+
+    for entry in &usage.entries {
+        let (icon, href) = if entry.is_dir {
+            ("📁", format!("{}?du", entry.href))
+        } else {
+            ("📄", entry.href.clone())
+        };
+        html += &format!(
+            "<tr><td>{} <a href=\"{}\">{}</a></td><td align=\"right\">{}</td></tr>",
+            icon,
+            href,
+            html_escape(&entry.name),
+            entry.size_display
+        );
+    }
+
+    html += "</table></body></html>";
+
+    Response::builder()
+        .header("Content-Type", "text/html; charset=utf-8")
+        .header("Content-Language", lang.code())
+        .body(Body::from(html))
+        .unwrap()
+}
@@ -0,0 +1,197 @@
+use hyper::{Body, Response, StatusCode};
+use minijinja::{context, path_loader, value::Kwargs, Environment, State, Value};
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+use super::{Asset, Listing, ListingRenderer, Theme, ASSET_PREFIX};
+use crate::du::DiskUsage;
+use crate::i18n::{self, Lang};
+use crate::util::normalize_relative;
+use crate::tr;
+
+const TEMPLATE_NAME: &str = "listing.html";
+/// 目录占用（`?du`）页面的模板名，模板目录中没有时使用内置主题的模板
+const USAGE_TEMPLATE_NAME: &str = "du.html";
+
+/// 内置主题（`--pretty`）
+const BUILTIN_TEMPLATE: &str = include_str!("theme/listing.html");
+const BUILTIN_USAGE_TEMPLATE: &str = include_str!("theme/du.html");
+const BUILTIN_ASSETS: &[(&str, &str)] = &[
+    ("style.css", include_str!("theme/style.css")),
+    ("app.js", include_str!("theme/app.js")),
+];
+
+/// 使用 minijinja 模板渲染目录列表
+///
+/// 模板中可用的变量：JSON 输出的全部字段（`path`、`relative_path`、`parent`、`breadcrumbs`、
+/// `entries`、`stats`、`writable`、`history`、`disk`），以及 `root`（根目录）、`locale`、`theme` 和 `asset_prefix`。
+/// 目录占用页面（`du.html`）可用的变量为 `?du&format=json` 输出的全部字段和上述公共变量。
+/// 另外提供按 `locale` 翻译的 `t(key, 参数=值)` 函数、`file_icon` 过滤器和 `image` 测试。
+/// 输出默认进行 HTML 转义。
+pub struct TemplateRenderer {
+    env: Environment<'static>,
+    /// 模板目录下的静态资源目录，未设置时只提供内置资源
+    static_dir: Option<PathBuf>,
+    theme: Theme,
+}
+
+impl TemplateRenderer {
+    /// 内置主题
+    pub fn builtin(theme: Theme) -> Self {
+        let mut env = new_env();
+        env.add_template(TEMPLATE_NAME, BUILTIN_TEMPLATE)
+            .expect("内置模板语法错误");
+        add_builtin_usage(&mut env);
+        TemplateRenderer { env, static_dir: None, theme }
+    }
+
+    /// 从单个文件加载模板，启动时即检查语法错误
+    pub fn from_file(path: &Path, theme: Theme) -> Result<Self, String> {
+        let source = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        let mut env = new_env();
+        env.add_template_owned(TEMPLATE_NAME, source)
+            .map_err(|e| e.to_string())?;
+        add_builtin_usage(&mut env);
+        Ok(TemplateRenderer { env, static_dir: None, theme })
+    }
+
+    /// 从模板目录加载：`listing.html` 为入口模板（可 include / extends 目录中的其他模板），
+    /// 可选的 `du.html` 为目录占用页面，`static/` 下的文件通过 `/_theme/` 提供
+    pub fn from_dir(dir: &Path, theme: Theme) -> Result<Self, String> {
+        let mut env = new_env();
+        env.set_loader(path_loader(dir));
+        env.get_template(TEMPLATE_NAME).map_err(|e| e.to_string())?;
+        if dir.join(USAGE_TEMPLATE_NAME).is_file() {
+            env.get_template(USAGE_TEMPLATE_NAME)
+                .map_err(|e| e.to_string())?;
+        } else {
+            add_builtin_usage(&mut env);
+        }
+        Ok(TemplateRenderer {
+            env,
+            static_dir: Some(dir.join("static")),
+            theme,
+        })
+    }
+}
+
+fn add_builtin_usage(env: &mut Environment<'static>) {
+    env.add_template(USAGE_TEMPLATE_NAME, BUILTIN_USAGE_TEMPLATE)
+        .expect("内置模板语法错误");
+}
+
+fn new_env() -> Environment<'static> {
+    let mut env = Environment::new();
+    env.add_function("t", translate);
+    env.add_filter("file_icon", |name: &str| file_icon(name).to_string());
+    env.add_test("image", |name: &str| is_image(name));
+    env
+}
+
+/// 模板函数 `t`：按当前页面的 `locale` 取出消息，关键字参数替换 `{name}` 占位符
+fn translate(state: &State, key: &str, kwargs: Kwargs) -> Result<String, minijinja::Error> {
+    let lang = state
+        .lookup("locale")
+        .and_then(|v| v.as_str().and_then(Lang::parse))
+        .unwrap_or(Lang::ZhCn);
+    let mut args: Vec<(&str, Value)> = Vec::new();
+    for name in kwargs.args() {
+        args.push((name, kwargs.get::<Value>(name)?));
+    }
+    let args: Vec<(&str, &dyn std::fmt::Display)> = args
+        .iter()
+        .map(|(name, value)| (*name, value as &dyn std::fmt::Display))
+        .collect();
+    Ok(i18n::format(lang.t(key), &args))
+}
+
+impl TemplateRenderer {
+    /// 以 `data` 的字段和公共变量渲染模板 `name`
+    fn render_page<T: Serialize>(
+        &self,
+        name: &str,
+        root: &Path,
+        data: &T,
+        lang: Lang,
+    ) -> Response<Body> {
+        let ctx = context! {
+            root => root.display().to_string(),
+            locale => lang.code(),
+            theme => self.theme,
+            asset_prefix => ASSET_PREFIX,
+            ..Value::from_serialize(data)
+        };
+        let result = self.env.get_template(name).and_then(|t| t.render(ctx));
+        match result {
+            Ok(html) => Response::builder()
+                .header("Content-Type", "text/html; charset=utf-8")
+                .header("Content-Language", lang.code())
+                .header("Access-Control-Allow-Origin", "*")
+                .body(Body::from(html))
+                .unwrap(),
+            Err(e) => Response::builder()
+                .status(StatusCode::INTERNAL_SERVER_ERROR)
+                .header("Content-Type", "text/plain; charset=utf-8")
+                .body(Body::from(tr!(lang, "listing.template_error", error = e)))
+                .unwrap(),
+        }
+    }
+}
+
+impl ListingRenderer for TemplateRenderer {
+    fn render(&self, listing: &Listing, lang: Lang) -> Response<Body> {
+        self.render_page(TEMPLATE_NAME, &listing.root, listing, lang)
+    }
+
+    fn render_usage(&self, usage: &DiskUsage, lang: Lang) -> Response<Body> {
+        self.render_page(USAGE_TEMPLATE_NAME, &usage.root, usage, lang)
+    }
+
+    /// 优先使用模板目录中的资源，找不到时回退到内置主题的资源
+    fn asset(&self, name: &str) -> Option<Asset> {
+        if let (Some(dir), Some(relative)) = (&self.static_dir, normalize_relative(name)) {
+            let path = dir.join(relative);
+            if path.is_file() {
+                return Some(Asset::File(path));
+            }
+        }
+        BUILTIN_ASSETS
+            .iter()
+            .find(|(asset, _)| *asset == name)
+            .map(|(_, content)| Asset::Embedded(content))
+    }
+}
+
+/// 是否为可以显示缩略图的图片
+fn is_image(name: &str) -> bool {
+    let name = name.to_lowercase();
+    [".jpg", ".jpeg", ".png", ".gif", ".bmp", ".webp", ".svg"]
+        .iter()
+        .any(|ext| name.ends_with(ext))
+}
+
+/// 根据文件扩展名选择不同的图标
+fn file_icon(name: &str) -> &'static str {
+    let ends_with_any = |exts: &[&str]| exts.iter().any(|ext| name.ends_with(ext));
+    if ends_with_any(&[".txt", ".md"]) {
+        "📄"
+    } else if ends_with_any(&[".mp4", ".avi", ".mov", ".mkv"]) {
+        "🎬"
+    } else if ends_with_any(&[".mp3", ".wav", ".flac"]) {
+        "🎵"
+    } else if ends_with_any(&[".pdf"]) {
+        "📕"
+    } else if ends_with_any(&[".zip", ".rar", ".7z"]) {
+        "📦"
+    } else if ends_with_any(&[".js", ".html", ".css"]) {
+        "💻"
+    } else if ends_with_any(&[".doc", ".docx"]) {
+        "📘"
+    } else if ends_with_any(&[".xls", ".xlsx"]) {
+        "📗"
+    } else if ends_with_any(&[".ppt", ".pptx"]) {
+        "📙"
+    } else {
+        "📄"
+    }
+}
@@ -0,0 +1,600 @@
+function replaceBackslashesWithForwardSlashes(inputString) {
+    return inputString.replace(/\\/g, '/');
+}
+
+document.addEventListener('DOMContentLoaded', function () {
+    const path = document.querySelector('.path');
+    if (path) {
+        path.textContent = replaceBackslashesWithForwardSlashes(path.textContent);
+    }
+});
+
+async function shareFile(event, path) {
+    event.preventDefault();
+    event.stopPropagation();
+    const expires = prompt(MESSAGES.shareExpires, '24h');
+    if (expires === null) return;
+    const max = prompt(MESSAGES.shareMaxDownloads, '');
+    if (max === null) return;
+    const password = prompt(MESSAGES.sharePassword, '');
+    if (password === null) return;
+    const res = await fetch('/_shares/create', {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json' },
+        body: JSON.stringify({
+            path: path,
+            expires: expires,
+            max_downloads: max ? parseInt(max, 10) : null,
+            password: password || null
+        })
+    });
+    if (!res.ok) {
+        alert(MESSAGES.shareFailed + ': ' + await res.text());
+        return;
+    }
+    const data = await res.json();
+    prompt(MESSAGES.shareCreated, data.url);
+}
+
+// 文件管理：多选、右键菜单，通过 /_api/fs 执行操作（仅在开启 --allow-write 且有权限时显示）
+
+function selectedItems() {
+    return Array.from(document.querySelectorAll('.file-item.selected'));
+}
+
+function updateSelection() {
+    const items = selectedItems();
+    document.body.classList.toggle('selecting', items.length > 0);
+    document.getElementById('selection-count').textContent = MESSAGES.selected.replace('{count}', items.length);
+    document.getElementById('rename-btn').disabled = items.length !== 1;
+    document.querySelectorAll('.select-box').forEach(function (box) {
+        box.checked = box.parentElement.classList.contains('selected');
+    });
+}
+
+function toggleSelect(event, box) {
+    event.stopPropagation();
+    event.preventDefault();
+    box.parentElement.classList.toggle('selected');
+    updateSelection();
+}
+
+function clearSelection() {
+    selectedItems().forEach(function (item) { item.classList.remove('selected'); });
+    updateSelection();
+}
+
+function joinPath(dir, name) {
+    return dir.replace(/\/$/, '') + '/' + name;
+}
+
+// 逐行读取 NDJSON 进度，返回最后一行的结果
+async function readProgress(response) {
+    const status = document.getElementById('fs-status');
+    const reader = response.body.getReader();
+    const decoder = new TextDecoder();
+    let buffer = '';
+    let result = null;
+    for (;;) {
+        const { done, value } = await reader.read();
+        if (done) break;
+        buffer += decoder.decode(value, { stream: true });
+        let end;
+        while ((end = buffer.indexOf('\n')) >= 0) {
+            const line = JSON.parse(buffer.slice(0, end));
+            buffer = buffer.slice(end + 1);
+            if ('ok' in line) {
+                result = line;
+            } else {
+                status.textContent = MESSAGES.working.replace('{done}', line.done).replace('{total}', line.total);
+            }
+        }
+    }
+    return result;
+}
+
+async function fsAction(action, body) {
+    const response = await fetch('/_api/fs/' + action, {
+        method: 'POST',
+        headers: { 'Content-Type': 'application/json', 'Accept': 'application/x-ndjson' },
+        body: JSON.stringify(body)
+    });
+    // 目标已存在时询问是否覆盖
+    if (response.status === 409 && !body.conflict) {
+        if (confirm(MESSAGES.confirmOverwrite.replace('{error}', await response.text()))) {
+            await fsAction(action, Object.assign({}, body, { conflict: 'overwrite' }));
+        }
+        return;
+    }
+    if (!response.ok) {
+        alert(MESSAGES.actionFailed + ': ' + await response.text());
+        return;
+    }
+    const result = await readProgress(response);
+    if (!result || !result.ok) {
+        alert(MESSAGES.actionFailed + ': ' + (result ? result.error : ''));
+    }
+    location.reload();
+}
+
+function newFolder() {
+    const name = prompt(MESSAGES.promptFolder, '');
+    if (name) fsAction('mkdir', { path: joinPath(CURRENT_PATH, name) });
+}
+
+function renameSelected() {
+    const items = selectedItems();
+    if (items.length !== 1) return;
+    const name = prompt(MESSAGES.promptRename, items[0].dataset.name);
+    if (name && name !== items[0].dataset.name) {
+        fsAction('rename', { path: items[0].dataset.path, name: name });
+    }
+}
+
+function transferSelected(action, message) {
+    const paths = selectedItems().map(function (item) { return item.dataset.path; });
+    if (paths.length === 0) return;
+    const to = prompt(message, CURRENT_PATH);
+    if (to) fsAction(action, { paths: paths, to: to });
+}
+
+function moveSelected() {
+    transferSelected('move', MESSAGES.promptMove);
+}
+
+function copySelected() {
+    transferSelected('copy', MESSAGES.promptCopy);
+}
+
+function deleteSelected() {
+    const paths = selectedItems().map(function (item) { return item.dataset.path; });
+    if (paths.length === 0) return;
+    if (confirm(MESSAGES.confirmDelete.replace('{count}', paths.length))) {
+        fsAction('delete', { paths: paths });
+    }
+}
+
+// 上传到当前目录：同名文件先确认是否覆盖，被覆盖的旧内容由服务保存为历史版本或移入回收站
+//
+// 使用 tus 断点续传协议分块上传，可暂停和继续；上传地址记在 localStorage 中，
+// 刷新页面后重新选择同一文件会从已上传的位置继续
+const TUS_ENDPOINT = '/_tus/';
+const TUS_CHUNK_SIZE = 4 * 1024 * 1024;
+const TUS_RETRIES = 3;
+const uploads = [];
+
+function uploadFiles(files) {
+    if (files.length === 0) return;
+    const existing = new Set(Array.from(document.querySelectorAll('.file-item[data-name]')).map(function (item) {
+        return item.dataset.name;
+    }));
+    const clashes = Array.from(files).map(function (file) { return file.name; }).filter(function (name) {
+        return existing.has(name);
+    });
+    let conflict = 'fail';
+    if (clashes.length > 0) {
+        if (!confirm(MESSAGES.confirmUploadOverwrite.replace('{names}', clashes.join('\n')))) return;
+        conflict = 'overwrite';
+    }
+    for (const file of files) {
+        const upload = createUpload(file, conflict);
+        uploads.push(upload);
+        runUpload(upload);
+    }
+    document.getElementById('upload-input').value = '';
+}
+
+function tusHeaders(extra) {
+    return Object.assign({ 'Tus-Resumable': '1.0.0' }, extra);
+}
+
+// Upload-Metadata 的值是 Base64 编码的 UTF-8 文本
+function encodeMetadata(text) {
+    return btoa(Array.from(new TextEncoder().encode(text), function (byte) {
+        return String.fromCharCode(byte);
+    }).join(''));
+}
+
+async function chunkChecksum(chunk) {
+    const digest = await crypto.subtle.digest('SHA-256', await chunk.arrayBuffer());
+    return 'sha256 ' + btoa(String.fromCharCode.apply(null, new Uint8Array(digest)));
+}
+
+function createUpload(file, conflict) {
+    const row = document.createElement('div');
+    row.className = 'upload-row';
+    row.appendChild(document.createElement('span')).textContent = file.name;
+    const progress = row.appendChild(document.createElement('progress'));
+    progress.max = 1;
+    progress.value = 0;
+    const status = row.appendChild(document.createElement('span'));
+    const pause = row.appendChild(document.createElement('button'));
+    const cancel = row.appendChild(document.createElement('button'));
+    cancel.textContent = MESSAGES.cancelUpload;
+    document.getElementById('upload-list').appendChild(row);
+
+    const upload = {
+        file: file,
+        conflict: conflict,
+        key: 'tus:' + [CURRENT_PATH, file.name, file.size, file.lastModified].join(':'),
+        url: null,
+        paused: false,
+        cancelled: false,
+        done: false,
+        controller: null,
+        row: row,
+        progress: progress,
+        status: status,
+        pause: pause
+    };
+    upload.url = localStorage.getItem(upload.key);
+    pause.onclick = function () {
+        if (upload.paused) {
+            runUpload(upload);
+        } else {
+            upload.paused = true;
+            if (upload.controller) upload.controller.abort();
+            showUpload(upload, null);
+        }
+    };
+    cancel.onclick = function () { cancelUpload(upload); };
+    return upload;
+}
+
+function showUpload(upload, offset) {
+    if (offset !== null) {
+        upload.progress.value = upload.file.size > 0 ? offset / upload.file.size : 1;
+    }
+    upload.pause.textContent = upload.paused ? MESSAGES.resume : MESSAGES.pause;
+    upload.pause.hidden = upload.done;
+    if (upload.done) {
+        upload.status.textContent = MESSAGES.uploadDone;
+    } else if (!upload.paused) {
+        upload.status.textContent = MESSAGES.uploading.replace('{percent}', Math.floor(upload.progress.value * 100));
+    }
+}
+
+// 从服务端记录的位置继续上传，没有可继续的上传时新建
+async function runUpload(upload) {
+    upload.paused = false;
+    showUpload(upload, null);
+    let retries = 0;
+    while (!upload.paused && !upload.cancelled) {
+        try {
+            let offset = await uploadOffset(upload);
+            showUpload(upload, offset);
+            while (offset < upload.file.size && !upload.paused && !upload.cancelled) {
+                offset = await sendChunk(upload, offset);
+                retries = 0;
+                showUpload(upload, offset);
+            }
+            if (offset >= upload.file.size) {
+                finishUpload(upload);
+            }
+            return;
+        } catch (error) {
+            if (upload.paused || upload.cancelled) return;
+            // 网络中断或上一个请求尚未结束时稍后重试，重试前重新查询服务端的位置
+            if (error.retry && retries < TUS_RETRIES) {
+                retries++;
+                await new Promise(function (resolve) { setTimeout(resolve, 1000 * retries); });
+                continue;
+            }
+            upload.paused = true;
+            showUpload(upload, null);
+            upload.status.textContent = MESSAGES.actionFailed + ': ' + error.message;
+            return;
+        }
+    }
+}
+
+async function tusRequest(url, options) {
+    let response;
+    try {
+        response = await fetch(url, options);
+    } catch (error) {
+        error.retry = true;
+        throw error;
+    }
+    if (!response.ok) {
+        const error = new Error(await response.text());
+        error.status = response.status;
+        error.retry = response.status === 409 || response.status === 460 || response.status >= 500;
+        throw error;
+    }
+    return response;
+}
+
+async function uploadOffset(upload) {
+    if (upload.url) {
+        try {
+            const response = await tusRequest(upload.url, { method: 'HEAD', headers: tusHeaders() });
+            return parseInt(response.headers.get('Upload-Offset'), 10);
+        } catch (error) {
+            if (error.status !== 404) throw error;
+            // 上传已过期或已完成，重新开始
+            localStorage.removeItem(upload.key);
+            upload.url = null;
+        }
+    }
+    const metadata = [
+        'filename ' + encodeMetadata(upload.file.name),
+        'dir ' + encodeMetadata(CURRENT_PATH),
+        'conflict ' + encodeMetadata(upload.conflict)
+    ];
+    const response = await tusRequest(TUS_ENDPOINT, {
+        method: 'POST',
+        headers: tusHeaders({
+            'Upload-Length': String(upload.file.size),
+            'Upload-Metadata': metadata.join(',')
+        })
+    });
+    upload.url = response.headers.get('Location');
+    if (upload.file.size > 0) localStorage.setItem(upload.key, upload.url);
+    return 0;
+}
+
+async function sendChunk(upload, offset) {
+    const chunk = upload.file.slice(offset, offset + TUS_CHUNK_SIZE);
+    const headers = tusHeaders({
+        'Content-Type': 'application/offset+octet-stream',
+        'Upload-Offset': String(offset)
+    });
+    // crypto.subtle 仅在 HTTPS 或本机访问时可用
+    if (window.crypto && crypto.subtle) headers['Upload-Checksum'] = await chunkChecksum(chunk);
+    upload.controller = new AbortController();
+    const response = await tusRequest(upload.url, {
+        method: 'PATCH',
+        headers: headers,
+        body: chunk,
+        signal: upload.controller.signal
+    });
+    upload.controller = null;
+    return parseInt(response.headers.get('Upload-Offset'), 10);
+}
+
+function finishUpload(upload) {
+    localStorage.removeItem(upload.key);
+    upload.done = true;
+    showUpload(upload, upload.file.size);
+    if (uploads.every(function (other) { return other.done || other.cancelled; })) {
+        location.reload();
+    }
+}
+
+async function cancelUpload(upload) {
+    upload.cancelled = true;
+    if (upload.controller) upload.controller.abort();
+    upload.row.remove();
+    localStorage.removeItem(upload.key);
+    if (upload.url && !upload.done) {
+        await fetch(upload.url, { method: 'DELETE', headers: tusHeaders() }).catch(function () {});
+    }
+    if (uploads.some(function (other) { return other.done; }) &&
+        uploads.every(function (other) { return other.done || other.cancelled; })) {
+        location.reload();
+    }
+}
+
+// 历史版本：列出文件被覆盖前保存的各个版本，可下载任一版本
+
+function formatSize(bytes) {
+    const units = ['B', 'KB', 'MB', 'GB', 'TB'];
+    let i = 0;
+    while (bytes >= 1024 && i < units.length - 1) { bytes /= 1024; i++; }
+    return (i === 0 ? bytes : bytes.toFixed(1)) + ' ' + units[i];
+}
+
+async function showHistory(event, item) {
+    event.preventDefault();
+    event.stopPropagation();
+    const href = item.getAttribute('href');
+    const response = await fetch(href + '?versions');
+    if (!response.ok) {
+        alert(MESSAGES.actionFailed + ': ' + await response.text());
+        return;
+    }
+    const data = await response.json();
+    document.getElementById('history-title').textContent = '🕘 ' + MESSAGES.historyTitle.replace('{name}', item.dataset.name);
+    const rows = document.getElementById('history-rows');
+    rows.replaceChildren();
+    data.versions.forEach(function (version) {
+        const row = document.createElement('tr');
+        [version.saved, version.modified].forEach(function (time) {
+            row.appendChild(document.createElement('td')).textContent = new Date(time * 1000).toLocaleString();
+        });
+        row.appendChild(document.createElement('td')).textContent = formatSize(version.size);
+        const link = document.createElement('a');
+        link.href = href + '?version=' + encodeURIComponent(version.id);
+        link.textContent = '⬇️';
+        row.appendChild(document.createElement('td')).appendChild(link);
+        rows.appendChild(row);
+    });
+    document.getElementById('history-empty').hidden = data.versions.length > 0;
+    document.getElementById('history-panel').hidden = false;
+}
+
+function closeHistory() {
+    document.getElementById('history-panel').hidden = true;
+}
+
+// 文件详情：大小、修改时间和各算法的校验值，打开时自动计算 SHA-256，其他算法点击后计算
+
+const CHECKSUM_ALGORITHMS = ['sha256', 'sha1', 'md5', 'blake3'];
+
+function showDetails(event, item) {
+    event.preventDefault();
+    event.stopPropagation();
+    const href = item.getAttribute('href');
+    const modified = item.dataset.modified;
+    document.getElementById('details-title').textContent = 'ℹ️ ' + item.dataset.name;
+    document.getElementById('details-size').textContent = item.dataset.size;
+    document.getElementById('details-modified').textContent = modified ? new Date(modified * 1000).toLocaleString() : '';
+    const rows = document.getElementById('details-checksums');
+    rows.replaceChildren();
+    CHECKSUM_ALGORITHMS.forEach(function (algorithm) {
+        const row = rows.appendChild(document.createElement('tr'));
+        row.appendChild(document.createElement('th')).textContent = algorithm.toUpperCase();
+        const cell = row.appendChild(document.createElement('td'));
+        const button = cell.appendChild(document.createElement('button'));
+        button.textContent = MESSAGES.compute;
+        button.onclick = function () { computeChecksum(href, algorithm, cell); };
+        if (algorithm === 'sha256') computeChecksum(href, algorithm, cell);
+    });
+    document.getElementById('details-panel').hidden = false;
+}
+
+async function computeChecksum(href, algorithm, cell) {
+    cell.textContent = MESSAGES.computing;
+    const response = await fetch(href + '?hash=' + algorithm);
+    const text = await response.text();
+    if (!response.ok) {
+        cell.textContent = text;
+        return;
+    }
+    const code = document.createElement('code');
+    code.textContent = text.split(' ')[0];
+    cell.replaceChildren(code);
+}
+
+function closeDetails() {
+    document.getElementById('details-panel').hidden = true;
+}
+
+document.addEventListener('keydown', function (event) {
+    if (event.key !== 'Escape') return;
+    if (document.getElementById('history-panel')) closeHistory();
+    if (document.getElementById('details-panel')) closeDetails();
+});
+
+document.addEventListener('DOMContentLoaded', function () {
+    if (typeof WRITABLE === 'undefined' || !WRITABLE) return;
+    const menu = document.getElementById('context-menu');
+    // 右键菜单作用于当前选择，右键未选中的项时只选中该项
+    document.addEventListener('contextmenu', function (event) {
+        const item = event.target.closest('.file-item[data-path]');
+        if (!item) return;
+        event.preventDefault();
+        if (!item.classList.contains('selected')) {
+            clearSelection();
+            item.classList.add('selected');
+            updateSelection();
+        }
+        menu.querySelector('[data-single]').disabled = selectedItems().length !== 1;
+        menu.style.left = event.pageX + 'px';
+        menu.style.top = event.pageY + 'px';
+        menu.hidden = false;
+    });
+    document.addEventListener('click', function () { menu.hidden = true; });
+    document.addEventListener('keydown', function (event) {
+        if (event.key === 'Escape') {
+            menu.hidden = true;
+            clearSelection();
+        }
+    });
+    updateSelection();
+});
+
+// 空间占用（?du）：可排序的表格和按大小划分面积的矩形树图
+
+function sortUsage(key) {
+    const rows = document.getElementById('usage-rows');
+    const header = document.querySelector('.usage-table th[data-sort="' + key + '"]');
+    const ascending = header.dataset.order !== 'asc';
+    document.querySelectorAll('.usage-table th[data-sort]').forEach(function (th) { delete th.dataset.order; });
+    header.dataset.order = ascending ? 'asc' : 'desc';
+    const items = Array.from(rows.querySelectorAll('tr[data-name]'));
+    items.sort(function (a, b) {
+        const result = key === 'name'
+            ? a.dataset.name.localeCompare(b.dataset.name)
+            : Number(a.dataset[key]) - Number(b.dataset[key]);
+        return ascending ? result : -result;
+    });
+    items.forEach(function (row) { rows.appendChild(row); });
+}
+
+// 方形化树图（squarified treemap）：逐个加入当前行，行内最差长宽比变差时另起一行
+function worstRatio(row, side) {
+    const sum = row.reduce(function (total, item) { return total + item.area; }, 0);
+    const max = Math.max.apply(null, row.map(function (item) { return item.area; }));
+    const min = Math.min.apply(null, row.map(function (item) { return item.area; }));
+    return Math.max(side * side * max / (sum * sum), sum * sum / (side * side * min));
+}
+
+function layoutRow(row, rect, tiles) {
+    const sum = row.reduce(function (total, item) { return total + item.area; }, 0);
+    if (rect.w >= rect.h) {
+        const width = sum / rect.h;
+        let y = rect.y;
+        row.forEach(function (item) {
+            tiles.push({ item: item, x: rect.x, y: y, w: width, h: item.area / width });
+            y += item.area / width;
+        });
+        return { x: rect.x + width, y: rect.y, w: rect.w - width, h: rect.h };
+    }
+    const height = sum / rect.w;
+    let x = rect.x;
+    row.forEach(function (item) {
+        tiles.push({ item: item, x: x, y: rect.y, w: item.area / height, h: height });
+        x += item.area / height;
+    });
+    return { x: rect.x, y: rect.y + height, w: rect.w, h: rect.h - height };
+}
+
+function squarify(items, rect) {
+    const tiles = [];
+    let row = [];
+    items.forEach(function (item) {
+        const side = Math.min(rect.w, rect.h);
+        if (row.length > 0 && worstRatio(row.concat([item]), side) > worstRatio(row, side)) {
+            rect = layoutRow(row, rect, tiles);
+            row = [];
+        }
+        row.push(item);
+    });
+    if (row.length > 0) layoutRow(row, rect, tiles);
+    return tiles;
+}
+
+function renderTreemap() {
+    const container = document.getElementById('treemap');
+    const entries = USAGE.filter(function (entry) { return entry.size > 0; });
+    const total = entries.reduce(function (sum, entry) { return sum + entry.size; }, 0);
+    container.replaceChildren();
+    container.hidden = total === 0;
+    if (total === 0) return;
+    const width = container.clientWidth;
+    const height = container.clientHeight;
+    const items = entries.map(function (entry) {
+        return { entry: entry, area: entry.size * width * height / total };
+    });
+    squarify(items, { x: 0, y: 0, w: width, h: height }).forEach(function (tile) {
+        const entry = tile.item.entry;
+        const link = document.createElement('a');
+        link.className = 'treemap-tile ' + (entry.is_dir ? 'folder' : 'file');
+        link.href = entry.is_dir ? entry.href + '?du' : entry.href;
+        link.title = entry.name + ' - ' + entry.size_display;
+        link.style.left = tile.x + 'px';
+        link.style.top = tile.y + 'px';
+        link.style.width = tile.w + 'px';
+        link.style.height = tile.h + 'px';
+        if (tile.w > 60 && tile.h > 36) {
+            link.appendChild(document.createElement('strong')).textContent = entry.name;
+            link.appendChild(document.createElement('span')).textContent = entry.size_display;
+        }
+        container.appendChild(link);
+    });
+}
+
+document.addEventListener('DOMContentLoaded', function () {
+    if (typeof USAGE === 'undefined') return;
+    document.querySelectorAll('.usage-table th[data-sort]').forEach(function (th) {
+        th.addEventListener('click', function () { sortUsage(th.dataset.sort); });
+    });
+    renderTreemap();
+    let resizing;
+    window.addEventListener('resize', function () {
+        clearTimeout(resizing);
+        resizing = setTimeout(renderTreemap, 200);
+    });
+});
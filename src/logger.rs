@@ -0,0 +1,107 @@
+use std::{collections::VecDeque, sync::Mutex};
+use tokio::{
+    fs::{File, OpenOptions},
+    io::AsyncWriteExt,
+    sync::{broadcast, mpsc, oneshot},
+};
+
+use crate::i18n::Lang;
+use crate::tr;
+
+/// 访问日志文件
+pub const LOG_FILE: &str = "access.log";
+
+/// 内存中保留的最近日志行数，供管理页面查看
+const RECENT_LINES: usize = 200;
+
+enum LogMsg {
+    Line(String),
+    Flush(oneshot::Sender<()>),
+}
+
+/// 访问日志写入器
+///
+/// 所有日志行通过通道交给同一个后台任务按顺序追加到 `access.log`，
+/// 退出前调用 [`FileLogger::flush`] 等待已提交的日志全部落盘。
+///
+/// 无论日志模式如何，每一行都会通过 [`FileLogger::publish`] 保留最近的若干行并推送给订阅者。
+pub struct FileLogger {
+    tx: mpsc::UnboundedSender<LogMsg>,
+    recent: Mutex<VecDeque<String>>,
+    live: broadcast::Sender<String>,
+}
+
+impl FileLogger {
+    /// 创建写入器并启动后台写入任务（首次写入时才打开文件）
+    pub fn new(lang: Lang) -> Self {
+        let (tx, rx) = mpsc::unbounded_channel();
+        tokio::spawn(write_loop(rx, lang));
+        FileLogger {
+            tx,
+            recent: Mutex::new(VecDeque::with_capacity(RECENT_LINES)),
+            live: broadcast::channel(RECENT_LINES).0,
+        }
+    }
+
+    /// 保留一行日志并推送给订阅者（不写入文件）
+    pub fn publish(&self, line: &str) {
+        let line = line.trim_end().to_string();
+        let mut recent = self.recent.lock().unwrap();
+        if recent.len() == RECENT_LINES {
+            recent.pop_front();
+        }
+        recent.push_back(line.clone());
+        let _ = self.live.send(line);
+    }
+
+    /// 最近的日志行，以及之后新日志的订阅
+    pub fn subscribe(&self) -> (Vec<String>, broadcast::Receiver<String>) {
+        let recent = self.recent.lock().unwrap();
+        (recent.iter().cloned().collect(), self.live.subscribe())
+    }
+
+    /// 提交一行日志
+    pub fn write(&self, line: String) {
+        let _ = self.tx.send(LogMsg::Line(line));
+    }
+
+    /// 等待此前提交的日志全部写入磁盘
+    pub async fn flush(&self) {
+        let (done_tx, done_rx) = oneshot::channel();
+        if self.tx.send(LogMsg::Flush(done_tx)).is_ok() {
+            let _ = done_rx.await;
+        }
+    }
+}
+
+async fn write_loop(mut rx: mpsc::UnboundedReceiver<LogMsg>, lang: Lang) {
+    let mut file: Option<File> = None;
+    while let Some(msg) = rx.recv().await {
+        match msg {
+            LogMsg::Line(line) => {
+                if file.is_none() {
+                    match OpenOptions::new().append(true).create(true).open(LOG_FILE).await {
+                        Ok(f) => file = Some(f),
+                        Err(e) => {
+                            eprintln!(
+                                "❌ \x1B[91m{}\x1B[0m",
+                                tr!(lang, "startup.log_open_failed", path = LOG_FILE, error = e)
+                            );
+                            continue;
+                        }
+                    }
+                }
+                if let Some(f) = file.as_mut() {
+                    let _ = f.write_all(line.as_bytes()).await;
+                }
+            }
+            LogMsg::Flush(done) => {
+                if let Some(f) = file.as_mut() {
+                    let _ = f.flush().await;
+                    let _ = f.sync_all().await;
+                }
+                let _ = done.send(());
+            }
+        }
+    }
+}
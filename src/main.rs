@@ -1,26 +1,126 @@
-use clap::Parser;
+mod access;
+mod admin;
+mod auth;
+mod banner;
+mod checksum;
+mod du;
+mod duplicates;
+mod fsapi;
+mod http3;
+mod i18n;
+mod ipfilter;
+mod limit;
+mod listener;
+mod listing;
+mod logger;
+mod metrics;
+mod monitor;
+mod qr;
+mod receive;
+mod recent;
+mod send;
+mod share;
+mod shutdown;
+mod stats;
+mod tls;
+mod trash;
+mod tus;
+mod upload;
+mod util;
+mod versions;
+
+use clap::{Parser, Subcommand};
 use hyper::{
     service::{make_service_fn, service_fn},
-    Body, Request, Response, Server, StatusCode,
+    header, header::HeaderValue, Body, Method, Request, Response, StatusCode,
 };
 use mime_guess::from_path;
 use percent_encoding::percent_decode_str;
 use std::{
     convert::Infallible,
-    fs::OpenOptions,
-    io::Write,
-    net::{SocketAddr, TcpListener},
-    path::PathBuf,
+    net::SocketAddr,
+    path::{Path, PathBuf},
     sync::Arc,
-    future::Future,
-    pin::Pin,
+    time::Instant,
 };
 use chrono::Local;
-use tokio::fs::{self, File};
-use tokio::sync::Mutex;
+use tokio::fs::File;
+use tokio::sync::Notify;
 use tokio_util::io::ReaderStream;
 
-type SharedLogger = Arc<Mutex<()>>;
+use access::AccessRules;
+use auth::AuthConfig;
+use checksum::HashCache;
+use du::UsageCache;
+use duplicates::DuplicateFinder;
+use i18n::Lang;
+use ipfilter::{IpRules, Rejection};
+use ipnet::IpNet;
+use limit::{ConnectionBudget, Limits};
+use listener::{BindSpec, Conn, ConnectionCaps, ConnectionLimits, Listener};
+use listing::{Asset, JsonRenderer, Listing, ListingRenderer, PlainRenderer, TemplateRenderer, Theme};
+use logger::FileLogger;
+use monitor::Monitor;
+use recent::RecentFiles;
+use share::ShareStore;
+use stats::{CountedBody, Stats};
+use tls::TlsIdentity;
+use trash::Trash;
+use tus::TusStore;
+use util::{normalize_relative, text_response};
+use versions::VersionStore;
+
+type SharedLogger = Arc<FileLogger>;
+
+/// 所有请求共享的服务状态
+pub(crate) struct AppState {
+    pub root_dir: PathBuf,
+    pub log_mode: String,
+    pub logger: SharedLogger,
+    /// 默认语言，HTML 页面和错误信息按 Accept-Language 协商
+    pub lang: Lang,
+    /// 目录列表渲染器，由 --template / --pretty 决定
+    pub renderer: Box<dyn ListingRenderer>,
+    pub share_store: ShareStore,
+    pub auth: AuthConfig,
+    /// 路径访问规则（排除、隐藏文件、目录规则文件）
+    pub access: AccessRules,
+    /// --allow / --deny 指定的全局 IP 规则，可被目录规则文件覆盖
+    pub ip_rules: IpRules,
+    /// 受信任的反向代理，仅信任来自这些地址的转发头
+    pub trusted_proxies: Vec<IpNet>,
+    /// 请求频率、带宽和并发下载限制
+    pub limits: Limits,
+    pub stats: Arc<Stats>,
+    /// 连接与传输登记表，供管理页面查看和操作
+    pub monitor: Arc<Monitor>,
+    /// 对外链接使用的协议，启用 TLS 时为 https
+    pub scheme: &'static str,
+    /// 指定了 --admin-bind 时健康检查、指标和管理页面只在管理监听器上提供
+    pub admin_listener: bool,
+    /// 指标中列出的请求最多的路径数
+    pub metrics_top: usize,
+    /// 是否开启文件管理接口（--allow-write）
+    pub allow_write: bool,
+    /// 回收站，--no-trash 时为 None，删除直接生效
+    pub trash: Option<Trash>,
+    /// 历史版本存储，未指定 --keep-versions 时为 None
+    pub versions: Option<VersionStore>,
+    /// 断点续传的暂存区，未开启 --allow-write 时为 None
+    pub tus: Option<TusStore>,
+    /// 单个上传文件的大小上限（--max-upload-size）
+    pub max_upload_size: u64,
+    /// 文件摘要缓存，供 ?hash=、?manifest= 和摘要响应头使用
+    pub hashes: HashCache,
+    /// 文件下载响应是否带 Repr-Digest / Digest 头（--digest-header）
+    pub digest_header: bool,
+    /// 目录占用统计缓存，供 ?du 使用
+    pub usage: UsageCache,
+    /// 重复文件查找的后台任务（/_tools/duplicates）
+    pub duplicates: DuplicateFinder,
+    /// 最近修改的文件（/_recent）
+    pub recent: RecentFiles,
+}
 
 /// 命令行参数解析结构 
 #[derive(Parser, Debug)]
@@ -35,694 +135,990 @@ v1.1.0
     Author: ZYG 
     Email:  zyg.2005@qq.com
     Repo:   https://github.com/Creeeeeeeeeeper/local-file-server
-"
+",
+    group = clap::ArgGroup::new("tls").args(["tls_cert", "tls_self_signed"])
 )]
 struct Args {
     /// 起始端口号（默认 8080）
-    #[arg(short, long, default_value_t = 8080)]
+    #[arg(short, long, default_value_t = 8080, global = true)]
     port: u16,
 
     /// 根目录（默认当前目录） [default: current directory]
-    #[arg(short, long)]
+    #[arg(short, long, global = true)]
     root: Option<String>,
 
     /// 日志模式: none / console / file / both
-    #[arg(long, default_value = "none")]
+    #[arg(long, default_value = "none", global = true)]
     log: String,
 
     /// 美化输出 （默认 false）[default: false]
     #[arg(long, default_value_t = false)]
     pretty: bool,
 
+    /// 使用自定义模板渲染目录列表（minijinja 语法），优先于 --pretty
+    #[arg(long, value_name = "FILE", conflicts_with = "template_dir")]
+    template: Option<String>,
+
+    /// 模板目录：listing.html 为目录列表模板，static/ 下的文件通过 /_theme/ 提供
+    #[arg(long, value_name = "DIR")]
+    template_dir: Option<String>,
+
+    /// 页面配色: auto（跟随系统）/ light / dark
+    #[arg(long, value_enum, default_value_t = Theme::Auto)]
+    theme: Theme,
+
     /// 是否允许局域网访问（默认 false）[default: false]
-    #[arg(long, default_value_t = false)]
+    #[arg(long, default_value_t = false, global = true)]
     public: bool,
 
-    /// Enable English output.（默认 false）[default: false]
-    #[arg(long, default_value_t = false)]
+    /// 监听地址，可多次指定：IP（端口取 --port）、IP:端口（端口 0 为系统分配）、
+    /// [IPv6]:端口 或 unix:/path/to.sock；指定后忽略 --public
+    #[arg(short, long, value_name = "ADDR", global = true)]
+    bind: Vec<BindSpec>,
+
+    /// 界面语言: zh-CN / en（默认取自 LC_ALL / LANG 环境变量）
+    #[arg(long, value_name = "LANG", global = true)]
+    lang: Option<Lang>,
+
+    /// Enable English output, same as --lang en.（默认 false）[default: false]
+    #[arg(long, default_value_t = false, global = true, conflicts_with = "lang")]
     en: bool,
+
+    /// 数据目录，保存分享链接等状态（默认系统本地数据目录下的 file_server）
+    #[arg(long, global = true)]
+    data_dir: Option<String>,
+
+    /// 退出时等待进行中请求完成的最长时间，例如 30s、2m
+    #[arg(long, default_value = "30s", value_parser = util::parse_duration, global = true)]
+    drain_timeout: std::time::Duration,
+
+    /// 只允许这些地址访问（CIDR 或 IP，例如 192.168.0.0/16），可多次指定
+    #[arg(long, value_name = "CIDR", value_parser = ipfilter::parse_net)]
+    allow: Vec<IpNet>,
+
+    /// 拒绝这些地址访问（CIDR 或 IP），可多次指定，优先于 --allow
+    #[arg(long, value_name = "CIDR", value_parser = ipfilter::parse_net)]
+    deny: Vec<IpNet>,
+
+    /// 受信任的反向代理（CIDR 或 IP），只有来自这些地址的 X-Forwarded-For / Forwarded 头才会被采用
+    #[arg(long, value_name = "CIDR", value_parser = ipfilter::parse_net)]
+    trusted_proxy: Vec<IpNet>,
+
+    /// 每个客户端 IP 每秒最多请求数，超过时返回 429
+    #[arg(long, value_name = "N")]
+    rate_limit: Option<f64>,
+
+    /// 请求频率限制允许的突发请求数（默认等于 --rate-limit）
+    #[arg(long, value_name = "N", requires = "rate_limit")]
+    rate_burst: Option<u32>,
+
+    /// 所有下载共享的带宽上限（每秒），例如 10M
+    #[arg(long, value_name = "SIZE", value_parser = util::parse_size)]
+    max_bandwidth: Option<u64>,
+
+    /// 每个连接的带宽上限（每秒），例如 2M
+    #[arg(long, value_name = "SIZE", value_parser = util::parse_size)]
+    conn_bandwidth: Option<u64>,
+
+    /// 同时进行的文件下载数上限，超过时返回 429
+    #[arg(long, value_name = "N")]
+    max_concurrent_downloads: Option<usize>,
+
+    /// 排除匹配的路径（gitignore 语法，相对根目录），可多次指定
+    #[arg(long, value_name = "PATTERN")]
+    exclude: Vec<String>,
+
+    /// 同时遵循目录中的 .gitignore 文件
+    #[arg(long)]
+    gitignore: bool,
+
+    /// 显示以 . 开头的隐藏文件（默认隐藏且无法访问）
+    #[arg(long)]
+    show_hidden: bool,
+
+    /// 管理令牌，设置后管理操作需携带该令牌（默认仅允许本机执行管理操作）
+    #[arg(long)]
+    admin_token: Option<String>,
+
+    /// 开启文件管理接口（/_api/fs）：新建目录、重命名、移动、复制和删除，与管理操作一样需要授权
+    #[arg(long)]
+    allow_write: bool,
+
+    /// 删除时直接删除，不移入回收站
+    #[arg(long)]
+    no_trash: bool,
+
+    /// 回收站中的项保留多久后自动清除，例如 7d；0 表示不按时间清除
+    #[arg(long, value_name = "DURATION", default_value = "30d", value_parser = util::parse_duration)]
+    trash_max_age: std::time::Duration,
+
+    /// 回收站总大小上限，超过时从最早删除的项开始清除，例如 10G
+    #[arg(long, value_name = "SIZE", value_parser = util::parse_size)]
+    trash_max_size: Option<u64>,
+
+    /// 通过上传覆盖文件时保留旧内容，每个文件最多保留 N 个历史版本（相同内容只存一份）
+    #[arg(long, value_name = "N", requires = "allow_write")]
+    keep_versions: Option<usize>,
+
+    /// 单个上传文件的大小上限（文件管理接口和断点续传），例如 500M、16G
+    #[arg(long, value_name = "SIZE", default_value = "16G", value_parser = util::parse_size)]
+    max_upload_size: u64,
+
+    /// 未完成的断点续传上传（/_tus/）保留多久，超过后清除已收到的数据，例如 24h
+    #[arg(long, value_name = "DURATION", default_value = "24h", value_parser = util::parse_duration)]
+    upload_expiry: std::time::Duration,
+
+    /// 最近修改页面（/_recent）默认列出多长时间内修改的文件，例如 24h、7d
+    #[arg(long, value_name = "DURATION", default_value = "7d", value_parser = util::parse_duration)]
+    recent_window: std::time::Duration,
+
+    /// 最近修改页面（/_recent）默认最多列出的文件数（不超过 1000）
+    #[arg(long, value_name = "N", default_value_t = 100)]
+    recent_limit: usize,
+
+    /// 文件下载响应附带 Repr-Digest 和 Digest 头（SHA-256），首次下载需先读完整个文件计算摘要
+    #[arg(long, default_value_t = false)]
+    digest_header: bool,
+
+    /// TLS 证书（PEM，可包含证书链），与 --tls-key 一起启用 HTTPS，并通过 ALPN 协商 HTTP/2
+    #[arg(long, value_name = "FILE", requires = "tls_key")]
+    tls_cert: Option<PathBuf>,
+
+    /// TLS 私钥（PEM）
+    #[arg(long, value_name = "FILE", requires = "tls_cert")]
+    tls_key: Option<PathBuf>,
+
+    /// 使用启动时生成的自签名证书启用 HTTPS（浏览器会提示证书不受信任）
+    #[arg(long)]
+    tls_self_signed: bool,
+
+    /// 同时在相同的 UDP 端口上提供 HTTP/3，并通过 Alt-Svc 响应头告知浏览器；需要启用 TLS
+    #[arg(long, requires = "tls")]
+    http3: bool,
+
+    /// 管理监听地址（写法同 --bind），可多次指定；指定后 /_health、/_metrics 与 /_admin
+    /// 只在这些地址上提供，不再出现在主服务上
+    #[arg(long, value_name = "ADDR")]
+    admin_bind: Vec<BindSpec>,
+
+    /// /_metrics 中列出的请求最多的路径数
+    #[arg(long, value_name = "N", default_value_t = 10)]
+    metrics_top: usize,
+
+    #[command(flatten)]
+    connection: ConnectionLimits,
+
+    #[command(subcommand)]
+    command: Option<Command>,
+}
+
+/// 子命令
+#[derive(Subcommand, Debug)]
+enum Command {
+    /// 创建、列出或撤销带签名和有效期的分享链接
+    Share(ShareArgs),
+    /// 一次性发送模式：只提供一个文件（目录自动打包为 zip），下载完成后退出
+    Send(SendArgs),
+    /// 接收模式：临时投递箱，只提供上传页面，收到的文件保存到指定目录
+    Receive(ReceiveArgs),
+    /// 查找内容相同的文件，结果以 JSON 输出
+    Duplicates(DuplicatesArgs),
+}
+
+/// `duplicates` 子命令参数
+#[derive(clap::Args, Debug)]
+pub struct DuplicatesArgs {
+    /// 要扫描的目录（须位于根目录下，默认为根目录）
+    path: Option<String>,
+
+    /// 忽略小于该大小的文件，例如 1M（默认只忽略空文件）
+    #[arg(long)]
+    min_size: Option<String>,
+}
+
+/// `receive` 子命令参数
+#[derive(clap::Args, Debug)]
+pub struct ReceiveArgs {
+    /// 保存收到文件的目录
+    dir: String,
+
+    /// 单个文件大小上限，例如 500M、2G
+    #[arg(long, default_value = "2G")]
+    max_size: Option<String>,
+
+    /// 收到第一次上传后退出
+    #[arg(long)]
+    once: bool,
+}
+
+/// `send` 子命令参数
+#[derive(clap::Args, Debug)]
+pub struct SendArgs {
+    /// 要发送的文件或目录
+    path: String,
+
+    /// 完成多少次下载后退出
+    #[arg(short = 'n', long, default_value_t = 1)]
+    downloads: u32,
+
+    /// 超时时间，例如 10m、1h（默认不超时）
+    #[arg(short, long)]
+    timeout: Option<String>,
+}
+
+/// `share` 子命令参数
+#[derive(clap::Args, Debug)]
+#[command(group = clap::ArgGroup::new("action").required(true).args(["path", "list", "revoke"]))]
+pub struct ShareArgs {
+    /// 要分享的文件（须位于根目录下）
+    path: Option<String>,
+
+    /// 有效期，例如 30m、24h、7d
+    #[arg(short, long, default_value = "24h")]
+    expires: String,
+
+    /// 最多下载次数（默认不限）
+    #[arg(short = 'n', long)]
+    max_downloads: Option<u32>,
+
+    /// 访问密码（可选）
+    #[arg(long)]
+    password: Option<String>,
+
+    /// 生成链接使用的地址（默认 http://127.0.0.1:<port>）
+    #[arg(long)]
+    base_url: Option<String>,
+
+    /// 列出所有分享链接
+    #[arg(long)]
+    list: bool,
+
+    /// 撤销指定 ID 的分享链接
+    #[arg(long, value_name = "ID")]
+    revoke: Option<String>,
 }
 
 #[tokio::main]
 async fn main() {
     let args = Args::parse();
+    let lang = if args.en {
+        Lang::En
+    } else {
+        args.lang.unwrap_or_else(Lang::from_env)
+    };
 
     let root_dir = match args.root {
         Some(p) => PathBuf::from(p),
         None => std::env::current_dir().unwrap(),
     };
-    let root_dir = Arc::new(root_dir);
+    let data_dir = resolve_data_dir(args.data_dir);
+
+    if let Some(Command::Share(cmd)) = &args.command {
+        share::run_share_command(cmd, &root_dir, &data_dir, args.port, lang);
+        return;
+    }
 
     let log_mode = Arc::new(args.log);
-    let logger = Arc::new(Mutex::new(()));
-
-    let (addr, actual_port) = find_available_port(args.port, 20, args.public);
-
-    if args.en {
-        println!("🚀 \x1B[92mFile Server has started!\x1B[0m");
-        println!("📁 Root directory: {}", root_dir.display());
-        println!("🌐 Address: http://127.0.0.1:{}", actual_port);
-        println!("🔌 Port: {}", actual_port);
-        if *log_mode == "none".to_string() {
-            println!("\x1B[2m📝 Log mode: {}\x1B[0m", log_mode);
-        } else {
-            println!("📝 Log mode: {}", log_mode);
-        }
-        if args.public {
-            println!("🖥️ Allowing public access");
-        } else {
-            println!("🖥️ Only allowing local access");
-        }
-        println!();
-        println!("📖 Use file_server.exe -h or --help to view help");
-        println!();
+    let logger = Arc::new(FileLogger::new(lang));
+
+    if let Some(Command::Send(cmd)) = &args.command {
+        let listeners = bind_or_exit(&args.bind, args.port, args.public, lang);
+        send::run_send(cmd, listeners, log_mode, logger.clone(), args.drain_timeout, &args.connection, lang).await;
+        logger.flush().await;
+        return;
+    }
+    if let Some(Command::Receive(cmd)) = &args.command {
+        let listeners = bind_or_exit(&args.bind, args.port, args.public, lang);
+        receive::run_receive(cmd, listeners, log_mode, logger.clone(), args.drain_timeout, &args.connection, lang).await;
+        logger.flush().await;
+        return;
+    }
+
+    let access = match AccessRules::new(&root_dir, &args.exclude, args.show_hidden, args.gitignore, lang) {
+        Ok(mut access) => {
+            // 数据目录（分享密钥）和日志文件位于根目录下时不对外提供
+            access.hide(&data_dir);
+            access.hide(Path::new(logger::LOG_FILE));
+            access
+        }
+        Err(e) => {
+            eprintln!("❌ \x1B[91m{}\x1B[0m", tr!(lang, "access.exclude_failed", error = e));
+            std::process::exit(1);
+        }
+    };
+    if let Some(Command::Duplicates(cmd)) = &args.command {
+        duplicates::run_duplicates_command(cmd, &root_dir, access, lang).await;
+        return;
+    }
+    let share_store = match ShareStore::open(&data_dir) {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!(
+                "❌ \x1B[91m{}\x1B[0m",
+                tr!(lang, "startup.share_store_failed", path = data_dir.display(), error = e)
+            );
+            std::process::exit(1);
+        }
+    };
+    let trash = if args.no_trash {
+        None
+    } else {
+        let max_age = Some(args.trash_max_age).filter(|age| !age.is_zero());
+        match Trash::open(&data_dir, max_age, args.trash_max_size) {
+            Ok(trash) => Some(trash),
+            Err(e) => {
+                eprintln!(
+                    "❌ \x1B[91m{}\x1B[0m",
+                    tr!(lang, "trash.open_failed", path = data_dir.join("trash").display(), error = e)
+                );
+                std::process::exit(1);
+            }
+        }
+    };
+    let versions = match args.keep_versions.filter(|keep| *keep > 0) {
+        Some(keep) => match VersionStore::open(&data_dir, keep) {
+            Ok(store) => Some(store),
+            Err(e) => {
+                eprintln!(
+                    "❌ \x1B[91m{}\x1B[0m",
+                    tr!(lang, "versions.open_failed", path = data_dir.join("versions").display(), error = e)
+                );
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+    let tus = if args.allow_write {
+        match TusStore::open(&data_dir, args.upload_expiry) {
+            Ok(store) => Some(store),
+            Err(e) => {
+                eprintln!(
+                    "❌ \x1B[91m{}\x1B[0m",
+                    tr!(lang, "tus.open_failed", path = data_dir.join("tus").display(), error = e)
+                );
+                std::process::exit(1);
+            }
+        }
     } else {
-        println!("🚀 \x1B[92m文件服务器已启动!\x1B[0m");
-        println!("📁 根目录: {}", root_dir.display());
-        println!("🌐 地址: http://127.0.0.1:{}", actual_port);
-        println!("🔌 端口: {}", actual_port);
-        if *log_mode == "none".to_string() {
-            println!("\x1B[2m📝 日志模式: {}\x1B[0m", log_mode);
-        } else {
-            println!("📝 日志模式: {}", log_mode);
-        }
-        if args.public {
-            println!("🖥️ 允许局域网访问");
-        } else {
-            println!("🖥️ 仅允许本机访问");
-        }
-        println!();
-        println!("📖 使用 file_server.exe -h 或 --help 查看帮助");
-        println!();
-    }
-
-    let pretty = args.pretty;
-    let make_service = make_service_fn(move |_conn| {
-    let root_dir = root_dir.clone();
-    let log_mode = log_mode.clone();
-    let logger = logger.clone();
-    async move {
-        Ok::<_, Infallible>(service_fn(move |req| {
-            let root_dir = root_dir.clone();
-            let log_mode = log_mode.clone();
-            let logger = logger.clone();
-
-            // 👇 把两个分支都包装为 Box<dyn Future> 注意 async + if/else 中的坑
-            if pretty {
-                Box::pin(handle_request_pretty(req, root_dir, log_mode, logger))
-                    as Pin<Box<dyn Future<Output = Result<Response<Body>, Infallible>> + Send>>
-            } else {
-                Box::pin(handle_request(req, root_dir, log_mode, logger))
-                    as Pin<Box<dyn Future<Output = Result<Response<Body>, Infallible>> + Send>>
+        None
+    };
+    let renderer: Box<dyn ListingRenderer> = {
+        let loaded = match (&args.template, &args.template_dir) {
+            (Some(file), _) => Some((file, TemplateRenderer::from_file(Path::new(file), args.theme))),
+            (None, Some(dir)) => Some((dir, TemplateRenderer::from_dir(Path::new(dir), args.theme))),
+            (None, None) => None,
+        };
+        match loaded {
+            Some((_, Ok(renderer))) => Box::new(renderer),
+            Some((path, Err(e))) => {
+                eprintln!("❌ \x1B[91m{}\x1B[0m", tr!(lang, "startup.template_failed", path = path, error = e));
+                std::process::exit(1);
+            }
+            None if args.pretty => Box::new(TemplateRenderer::builtin(args.theme)),
+            None => Box::new(PlainRenderer),
+        }
+    };
+
+    let listeners = bind_or_exit(&args.bind, args.port, args.public, lang);
+    let tcp_addrs: Vec<SocketAddr> = listeners.iter().filter_map(|l| l.tcp_addr()).collect();
+    let admin_listeners = if args.admin_bind.is_empty() {
+        Vec::new()
+    } else {
+        bind_or_exit(&args.admin_bind, 0, false, lang)
+    };
+    let identity = match (&args.tls_cert, &args.tls_key) {
+        (Some(cert), Some(key)) => Some(TlsIdentity::load(cert, key)),
+        _ if args.tls_self_signed => {
+            let ips: Vec<_> = tcp_addrs.iter().flat_map(|addr| banner::reachable_ips(*addr)).collect();
+            Some(TlsIdentity::self_signed(&ips))
+        }
+        _ => None,
+    };
+    let tls = identity
+        .map(|identity| -> Result<_, String> {
+            let identity = identity?;
+            let config = identity.server_config(tls::TCP_ALPN)?;
+            Ok((identity, Arc::new(config)))
+        })
+        .transpose();
+    let (identity, tls_config) = match tls {
+        Ok(Some((identity, config))) => (Some(identity), Some(config)),
+        Ok(None) => (None, None),
+        Err(e) => {
+            eprintln!("❌ \x1B[91m{}\x1B[0m", tr!(lang, "tls.load_failed", error = e));
+            std::process::exit(1);
+        }
+    };
+    let endpoints = match identity {
+        Some(identity) if args.http3 => {
+            match http3::bind(&tcp_addrs, &identity, args.connection.idle_timeout) {
+                Ok(endpoints) => endpoints,
+                Err(e) => {
+                    eprintln!("❌ \x1B[91m{}\x1B[0m", tr!(lang, "tls.http3_failed", error = e));
+                    std::process::exit(1);
+                }
             }
-        }))
+        }
+        _ => Vec::new(),
+    };
+    let scheme = if tls_config.is_some() { "https" } else { "http" };
+    let urls = banner::listener_urls(&listeners, scheme, "/");
+    let mut ports: Vec<String> = Vec::new();
+    for port in listeners.iter().filter_map(|l| l.tcp_addr()).map(|a| a.port().to_string()) {
+        if !ports.contains(&port) {
+            ports.push(port);
+        }
     }
-});
+    let ports = ports.join(", ");
+    let program = banner::program_name();
 
-    if let Err(e) = Server::bind(&addr).serve(make_service).await {
-        eprintln!("❌ \x1B[91m服务器错误: {}\x1B[0m", e);
+    println!("🚀 \x1B[92m{}\x1B[0m", tr!(lang, "banner.started"));
+    println!("📁 {}", tr!(lang, "banner.root", path = root_dir.display()));
+    banner::print_urls(lang.t("banner.address"), &urls);
+    if !ports.is_empty() {
+        println!("🔌 {}", tr!(lang, "banner.port", ports = ports));
     }
-}
+    if *log_mode == "none" {
+        println!("\x1B[2m📝 {}\x1B[0m", tr!(lang, "banner.log_mode", mode = log_mode));
+    } else {
+        println!("📝 {}", tr!(lang, "banner.log_mode", mode = log_mode));
+    }
+    if args.public {
+        println!("🖥️ {}", tr!(lang, "banner.public"));
+    } else {
+        println!("🖥️ {}", tr!(lang, "banner.local"));
+    }
+    if !args.allow.is_empty() {
+        println!("🛡️ {}", tr!(lang, "banner.allow", nets = join_nets(&args.allow)));
+    }
+    if !args.deny.is_empty() {
+        println!("🚫 {}", tr!(lang, "banner.deny", nets = join_nets(&args.deny)));
+    }
+    if tls_config.is_some() {
+        println!("🔒 {}", lang.t("banner.tls"));
+        if args.tls_self_signed {
+            println!("\x1B[93m⚠️ {}\x1B[0m", lang.t("banner.self_signed"));
+        }
+    }
+    if !endpoints.is_empty() {
+        println!("⚡ {}", tr!(lang, "banner.http3", ports = ports));
+    }
+    if args.allow_write {
+        println!("✏️ {}", lang.t("banner.allow_write"));
+        match &trash {
+            Some(trash) => println!("🗑️ {}", tr!(lang, "banner.trash", path = trash.dir().display())),
+            None => println!("\x1B[93m⚠️ {}\x1B[0m", lang.t("banner.no_trash")),
+        }
+        if let Some(versions) = &versions {
+            println!("🕘 {}", tr!(lang, "banner.versions", count = versions.keep(), path = versions.dir().display()));
+        }
+    }
+    for url in banner::listener_urls(&admin_listeners, "http", metrics::METRICS_PATH) {
+        println!("🩺 {}", tr!(lang, "banner.admin", url = url));
+    }
+    println!();
+    println!("📖 {}", tr!(lang, "banner.help", program = program));
+    println!();
+    banner::print_primary_qr(&urls);
 
-fn find_available_port(start_port: u16, max_attempts: u16, is_public: bool) -> (SocketAddr, u16) {
-    for offset in 0..max_attempts {
-        let port = start_port + offset;
-        let ip = if is_public {
-            [0, 0, 0, 0] // 允许局域网访问
-        } else {
-            [127, 0, 0, 1] // 仅允许本机访问
-        };
-        let addr = SocketAddr::from((ip, port));
-        if TcpListener::bind(addr).is_ok() {
-            return (addr, port);
-        } else {
-            println!(
-                "\x1B[93m🟡 warning: 端口 {} 已被占用，尝试使用端口 {}\x1B[0m",
-                port,
-                port + 1
-            );
+    let stats = Arc::new(Stats::new());
+    let state = Arc::new(AppState {
+        root_dir,
+        log_mode: log_mode.to_string(),
+        logger: logger.clone(),
+        lang,
+        renderer,
+        share_store,
+        auth: AuthConfig::new(args.admin_token),
+        access,
+        ip_rules: IpRules {
+            allow: args.allow,
+            deny: args.deny,
+        },
+        trusted_proxies: args.trusted_proxy,
+        limits: Limits::new(
+            args.rate_limit,
+            args.rate_burst,
+            args.max_bandwidth,
+            args.conn_bandwidth,
+            args.max_concurrent_downloads,
+        ),
+        stats: stats.clone(),
+        monitor: Arc::new(Monitor::new()),
+        scheme,
+        admin_listener: !admin_listeners.is_empty(),
+        metrics_top: args.metrics_top,
+        allow_write: args.allow_write,
+        trash,
+        versions,
+        tus,
+        max_upload_size: args.max_upload_size,
+        hashes: HashCache::default(),
+        digest_header: args.digest_header,
+        usage: UsageCache::default(),
+        duplicates: DuplicateFinder::default(),
+        recent: RecentFiles::new(args.recent_window, args.recent_limit),
+    });
+    if state.trash.is_some() {
+        tokio::spawn(trash::purge_periodically(state.clone()));
+    }
+    if state.tus.is_some() {
+        tokio::spawn(tus::purge_periodically(state.clone()));
+    }
+    if !admin_listeners.is_empty() {
+        let (state, limits) = (state.clone(), args.connection.clone());
+        tokio::spawn(async move {
+            admin::serve_listener(admin_listeners, state, &limits, lang).await;
+        });
+    }
+    let monitor = state.monitor.clone();
+    // TCP 与 QUIC 连接共用连接数上限
+    let caps = ConnectionCaps::new(&args.connection);
+    for endpoint in &endpoints {
+        tokio::spawn(http3::serve(endpoint.clone(), state.clone(), caps.clone()));
+    }
+    let http3 = !endpoints.is_empty();
+    let make_service = make_service_fn(move |conn: &Conn| {
+        let remote = conn.remote_addr();
+        let state = state.clone();
+        let budget = state.limits.connection();
+        let activity = conn.activity();
+        let connection = (
+            state.stats.connection(),
+            state.monitor.connection(remote, conn.transport()),
+        );
+        // 告知客户端同一端口上可用 HTTP/3
+        let alt_svc = conn
+            .local_addr()
+            .filter(|_| http3)
+            .map(|addr| HeaderValue::from_str(&format!("h3=\":{}\"; ma=86400", addr.port())).unwrap());
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req: Request<Body>| {
+                // 服务随连接一起释放，连接也随之从统计和登记表中移除
+                connection.1.request();
+                let state = state.clone();
+                let budget = budget.clone();
+                let alt_svc = alt_svc.clone();
+                // 计算响应期间连接不会因空闲而关闭
+                let busy = activity.begin();
+                async move {
+                    let mut response = serve_request(req, remote, budget, state).await;
+                    drop(busy);
+                    if let Some(value) = alt_svc {
+                        response.headers_mut().insert(header::ALT_SVC, value);
+                    }
+                    Ok::<_, Infallible>(response)
+                }
+            }))
         }
+    });
+
+    // 收到 Ctrl-C / SIGTERM 后停止接受新连接，等待进行中的下载完成
+    let draining = Arc::new(Notify::new());
+    let drain_timeout = args.drain_timeout;
+    let trigger = {
+        let draining = draining.clone();
+        let endpoints = endpoints.clone();
+        async move {
+            shutdown::signal().await;
+            shutdown::announce(drain_timeout, lang);
+            monitor.close();
+            // QUIC 端点不再接受新连接，已有连接上的请求继续处理
+            for endpoint in &endpoints {
+                endpoint.set_server_config(None);
+            }
+            draining.notify_one();
+        }
+    };
+    let server = listener::server(listeners, &args.connection, caps, tls_config, lang)
+        .serve(make_service)
+        .with_graceful_shutdown(trigger);
+    if let Err(e) = shutdown::drain(server, draining, drain_timeout, lang).await {
+        eprintln!("❌ \x1B[91m{}\x1B[0m", tr!(lang, "common.server_error", error = e));
+    }
+    for endpoint in &endpoints {
+        endpoint.close(0u32.into(), b"");
     }
 
-    eprintln!("❌ \x1B[91m没有可用端口，程序退出。\x1B[0m");
-    std::process::exit(1);
+    logger.flush().await;
+    println!("{}", stats.summary(lang));
+}
+
+/// 数据目录：优先使用 --data-dir，否则为系统本地数据目录下的 file_server
+fn resolve_data_dir(arg: Option<String>) -> PathBuf {
+    match arg {
+        Some(p) => PathBuf::from(p),
+        None => dirs::data_local_dir()
+            .map(|d| d.join("file_server"))
+            .unwrap_or_else(|| PathBuf::from(".file_server")),
+    }
+}
+
+/// 绑定监听地址，失败时退出程序
+pub(crate) fn bind_or_exit(binds: &[BindSpec], port: u16, public: bool, lang: Lang) -> Vec<Listener> {
+    match listener::bind_all(binds, port, public, lang) {
+        Ok(listeners) if !listeners.is_empty() => listeners,
+        Ok(_) => {
+            eprintln!("❌ \x1B[91m{}\x1B[0m", tr!(lang, "startup.no_port"));
+            std::process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("❌ \x1B[91m{}\x1B[0m", tr!(lang, "startup.no_port_error", error = e));
+            std::process::exit(1);
+        }
+    }
 }
 
 
 /// 记录请求日志
 fn log_request(log_mode: &str, logger: SharedLogger, info: String) {
     let log_mode = log_mode.to_lowercase();
+    let time = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
+    let line = format!("[{}] {}\n", time, info);
+    // 管理页面的实时日志不受日志模式影响
+    logger.publish(&line);
 
     if log_mode == "none" {
         return;
     }
 
-    let time = Local::now().format("%Y-%m-%d %H:%M:%S").to_string();
-    let line = format!("[{}] {}\n", time, info);
-
     if log_mode == "console" || log_mode == "both" {
         print!("{}", line);
     }
 
     if log_mode == "file" || log_mode == "both" {
-        let logger = logger.clone();
-        tokio::spawn(async move {
-            let _lock = logger.lock().await;
-            let mut file = OpenOptions::new()
-                .append(true)
-                .create(true)
-                .open("access.log")
-                .unwrap();
-            let _ = file.write_all(line.as_bytes());
-        });
+        logger.write(line);
     }
 }
 
+/// 单个请求的完整处理流程，HTTP/1.1、HTTP/2 与 HTTP/3 共用
+///
+/// 统计请求数、收发字节数和耗时（退出时打印汇总，并通过 /_metrics 导出），
+/// 并按受信任代理确定客户端地址。
+pub(crate) async fn serve_request(
+    req: Request<Body>,
+    remote: SocketAddr,
+    budget: ConnectionBudget,
+    state: Arc<AppState>,
+) -> Response<CountedBody> {
+    let started = Instant::now();
+    let (method, path) = (req.method().clone(), req.uri().path().to_string());
+    let mut req = req.map(|body| state.stats.count_received(body));
+    req.extensions_mut().insert(budget);
+    // HTTP/2 与 HTTP/3 用 :authority 代替 Host 头，补上以便生成完整链接
+    if !req.headers().contains_key(header::HOST) {
+        if let Some(host) = req.uri().authority().and_then(|a| HeaderValue::from_str(a.as_str()).ok()) {
+            req.headers_mut().insert(header::HOST, host);
+        }
+    }
+    state.stats.record_request();
+    // 来自受信任代理的请求以转发头中的客户端地址为准
+    let client = ipfilter::client_addr(&req, remote, &state.trusted_proxies);
+    let Ok(response) = handle_request(req, client, state.clone()).await;
+    state
+        .stats
+        .record_response(&method, response.status(), &path, started.elapsed());
+    response.map(|body| CountedBody::new(body, state.stats.clone()))
+}
+
 /// 处理 HTTP 请求
-async fn handle_request_pretty(
+///
+/// 所有请求共用同一套路径解析、文件传输和错误处理，目录列表交给配置的渲染器生成。
+async fn handle_request(
     req: Request<Body>,
-    root_dir: Arc<PathBuf>,
-    log_mode: Arc<String>,
-    logger: SharedLogger,
+    remote: SocketAddr,
+    state: Arc<AppState>,
 ) -> Result<Response<Body>, Infallible> {
-    let uri_path = req.uri().path();
-    let decoded_path = percent_decode_str(uri_path)
-        .decode_utf8()
-        .unwrap_or_else(|_| uri_path.into());
-
-    let relative_path = decoded_path.trim_start_matches('/');
-    let full_path = root_dir.join(relative_path);
-
-    let method = req.method().to_string();
-    let path = req.uri().path().to_string();
-    log_request(&log_mode, logger, format!("{} {}", method, path));
-
-    if full_path.is_dir() {
-        match fs::read_dir(&full_path).await {
-            Ok(mut entries) => {
-                let current_path = if relative_path.is_empty() {
-                    "/".to_string()
-                } else {
-                    format!("/{}", relative_path)
-                };
+    if let Some(response) = check_banned(&state, &req, remote) {
+        return Ok(response);
+    }
 
-                let display_path = if relative_path.is_empty() {
-                    root_dir.display().to_string()
-                } else {
-                    format!("{}/{}", root_dir.display(), relative_path)
-                };
+    if let Some(response) = check_rate(&state, &req, remote) {
+        return Ok(response);
+    }
 
-                let mut html = format!(
-                    r#"
-<!DOCTYPE html>
-<html lang="zh-CN">
-<head>
-    <meta charset="utf-8">
-    <meta name="viewport" content="width=device-width, initial-scale=1.0">
-    <title>📁 文件服务器 - {}</title>
-    <style>
-        * {{
-            margin: 0;
-            padding: 0;
-            box-sizing: border-box;
-        }}
-        
-        body {{
-            font-family: -apple-system, BlinkMacSystemFont, 'Segoe UI', Roboto, 'Helvetica Neue', Arial, sans-serif;
-            background: linear-gradient(135deg, #667eea 0%, #764ba2 100%);
-            min-height: 100vh;
-            padding: 20px;
-        }}
-        
-        .container {{
-            max-width: 1200px;
-            margin: 0 auto;
-            background: rgba(255, 255, 255, 0.95);
-            backdrop-filter: blur(10px);
-            border-radius: 20px;
-            box-shadow: 0 20px 40px rgba(0, 0, 0, 0.1);
-            overflow: hidden;
-        }}
-        
-        .header {{
-            background: linear-gradient(135deg, #4facfe 0%, #00f2fe 100%);
-            color: white;
-            padding: 30px;
-            text-align: center;
-            position: relative;
-        }}
-        
-        .header::before {{
-            content: '';
-            position: absolute;
-            top: 0;
-            left: 0;
-            right: 0;
-            bottom: 0;
-            background: url("data:image/svg+xml,%3Csvg width='60' height='60' viewBox='0 0 60 60' xmlns='http://www.w3.org/2000/svg'%3E%3Cg fill='none' fill-rule='evenodd'%3E%3Cg fill='%23ffffff' fill-opacity='0.1'%3E%3Ccircle cx='30' cy='30' r='4'/%3E%3C/g%3E%3C/g%3E%3C/svg%3E") repeat;
-        }}
-        
-        .header h1 {{
-            font-size: 2.5em;
-            margin-bottom: 10px;
-            position: relative;
-            z-index: 1;
-        }}
-        
-        .header .path {{
-            font-size: 1.2em;
-            opacity: 0.9;
-            position: relative;
-            z-index: 1;
-        }}
-        
-        .content {{
-            padding: 40px;
-        }}
-        
-        .breadcrumb {{
-            margin-bottom: 30px;
-            padding: 15px 20px;
-            background: #f8f9fa;
-            border-radius: 10px;
-            border: 1px solid #e9ecef;
-        }}
-        
-        .breadcrumb a {{
-            color: #007bff;
-            text-decoration: none;
-            font-weight: 500;
-        }}
-        
-        .breadcrumb a:hover {{
-            text-decoration: underline;
-        }}
-        
-        .file-grid {{
-            display: grid;
-            grid-template-columns: repeat(auto-fill, minmax(300px, 1fr));
-            gap: 20px;
-            margin-top: 20px;
-        }}
-        
-        .file-item {{
-            background: white;
-            border: 1px solid #e9ecef;
-            border-radius: 15px;
-            padding: 20px;
-            transition: all 0.3s ease;
-            position: relative;
-            overflow: hidden;
-            cursor: pointer;
-            display: block;
-            text-decoration: none;
-            color: inherit;
-        }}
-        
-        .file-item:hover {{
-            transform: translateY(-5px);
-            box-shadow: 0 15px 30px rgba(0, 0, 0, 0.1);
-            border-color: #007bff;
-            text-decoration: none;
-        }}
-        
-        .file-item::before {{
-            content: '';
-            position: absolute;
-            top: 0;
-            left: 0;
-            right: 0;
-            height: 4px;
-            background: linear-gradient(90deg, #007bff, #00d4ff);
-            transform: scaleX(0);
-            transition: transform 0.3s ease;
-        }}
-        
-        .file-item:hover::before {{
-            transform: scaleX(1);
-        }}
-        
-        .file-icon {{
-            font-size: 2.5em;
-            margin-bottom: 10px;
-            display: block;
-        }}
-        
-        .file-name {{
-            color: #333;
-            text-decoration: none;
-            font-weight: 500;
-            font-size: 1.1em;
-            display: block;
-            word-break: break-all;
-        }}
-        
-        .file-item:hover .file-name {{
-            color: #007bff;
-        }}
-        
-        .file-type {{
-            color: #666;
-            font-size: 0.9em;
-            margin-top: 5px;
-        }}
-        
-        .folder {{
-            background: linear-gradient(135deg, #ffeaa7 0%, #fab1a0 100%);
-        }}
-        
-        .file {{
-            background: linear-gradient(135deg, #a8e6cf 0%, #88d8c0 100%);
-        }}
-        
-        .empty-state {{
-            text-align: center;
-            padding: 60px 20px;
-            color: #666;
-        }}
-        
-        .empty-state .icon {{
-            font-size: 4em;
-            margin-bottom: 20px;
-            opacity: 0.5;
-        }}
-        
-        .footer {{
-            background: #f8f9fa;
-            padding: 20px;
-            text-align: center;
-            color: #666;
-            font-size: 0.9em;
-            border-top: 1px solid #e9ecef;
-        }}
-        .image-preview {{
-                width: 80px;
-                height: 80px;
-                object-fit: scale-down;
-        }}
-        
-        @media (max-width: 768px) {{
-            .file-grid {{
-                grid-template-columns: 1fr;
-                gap: 15px;
-            }}
-            
-            .header h1 {{
-                font-size: 2em;
-            }}
-            
-            .content {{
-                padding: 20px;
-            }}
-            .image-preview {{
-                width: 80px;
-                height: 80px;
-                object-fit: scale-down;
-            }}
-        }}
-    </style>
-</head>
-<body>
-    <div class="container">
-        <div class="header">
-            <h1>📁 文件服务器</h1>
-            <div class="path">当前路径: {}</div>
-        </div>
-
-        <script>
-                    function replaceBackslashesWithForwardSlashes(inputString) {{
-    return inputString.replace(/\\/g, '/');
-                }}
-                document.addEventListener('DOMContentLoaded', function() {{
-                    document.querySelector(".path").innerHTML = replaceBackslashesWithForwardSlashes(document.querySelector(".path").innerHTML);
-                    console.log(document.querySelector(".path").innerHTML)
-                }});
-            </script>
-        
-        <div class="content">
-            <div class="breadcrumb">
-                🏠 <a href="/">首页</a> {} 📁 {}
-            </div>
-            
-            <div class="file-grid">
-"#,
-                    current_path,
-                    display_path,
-                    if relative_path.is_empty() { "" } else { " / " },
-                    if relative_path.is_empty() { "" } else { relative_path }
-                );
+    // 管理页面；指定 --admin-bind 时只在管理监听器上提供，这里按普通路径处理
+    if !state.admin_listener && admin::is_admin_path(req.uri().path()) {
+        if let Some(response) = check_ip(&state, &req, remote, &state.ip_rules) {
+            return Ok(response);
+        }
+        return Ok(admin::handle(req, remote, &state).await);
+    }
 
-                let mut file_count = 0;
-                let mut dir_count = 0;
-                let mut items = Vec::new();
-
-                // 收集所有文件和目录
-                while let Ok(Some(entry)) = entries.next_entry().await {
-                    let file_name = entry.file_name();
-                    let file_name_str = file_name.to_string_lossy();
-                    let file_type = entry.file_type().await.unwrap();
-                    
-                    items.push((file_name_str.to_string(), file_type.is_dir()));
-                    
-                    if file_type.is_dir() {
-                        dir_count += 1;
-                    } else {
-                        file_count += 1;
-                    }
-                }
+    // 健康检查与指标；指定 --admin-bind 时只在管理监听器上提供，这里按普通路径处理
+    if !state.admin_listener && metrics::is_metrics_path(req.uri().path()) {
+        if let Some(response) = check_ip(&state, &req, remote, &state.ip_rules) {
+            return Ok(response);
+        }
+        // 指标包含访问路径等信息，与管理操作一样需要授权；健康检查对所有人开放
+        if req.uri().path() == metrics::METRICS_PATH && !state.auth.is_authorized(&req, remote) {
+            return Ok(auth::unauthorized(Lang::for_request(&req, state.lang)));
+        }
+        if let Some(response) = metrics::handle(&req, &state.stats, state.metrics_top) {
+            return Ok(response);
+        }
+    }
 
-                // 排序：目录在前，文件在后
-                items.sort_by(|a, b| {
-                    match (a.1, b.1) {
-                        (true, false) => std::cmp::Ordering::Less,
-                        (false, true) => std::cmp::Ordering::Greater,
-                        _ => a.0.cmp(&b.0),
-                    }
-                });
-
-                // 添加返回上级目录的链接
-                if !relative_path.is_empty() {
-                    let parent_path = if relative_path.contains('/') {
-                        let parts: Vec<&str> = relative_path.split('/').collect();
-                        parts[..parts.len()-1].join("/")
-                    } else {
-                        "".to_string()
-                    };
-                    
-                    html += &format!(
-                        r#"
-                <a href="/{}" class="file-item folder">
-                    <span class="file-icon">⬆️</span>
-                    <div class="file-name">.. 返回上级目录</div>
-                    <div class="file-type">目录</div>
-                </a>
-                        "#,
-                        parent_path
-                    );
-                }
+    // 文件管理接口，逐项检查路径的访问规则和写权限，每项修改记入访问日志
+    if fsapi::is_fs_api_path(req.uri().path()) {
+        if let Some(response) = check_ip(&state, &req, remote, &state.ip_rules) {
+            return Ok(response);
+        }
+        return Ok(fsapi::handle(req, remote, state).await);
+    }
 
-                // 生成文件和目录列表
-                for (file_name, is_dir) in items {
-                    let rel_link = if relative_path.is_empty() {
-                        file_name.clone()
-                    } else {
-                        format!("{}/{}", relative_path, file_name)
-                    };
-
-                    let (icon_html, class, type_text) = if is_dir {
-                        ("<span class=\"file-icon\">📁</span>".to_string(), "folder", "目录")
-                    } else {
-                        // 检查是否为图片文件
-                        let is_image = file_name.to_lowercase().ends_with(".jpg") || 
-                                      file_name.to_lowercase().ends_with(".jpeg") || 
-                                      file_name.to_lowercase().ends_with(".png") || 
-                                      file_name.to_lowercase().ends_with(".gif") || 
-                                      file_name.to_lowercase().ends_with(".bmp") || 
-                                      file_name.to_lowercase().ends_with(".webp") || 
-                                      file_name.to_lowercase().ends_with(".svg");
-                        
-                        if is_image {
-                            // 对图片显示缩略图
-                            let preview_html = format!(
-                                "<img src=\"/{}\" class=\"image-preview\" alt=\"{}\" loading=\"lazy\" onerror=\"this.style.display='none'; this.nextElementSibling.style.display='block'\"><span class=\"file-icon\" style=\"display:none\">🖼️</span>",
-                                rel_link, file_name
-                            );
-                            (preview_html, "file image-item", "图片")
-                        } else {
-                            // 根据文件扩展名选择不同的图标
-                            let icon = if file_name.ends_with(".txt") || file_name.ends_with(".md") {
-                                "📄"
-                            } else if file_name.ends_with(".mp4") || file_name.ends_with(".avi") || file_name.ends_with(".mov") || file_name.ends_with(".mkv") {
-                                "🎬"
-                            } else if file_name.ends_with(".mp3") || file_name.ends_with(".wav") || file_name.ends_with(".flac") {
-                                "🎵"
-                            } else if file_name.ends_with(".pdf") {
-                                "📕"
-                            } else if file_name.ends_with(".zip") || file_name.ends_with(".rar") || file_name.ends_with(".7z") {
-                                "📦"
-                            } else if file_name.ends_with(".js") || file_name.ends_with(".html") || file_name.ends_with(".css") {
-                                "💻"
-                            } else if file_name.ends_with(".doc") || file_name.ends_with(".docx") {
-                                "📘"
-                            } else if file_name.ends_with(".xls") || file_name.ends_with(".xlsx") {
-                                "📗"
-                            } else if file_name.ends_with(".ppt") || file_name.ends_with(".pptx") {
-                                "📙"
-                            } else {
-                                "📄"
-                            };
-                            (format!("<span class=\"file-icon\">{}</span>", icon), "file", "文件")
-                        }
-                    };
-
-                    html += &format!(
-                        r#"
-                <a href="/{}" class="file-item {}">
-                    {}
-                    <div class="file-name">{}</div>
-                    <div class="file-type">{}</div>
-                </a>
-                        "#,
-                        rel_link, class, icon_html, file_name, type_text
-                    );
-                }
+    // 回收站页面与恢复、清除操作
+    if trash::is_trash_path(req.uri().path()) {
+        if let Some(response) = check_ip(&state, &req, remote, &state.ip_rules) {
+            return Ok(response);
+        }
+        return Ok(trash::handle(req, remote, state).await);
+    }
 
-                // 如果目录为空
-                if dir_count == 0 && file_count == 0 {
-                    html += r#"
-                <div class="empty-state">
-                    <div class="icon">📭</div>
-                    <h3>此目录为空</h3>
-                    <p>没有找到任何文件或文件夹</p>
-                </div>
-                    "#;
-                }
+    // 重复文件查找
+    if duplicates::is_duplicates_path(req.uri().path()) {
+        if let Some(response) = check_ip(&state, &req, remote, &state.ip_rules) {
+            return Ok(response);
+        }
+        return Ok(duplicates::handle(req, remote, state).await);
+    }
 
-                html += &format!(
-                    r#"
-            </div>
-        </div>
-        
-        <div class="footer">
-            📊 统计信息: {} 个文件夹, {} 个文件 | 🚀 由 Rust 文件服务器强力驱动
-        </div>
-    </div>
-</body>
-</html>
-                    "#,
-                    dir_count, file_count
-                );
+    // 断点续传（tus）上传
+    if tus::is_tus_path(req.uri().path()) {
+        if let Some(response) = check_ip(&state, &req, remote, &state.ip_rules) {
+            return Ok(response);
+        }
+        return Ok(tus::handle(req, remote, state).await);
+    }
 
-                Ok(Response::builder()
-                    .header("Content-Type", "text/html; charset=utf-8")
-                    .header("Access-Control-Allow-Origin", "*")
-                    .body(Body::from(html))
-                    .unwrap())
-            }
-            Err(_) => Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .body(Body::from("无法读取目录"))
-                .unwrap()),
+    // 最近修改的文件与订阅源
+    if recent::is_recent_path(req.uri().path()) {
+        if let Some(response) = check_ip(&state, &req, remote, &state.ip_rules) {
+            return Ok(response);
         }
-    } else if full_path.is_file() {
-        match File::open(&full_path).await {
-            Ok(file) => {
-                let mime = from_path(&full_path).first_or_octet_stream();
-                let stream = ReaderStream::new(file);
-                Ok(Response::builder()
-                    .header("Content-Type", mime.as_ref())
-                    .body(Body::wrap_stream(stream))
-                    .unwrap())
-            }
-            Err(_) => Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .body(Body::from("无法打开文件"))
-                .unwrap()),
+        return Ok(recent::handle(req, remote, state).await);
+    }
+
+    // 分享链接在常规路径解析之前校验
+    if share::is_share_path(req.uri().path()) {
+        if let Some(response) = check_ip(&state, &req, remote, &state.ip_rules) {
+            return Ok(response);
         }
-    } else {
-        Ok(Response::builder()
-            .status(StatusCode::NOT_FOUND)
-            .header("Content-Type", "text/plain; charset=utf-8")
-            .body(Body::from("404 - 文件未找到"))
-            .unwrap())
+        return share::handle_share_request(req, remote, state).await;
     }
-}
 
-/// 处理 HTTP 请求
-async fn handle_request(
-    req: Request<Body>,
-    root_dir: Arc<PathBuf>,
-    log_mode: Arc<String>,
-    logger: SharedLogger,
-) -> Result<Response<Body>, Infallible> {
     let uri_path = req.uri().path();
     let decoded_path = percent_decode_str(uri_path)
         .decode_utf8()
         .unwrap_or_else(|_| uri_path.into());
 
-    let relative_path = decoded_path.trim_start_matches('/');
-    let full_path = root_dir.join(relative_path);
-
     let method = req.method().to_string();
     let path = req.uri().path().to_string();
-    log_request(&log_mode, logger, format!("{} {}", method, path));
-
-    if full_path.is_dir() {
-        match fs::read_dir(&full_path).await {
-            Ok(mut entries) => {
-                let mut html = format!(
-                    "<html><head><meta charset='utf-8'><title>Index of {}</title></head><body><h3>📁 Index of {}</h3><ul>",
-                    full_path.display(),
-                    full_path.display()
-                );
+    log_request(&state.log_mode, state.logger.clone(), format!("{} {}", method, path));
+    let lang = Lang::for_request(&req, state.lang);
+
+    // 上传：PUT 写入单个文件，POST multipart 上传到目录，路径校验与文件管理接口相同
+    if req.method() == Method::PUT || req.method() == Method::POST {
+        if let Some(response) = check_ip(&state, &req, remote, &state.ip_rules) {
+            return Ok(response);
+        }
+        let decoded_path = decoded_path.to_string();
+        return Ok(fsapi::upload(req, &decoded_path, remote, state).await);
+    }
 
-                while let Ok(Some(entry)) = entries.next_entry().await {
-                    let file_name = entry.file_name();
-                    let file_name_str = file_name.to_string_lossy();
-                    let rel_link = if relative_path.is_empty() {
-                        file_name_str.to_string()
-                    } else {
-                        format!("{}/{}", relative_path, file_name_str)
-                    };
+    // 主题静态资源使用保留前缀，不映射到根目录
+    if let Some(name) = req.uri().path().strip_prefix(listing::ASSET_PREFIX) {
+        if let Some(response) = check_ip(&state, &req, remote, &state.ip_rules) {
+            return Ok(response);
+        }
+        return Ok(serve_asset(&state, name, lang).await);
+    }
 
-                    let file_type = entry.file_type().await.unwrap();
-                    let icon = if file_type.is_dir() { "📁" } else { "📄" };
+    // 拒绝 `..` 等越出根目录的路径
+    let Some(relative_path) = normalize_relative(&decoded_path) else {
+        return Ok(text_response(StatusCode::BAD_REQUEST, lang.t("http.bad_path")));
+    };
+    let full_path = state.root_dir.join(&relative_path);
+    let is_dir = full_path.is_dir();
 
-                    html += &format!("<li>{} <a href=\"/{}\">{}</a></li>", icon, rel_link, file_name_str);
-                }
+    // 被排除的路径与不存在的路径返回相同的 404
+    let access = state.access.check(&relative_path, is_dir);
+    let ip_rules = access.ip_rules.as_ref().unwrap_or(&state.ip_rules);
+    if let Some(response) = check_ip(&state, &req, remote, ip_rules) {
+        return Ok(response);
+    }
+    if access.denied {
+        return Ok(text_response(StatusCode::NOT_FOUND, lang.t("http.not_found")));
+    }
+    if access.auth_required && !state.auth.is_authorized(&req, remote) {
+        return Ok(auth::unauthorized(lang));
+    }
 
-                html += "</ul></body></html>";
+    // 文件摘要与目录的校验清单；禁止列出的目录不提供清单
+    let checksum = if is_dir && !access.listing {
+        None
+    } else {
+        checksum::handle_query(&req, remote, &state, &relative_path, is_dir, lang).await
+    };
 
-                Ok(Response::builder()
-                    .header("Content-Type", "text/html; charset=utf-8")
-                    .body(Body::from(html))
-                    .unwrap())
+    let mut response = if let Some(response) = checksum {
+        response
+    } else if is_dir {
+        if !access.listing {
+            return Ok(text_response(StatusCode::FORBIDDEN, lang.t("http.listing_disabled")));
+        }
+        if util::query_param(req.uri().query(), "du").is_some() {
+            let renderer: &dyn ListingRenderer = if wants_json(&req) { &JsonRenderer } else { &*state.renderer };
+            let authorized = state.auth.is_authorized(&req, remote);
+            return Ok(du::handle(&state, &relative_path, remote, authorized, renderer, lang).await);
+        }
+        match Listing::read(&state.root_dir, &relative_path, &state.access).await {
+            Ok(mut listing) => {
+                listing.writable = state.allow_write && access.writable && state.auth.is_authorized(&req, remote);
+                listing.history = state.versions.is_some();
+                listing.disk = du::disk_space(&state.root_dir);
+                if wants_json(&req) {
+                    JsonRenderer.render(&listing, lang)
+                } else {
+                    state.renderer.render(&listing, lang)
+                }
             }
-            Err(_) => Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .body(Body::from("无法读取目录"))
-                .unwrap()),
+            Err(_) => text_response(StatusCode::INTERNAL_SERVER_ERROR, lang.t("http.read_dir_failed")),
         }
+    } else if let Some(response) = versions::handle_query(&req, remote, &state, &relative_path, lang).await {
+        response
     } else if full_path.is_file() {
+        let Some(slot) = state.limits.download_slot() else {
+            return Ok(limit::too_many_requests(limit::BUSY_RETRY_AFTER, lang.t("http.too_many_downloads")));
+        };
         match File::open(&full_path).await {
             Ok(file) => {
                 let mime = from_path(&full_path).first_or_octet_stream();
-                let stream = ReaderStream::new(file);
-                Ok(Response::builder()
-                    .header("Content-Type", mime.as_ref())
-                    .body(Body::wrap_stream(stream))
-                    .unwrap())
+                let size = file.metadata().await.ok().map(|m| m.len());
+                let digests = if state.digest_header {
+                    checksum::digest_headers(&state, &full_path).await
+                } else {
+                    Vec::new()
+                };
+                let body = state.limits.file_body(file, &req, slot);
+                let mut builder = Response::builder().header("Content-Type", mime.as_ref());
+                for (name, value) in digests {
+                    builder = builder.header(name, value);
+                }
+                builder
+                    .body(state.monitor.track_download(body, &path, remote.ip(), size))
+                    .unwrap()
             }
-            Err(_) => Ok(Response::builder()
-                .status(StatusCode::INTERNAL_SERVER_ERROR)
-                .body(Body::from("无法打开文件"))
-                .unwrap()),
+            Err(_) => text_response(StatusCode::INTERNAL_SERVER_ERROR, lang.t("http.open_file_failed")),
         }
     } else {
-        Ok(Response::builder()
-            .status(StatusCode::NOT_FOUND)
-            .header("Content-Type", "text/plain; charset=utf-8")
-            .body(Body::from("404 - 文件未找到"))
-            .unwrap())
+        text_response(StatusCode::NOT_FOUND, lang.t("http.not_found"))
+    };
+
+    // 通过 ?token= 访问受保护目录后写入 Cookie，页面内的后续链接无需再带令牌
+    if access.auth_required {
+        if let Some(cookie) = state.auth.cookie_for(&req) {
+            response
+                .headers_mut()
+                .insert(header::SET_COOKIE, cookie.parse().unwrap());
+        }
+    }
+    Ok(response)
+}
+
+/// 客户端地址不符合 IP 规则时返回 403，并在日志中记录原因
+fn check_ip(
+    state: &AppState,
+    req: &Request<Body>,
+    remote: SocketAddr,
+    rules: &IpRules,
+) -> Option<Response<Body>> {
+    let ip = remote.ip();
+    let reason = match rules.check(ip).err()? {
+        Rejection::Denied(net) => tr!(state.lang, "ip.denied", ip = ip, rule = net),
+        Rejection::NotAllowed => tr!(state.lang, "ip.not_allowed", ip = ip),
+    };
+    log_request(
+        &state.log_mode,
+        state.logger.clone(),
+        format!("403 {} {} - {}", req.method(), req.uri().path(), reason),
+    );
+    let lang = Lang::for_request(req, state.lang);
+    Some(text_response(StatusCode::FORBIDDEN, lang.t("http.forbidden")))
+}
+
+/// 在管理页面被封禁的地址返回 403，并在日志中记录
+fn check_banned(state: &AppState, req: &Request<Body>, remote: SocketAddr) -> Option<Response<Body>> {
+    if !state.monitor.is_banned(remote.ip()) {
+        return None;
+    }
+    log_request(
+        &state.log_mode,
+        state.logger.clone(),
+        format!(
+            "403 {} {} - {}",
+            req.method(),
+            req.uri().path(),
+            tr!(state.lang, "ip.banned", ip = remote.ip())
+        ),
+    );
+    let lang = Lang::for_request(req, state.lang);
+    Some(text_response(StatusCode::FORBIDDEN, lang.t("http.forbidden")))
+}
+
+/// 客户端超过请求频率限制时返回 429，并在日志中记录
+fn check_rate(state: &AppState, req: &Request<Body>, remote: SocketAddr) -> Option<Response<Body>> {
+    let wait = state.limits.check_rate(remote.ip()).err()?;
+    log_request(
+        &state.log_mode,
+        state.logger.clone(),
+        format!(
+            "429 {} {} - {}",
+            req.method(),
+            req.uri().path(),
+            tr!(state.lang, "limit.rate_exceeded", ip = remote.ip())
+        ),
+    );
+    let lang = Lang::for_request(req, state.lang);
+    Some(limit::too_many_requests(
+        limit::retry_after_secs(wait),
+        lang.t("http.too_many_requests"),
+    ))
+}
+
+/// 横幅中显示的地址列表
+fn join_nets(nets: &[IpNet]) -> String {
+    nets.iter().map(|net| net.to_string()).collect::<Vec<_>>().join(", ")
+}
+
+/// 提供当前渲染器的主题静态资源
+async fn serve_asset(state: &AppState, name: &str, lang: Lang) -> Response<Body> {
+    let mime = from_path(name).first_or_octet_stream();
+    let body = match state.renderer.asset(name) {
+        Some(Asset::Embedded(content)) => Body::from(content),
+        Some(Asset::File(path)) => match File::open(&path).await {
+            Ok(file) => Body::wrap_stream(ReaderStream::new(file)),
+            Err(_) => {
+                return text_response(StatusCode::INTERNAL_SERVER_ERROR, lang.t("http.open_file_failed"))
+            }
+        },
+        None => return text_response(StatusCode::NOT_FOUND, lang.t("http.not_found")),
+    };
+    Response::builder()
+        .header("Content-Type", mime.as_ref())
+        .header(header::CACHE_CONTROL, "public, max-age=3600")
+        .body(body)
+        .unwrap()
+}
+
+/// 客户端是否要求 JSON 格式的目录列表（`?format=json` 或 `Accept: application/json`）
+fn wants_json(req: &Request<Body>) -> bool {
+    if util::query_param(req.uri().query(), "format").as_deref() == Some("json") {
+        return true;
     }
-}
\ No newline at end of file
+    req.headers()
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .map(|v| v.contains("application/json"))
+        .unwrap_or(false)
+}
@@ -0,0 +1,44 @@
+use hyper::{Body, Request, Response};
+
+use crate::stats::Stats;
+
+/// 健康检查路径
+pub const HEALTH_PATH: &str = "/_health";
+
+/// Prometheus 指标路径
+pub const METRICS_PATH: &str = "/_metrics";
+
+/// 是否为健康检查或指标路径
+pub fn is_metrics_path(path: &str) -> bool {
+    path == HEALTH_PATH || path == METRICS_PATH
+}
+
+/// 提供健康检查或指标，其他路径返回 None
+///
+/// `/_health` 返回 JSON，服务能处理请求即为健康；`/_metrics` 为 Prometheus 文本格式。
+pub fn handle(req: &Request<Body>, stats: &Stats, top_paths: usize) -> Option<Response<Body>> {
+    match req.uri().path() {
+        HEALTH_PATH => {
+            let json = serde_json::json!({
+                "status": "ok",
+                "version": env!("CARGO_PKG_VERSION"),
+                "uptime_seconds": stats.uptime().as_secs(),
+            });
+            Some(
+                Response::builder()
+                    .header("Content-Type", "application/json")
+                    .header("Cache-Control", "no-store")
+                    .body(Body::from(json.to_string()))
+                    .unwrap(),
+            )
+        }
+        METRICS_PATH => Some(
+            Response::builder()
+                .header("Content-Type", "text/plain; version=0.0.4; charset=utf-8")
+                .header("Cache-Control", "no-store")
+                .body(Body::from(stats.prometheus(top_paths)))
+                .unwrap(),
+        ),
+        _ => None,
+    }
+}
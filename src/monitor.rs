@@ -0,0 +1,304 @@
+use futures_util::Stream;
+use hyper::body::{Bytes, HttpBody};
+use hyper::Body;
+use serde::Serialize;
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    future::Future,
+    io,
+    net::{IpAddr, SocketAddr},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::Instant,
+};
+use tokio_util::sync::{CancellationToken, WaitForCancellationFutureOwned};
+
+/// 当前的连接、传输和运行时封禁的 IP
+///
+/// 文件传输把进度报告到这里，管理页面读取快照，并可以取消传输或封禁 IP。
+pub struct Monitor {
+    next_id: AtomicU64,
+    connections: Mutex<BTreeMap<u64, Arc<ConnectionEntry>>>,
+    transfers: Mutex<BTreeMap<u64, Arc<Transfer>>>,
+    banned: Mutex<BTreeSet<IpAddr>>,
+    /// 服务退出时结束日志推送等长时间保持的响应
+    closing: CancellationToken,
+}
+
+struct ConnectionEntry {
+    remote: SocketAddr,
+    transport: &'static str,
+    started: Instant,
+    requests: AtomicU64,
+}
+
+/// 传输方向
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    Download,
+    Upload,
+}
+
+struct Transfer {
+    direction: Direction,
+    path: String,
+    client: IpAddr,
+    total: Option<u64>,
+    done: AtomicU64,
+    started: Instant,
+    cancel: CancellationToken,
+}
+
+/// 连接打开期间持有，释放时从登记表中移除
+pub struct ConnectionHandle {
+    monitor: Arc<Monitor>,
+    id: u64,
+    entry: Arc<ConnectionEntry>,
+}
+
+/// 传输进行期间持有，释放时从登记表中移除
+struct TransferHandle {
+    monitor: Arc<Monitor>,
+    id: u64,
+    transfer: Arc<Transfer>,
+}
+
+/// 管理页面使用的快照
+#[derive(Serialize)]
+pub struct Snapshot {
+    connections: Vec<ConnectionInfo>,
+    transfers: Vec<TransferInfo>,
+    banned: Vec<IpAddr>,
+}
+
+#[derive(Serialize)]
+struct ConnectionInfo {
+    id: u64,
+    remote: String,
+    transport: &'static str,
+    seconds: u64,
+    requests: u64,
+}
+
+#[derive(Serialize)]
+struct TransferInfo {
+    id: u64,
+    direction: Direction,
+    path: String,
+    client: IpAddr,
+    total: Option<u64>,
+    done: u64,
+    elapsed_ms: u128,
+}
+
+impl Monitor {
+    pub fn new() -> Self {
+        Monitor {
+            next_id: AtomicU64::new(1),
+            connections: Mutex::new(BTreeMap::new()),
+            transfers: Mutex::new(BTreeMap::new()),
+            banned: Mutex::new(BTreeSet::new()),
+            closing: CancellationToken::new(),
+        }
+    }
+
+    fn next_id(&self) -> u64 {
+        self.next_id.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// 登记新连接，`transport` 为 http / https / unix / h3
+    pub fn connection(self: &Arc<Self>, remote: SocketAddr, transport: &'static str) -> ConnectionHandle {
+        let id = self.next_id();
+        let entry = Arc::new(ConnectionEntry {
+            remote,
+            transport,
+            started: Instant::now(),
+            requests: AtomicU64::new(0),
+        });
+        self.connections.lock().unwrap().insert(id, entry.clone());
+        ConnectionHandle {
+            monitor: self.clone(),
+            id,
+            entry,
+        }
+    }
+
+    /// 登记一个下载，返回报告进度并可被取消的响应体
+    ///
+    /// 取消后响应体以错误结束，连接随之中断，客户端不会把不完整的文件当作下载完成。
+    pub fn track_download(self: &Arc<Self>, body: Body, path: &str, client: IpAddr, total: Option<u64>) -> Body {
+        self.track(Direction::Download, body, path, client, total)
+    }
+
+    /// 登记一个上传，返回报告进度并可被取消的请求体
+    ///
+    /// 取消后读取请求体出错，上传的临时文件随之删除。
+    pub fn track_upload(self: &Arc<Self>, body: Body, path: &str, client: IpAddr, total: Option<u64>) -> Body {
+        self.track(Direction::Upload, body, path, client, total)
+    }
+
+    fn track(
+        self: &Arc<Self>,
+        direction: Direction,
+        body: Body,
+        path: &str,
+        client: IpAddr,
+        total: Option<u64>,
+    ) -> Body {
+        let id = self.next_id();
+        let transfer = Arc::new(Transfer {
+            direction,
+            path: path.to_string(),
+            client: client.to_canonical(),
+            total,
+            done: AtomicU64::new(0),
+            started: Instant::now(),
+            cancel: CancellationToken::new(),
+        });
+        self.transfers.lock().unwrap().insert(id, transfer.clone());
+        Body::wrap_stream(Tracked {
+            body,
+            cancelled: Box::pin(transfer.cancel.clone().cancelled_owned()),
+            handle: TransferHandle {
+                monitor: self.clone(),
+                id,
+                transfer,
+            },
+        })
+    }
+
+    /// 取消传输，传输不存在时返回 false
+    pub fn cancel(&self, id: u64) -> bool {
+        match self.transfers.lock().unwrap().get(&id) {
+            Some(transfer) => {
+                transfer.cancel.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// 封禁 IP：之后的请求返回 403，进行中的传输被取消
+    pub fn ban(&self, ip: IpAddr) {
+        let ip = ip.to_canonical();
+        self.banned.lock().unwrap().insert(ip);
+        for transfer in self.transfers.lock().unwrap().values() {
+            if transfer.client == ip {
+                transfer.cancel.cancel();
+            }
+        }
+    }
+
+    /// 解除封禁，IP 未被封禁时返回 false
+    pub fn unban(&self, ip: IpAddr) -> bool {
+        self.banned.lock().unwrap().remove(&ip.to_canonical())
+    }
+
+    pub fn is_banned(&self, ip: IpAddr) -> bool {
+        let banned = self.banned.lock().unwrap();
+        !banned.is_empty() && banned.contains(&ip.to_canonical())
+    }
+
+    /// 服务开始退出时调用
+    pub fn close(&self) {
+        self.closing.cancel();
+    }
+
+    /// 服务开始退出时完成
+    pub fn closing(&self) -> CancellationToken {
+        self.closing.clone()
+    }
+
+    pub fn snapshot(&self) -> Snapshot {
+        let connections = self
+            .connections
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, entry)| ConnectionInfo {
+                id: *id,
+                remote: entry.remote.to_string(),
+                transport: entry.transport,
+                seconds: entry.started.elapsed().as_secs(),
+                requests: entry.requests.load(Ordering::Relaxed),
+            })
+            .collect();
+        let transfers = self
+            .transfers
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(id, transfer)| TransferInfo {
+                id: *id,
+                direction: transfer.direction,
+                path: transfer.path.clone(),
+                client: transfer.client,
+                total: transfer.total,
+                done: transfer.done.load(Ordering::Relaxed),
+                elapsed_ms: transfer.started.elapsed().as_millis(),
+            })
+            .collect();
+        Snapshot {
+            connections,
+            transfers,
+            banned: self.banned.lock().unwrap().iter().copied().collect(),
+        }
+    }
+}
+
+impl ConnectionHandle {
+    /// 记录连接上的一次请求
+    pub fn request(&self) {
+        self.entry.requests.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+impl Drop for ConnectionHandle {
+    fn drop(&mut self) {
+        self.monitor.connections.lock().unwrap().remove(&self.id);
+    }
+}
+
+impl Drop for TransferHandle {
+    fn drop(&mut self) {
+        self.monitor.transfers.lock().unwrap().remove(&self.id);
+    }
+}
+
+/// 统计已传输字节数、响应取消的请求体或响应体
+struct Tracked {
+    body: Body,
+    cancelled: Pin<Box<WaitForCancellationFutureOwned>>,
+    handle: TransferHandle,
+}
+
+impl Stream for Tracked {
+    type Item = Result<Bytes, Box<dyn std::error::Error + Send + Sync>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.cancelled.as_mut().poll(cx).is_ready() {
+            return Poll::Ready(Some(Err(io::Error::new(
+                io::ErrorKind::ConnectionAborted,
+                "transfer cancelled",
+            )
+            .into())));
+        }
+        match Pin::new(&mut self.body).poll_data(cx) {
+            Poll::Ready(Some(Ok(chunk))) => {
+                self.handle
+                    .transfer
+                    .done
+                    .fetch_add(chunk.len() as u64, Ordering::Relaxed);
+                Poll::Ready(Some(Ok(chunk)))
+            }
+            Poll::Ready(Some(Err(e))) => Poll::Ready(Some(Err(e.into()))),
+            Poll::Ready(None) => Poll::Ready(None),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
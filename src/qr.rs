@@ -0,0 +1,17 @@
+use qrcode::{render::unicode::Dense1x2, QrCode};
+
+/// 在终端中打印地址的二维码，方便手机扫码访问
+///
+/// 终端多为深色背景，因此反色绘制（亮块作为码点），并保留静区。
+pub fn print_qr(url: &str) {
+    let Ok(code) = QrCode::new(url.as_bytes()) else {
+        return;
+    };
+    let image = code
+        .render::<Dense1x2>()
+        .dark_color(Dense1x2::Light)
+        .light_color(Dense1x2::Dark)
+        .quiet_zone(true)
+        .build();
+    println!("{}", image);
+}
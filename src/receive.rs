@@ -0,0 +1,310 @@
+use hyper::{
+    header,
+    service::{make_service_fn, service_fn},
+    Body, Method, Request, Response, StatusCode,
+};
+use std::{
+    convert::Infallible,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
+use tokio::sync::{mpsc, Notify};
+
+use crate::banner::{listener_urls, print_urls};
+use crate::i18n::Lang;
+use crate::listener::{self, Conn, ConnectionCaps, ConnectionLimits, Listener};
+use crate::upload::{self, ReceivedFile, UploadError};
+use crate::util::{format_size, html_escape, parse_size, random_token, text_response};
+use crate::{log_request, qr, shutdown, tr, ReceiveArgs, SharedLogger};
+
+/// 接收模式的运行配置
+struct ReceiveConfig {
+    dir: PathBuf,
+    token: String,
+    max_size: Option<u64>,
+    /// 默认语言，上传页面按 Accept-Language 协商
+    lang: Lang,
+}
+
+/// `receive` 子命令：提供一个只能上传的临时投递页面，收到的文件保存到指定目录
+pub async fn run_receive(
+    cmd: &ReceiveArgs,
+    listeners: Vec<Listener>,
+    log_mode: Arc<String>,
+    logger: SharedLogger,
+    drain_timeout: Duration,
+    limits: &ConnectionLimits,
+    lang: Lang,
+) {
+    let dir = PathBuf::from(&cmd.dir);
+    if let Err(e) = std::fs::create_dir_all(&dir) {
+        eprintln!(
+            "❌ \x1B[91m{}\x1B[0m",
+            tr!(lang, "receive.create_dir_failed", path = dir.display(), error = e)
+        );
+        std::process::exit(1);
+    }
+    let max_size = match cmd.max_size.as_deref().map(parse_size).transpose() {
+        Ok(size) => size,
+        Err(e) => {
+            eprintln!("❌ \x1B[91m{}\x1B[0m", e);
+            std::process::exit(1);
+        }
+    };
+
+    let config = Arc::new(ReceiveConfig {
+        dir: dir.canonicalize().unwrap_or(dir),
+        token: random_token(16),
+        max_size,
+        lang,
+    });
+    let urls = listener_urls(&listeners, "http", &format!("/{}/", config.token));
+
+    println!("📥 \x1B[92m{}\x1B[0m", lang.t("receive.ready"));
+    println!("📁 {}", tr!(lang, "receive.save_to", path = config.dir.display()));
+    print_urls(lang.t("banner.address"), &urls);
+    if let Some(limit) = max_size {
+        println!("📏 {}", tr!(lang, "receive.max_size", size = format_size(limit)));
+    }
+    if cmd.once {
+        println!("🔚 {}", lang.t("receive.once"));
+    }
+    qr::print_qr(&urls[0]);
+
+    let (received_tx, mut received_rx) = mpsc::unbounded_channel::<Vec<ReceivedFile>>();
+    let make_service = make_service_fn(move |_conn: &Conn| {
+        let config = config.clone();
+        let log_mode = log_mode.clone();
+        let logger = logger.clone();
+        let received_tx = received_tx.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                handle_receive(
+                    req,
+                    config.clone(),
+                    log_mode.clone(),
+                    logger.clone(),
+                    received_tx.clone(),
+                )
+            }))
+        }
+    });
+
+    // 在终端打印收到的文件；--once 时第一次上传完成后触发退出
+    let once = cmd.once;
+    let finished = async move {
+        while let Some(files) = received_rx.recv().await {
+            for file in &files {
+                println!(
+                    "📥 {}",
+                    tr!(lang, "receive.received", path = file.path.display(), size = format_size(file.size))
+                );
+                println!("   \x1B[2mSHA-256: {}\x1B[0m", file.sha256);
+            }
+            if once {
+                break;
+            }
+        }
+    };
+
+    // 正常完成或收到 Ctrl-C / SIGTERM 时都优雅退出
+    let draining = Arc::new(Notify::new());
+    let trigger = {
+        let draining = draining.clone();
+        async move {
+            tokio::select! {
+                _ = finished => {}
+                _ = shutdown::signal() => shutdown::announce(drain_timeout, lang),
+            }
+            draining.notify_one();
+        }
+    };
+    let server = listener::server(listeners, limits, ConnectionCaps::new(limits), None, lang)
+        .serve(make_service)
+        .with_graceful_shutdown(trigger);
+    if let Err(e) = shutdown::drain(server, draining, drain_timeout, lang).await {
+        eprintln!("❌ \x1B[91m{}\x1B[0m", tr!(lang, "common.server_error", error = e));
+    }
+    println!("👋 {}", lang.t("common.bye"));
+}
+
+async fn handle_receive(
+    req: Request<Body>,
+    config: Arc<ReceiveConfig>,
+    log_mode: Arc<String>,
+    logger: SharedLogger,
+    received_tx: mpsc::UnboundedSender<Vec<ReceivedFile>>,
+) -> Result<Response<Body>, Infallible> {
+    let path = req.uri().path().to_string();
+    log_request(&log_mode, logger.clone(), format!("{} {}", req.method(), path));
+    let lang = Lang::for_request(&req, config.lang);
+
+    // 只响应 /<token>/ 页面和 /<token>/upload 上传接口，不列出任何已有内容
+    let rest = path.trim_start_matches('/');
+    let (token, action) = rest.split_once('/').unwrap_or((rest, ""));
+    if token != config.token {
+        return Ok(text_response(StatusCode::NOT_FOUND, lang.t("http.not_found")));
+    }
+
+    match (req.method(), action) {
+        (&Method::GET, "") => Ok(Response::builder()
+            .header("Content-Type", "text/html; charset=utf-8")
+            .header(header::CACHE_CONTROL, "no-store")
+            .header("Content-Language", lang.code())
+            .body(Body::from(upload_page(config.max_size, lang)))
+            .unwrap()),
+        (&Method::POST, "upload") => {
+            match receive_files(req, &config.dir, config.max_size).await {
+                Ok(files) if files.is_empty() => {
+                    Ok(text_response(StatusCode::BAD_REQUEST, lang.t("receive.no_files")))
+                }
+                Ok(files) => {
+                    for file in &files {
+                        log_request(
+                            &log_mode,
+                            logger.clone(),
+                            format!(
+                                "UPLOAD {} {} sha256={}{}",
+                                file.name,
+                                file.size,
+                                file.sha256,
+                                upload::verified_note(&file.verified)
+                            ),
+                        );
+                    }
+                    let json = serde_json::json!({
+                        "files": files.iter().map(|f| serde_json::json!({
+                            "name": f.name,
+                            "size": f.size,
+                            "sha256": f.sha256,
+                        })).collect::<Vec<_>>()
+                    });
+                    let _ = received_tx.send(files);
+                    Ok(Response::builder()
+                        .header("Content-Type", "application/json")
+                        .body(Body::from(json.to_string()))
+                        .unwrap())
+                }
+                Err(e @ UploadError::TooLarge(_)) => Ok(text_response(
+                    StatusCode::PAYLOAD_TOO_LARGE,
+                    &format!("413 - {}", e.message(lang)),
+                )),
+                Err(e @ UploadError::Io(_)) => Ok(text_response(
+                    StatusCode::INTERNAL_SERVER_ERROR,
+                    &format!("500 - {}", e.message(lang)),
+                )),
+                Err(e) => Ok(text_response(
+                    StatusCode::BAD_REQUEST,
+                    &format!("400 - {}", e.message(lang)),
+                )),
+            }
+        }
+        _ => Ok(text_response(StatusCode::NOT_FOUND, lang.t("http.not_found"))),
+    }
+}
+
+/// 逐个保存 multipart 请求中的文件字段
+async fn receive_files(
+    req: Request<Body>,
+    dir: &Path,
+    max_size: Option<u64>,
+) -> Result<Vec<ReceivedFile>, UploadError> {
+    let content_type = req
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    let boundary = multer::parse_boundary(content_type)?;
+    if !upload::header_digests(req.headers())?.is_empty() {
+        return Err(UploadError::MultipartDigest);
+    }
+    let mut multipart = multer::Multipart::new(req.into_body(), boundary);
+
+    // 文件字段之前的摘要字段用于校验该文件
+    let mut files = Vec::new();
+    let mut expected = Vec::new();
+    while let Some(field) = multipart.next_field().await? {
+        if field.file_name().is_none() {
+            expected.extend(upload::digest_field(field).await?);
+            continue;
+        }
+        let expected = std::mem::take(&mut expected);
+        files.push(upload::save_field(field, dir, max_size, &expected).await?);
+    }
+    Ok(files)
+}
+
+/// 极简的上传页面
+fn upload_page(max_size: Option<u64>, lang: Lang) -> String {
+    let limit = max_size
+        .map(|l| tr!(lang, "receive.max_size", size = format_size(l)))
+        .unwrap_or_default();
+    let title = lang.t("receive.page_title");
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+    <meta charset="utf-8">
+    <meta name="viewport" content="width=device-width, initial-scale=1.0">
+    <title>📥 {title}</title>
+    <style>
+        body {{ font-family: -apple-system, 'Segoe UI', Roboto, sans-serif; max-width: 520px; margin: 60px auto; padding: 0 20px; }}
+        .drop {{ border: 2px dashed #4facfe; border-radius: 15px; padding: 40px; text-align: center; }}
+        progress {{ width: 100%; margin-top: 20px; }}
+        li {{ word-break: break-all; }}
+    </style>
+</head>
+<body>
+    <h2>📥 {title}</h2>
+    <form class="drop" id="form">
+        <input type="file" name="file" id="file" multiple>
+        <p style="color:#666">{limit}</p>
+        <button type="submit">{upload}</button>
+    </form>
+    <progress id="progress" value="0" max="100" hidden></progress>
+    <ul id="result"></ul>
+    <script>
+        document.getElementById('form').addEventListener('submit', function (e) {{
+            e.preventDefault();
+            const input = document.getElementById('file');
+            if (!input.files.length) return;
+            const data = new FormData();
+            for (const f of input.files) data.append('file', f, f.name);
+            const progress = document.getElementById('progress');
+            const result = document.getElementById('result');
+            progress.hidden = false;
+            const xhr = new XMLHttpRequest();
+            xhr.open('POST', 'upload');
+            xhr.upload.onprogress = (ev) => {{ if (ev.lengthComputable) progress.value = ev.loaded / ev.total * 100; }};
+            xhr.onload = () => {{
+                progress.hidden = true;
+                const li = document.createElement('li');
+                if (xhr.status === 200) {{
+                    const files = JSON.parse(xhr.responseText).files;
+                    li.textContent = '✅ ' + files.map(f => f.name).join(', ');
+                    input.value = '';
+                }} else {{
+                    li.textContent = '❌ ' + xhr.responseText;
+                }}
+                result.appendChild(li);
+            }};
+            xhr.onerror = () => {{
+                progress.hidden = true;
+                const li = document.createElement('li');
+                li.textContent = '❌ ' + {failed};
+                result.appendChild(li);
+            }};
+            xhr.send(data);
+        }});
+    </script>
+</body>
+</html>"#,
+        lang = lang.code(),
+        title = title,
+        limit = html_escape(&limit),
+        upload = lang.t("receive.upload"),
+        failed = serde_json::to_string(lang.t("receive.upload_failed")).unwrap(),
+    )
+}
+
@@ -0,0 +1,566 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use hyper::{header, Body, Method, Request, Response, StatusCode};
+use serde::Serialize;
+use std::{
+    net::SocketAddr,
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+use tokio::sync::Mutex;
+use walkdir::WalkDir;
+
+use crate::i18n::Lang;
+use crate::listing::href_for;
+use crate::util::{
+    format_size, format_time, html_escape, parse_duration, query_param, text_response,
+};
+use crate::{tr, AppState};
+
+/// 最近修改页面路径
+pub const RECENT_PAGE: &str = "/_recent";
+
+/// 扫描结果的缓存时间，轮询的客户端共享同一次扫描
+const SCAN_TTL: Duration = Duration::from_secs(10);
+/// 单次请求最多返回的文件数
+const MAX_LIMIT: usize = 1000;
+/// 扫描结果中最多保留的文件数（最新的）
+const MAX_SCANNED: usize = 100_000;
+
+/// 扫描到的文件
+struct ScannedFile {
+    /// 相对根目录的路径（不带前导 `/`）
+    path: String,
+    size: u64,
+    modified: SystemTime,
+}
+
+impl ScannedFile {
+    /// 修改时间（Unix 纳秒），用作 `since=` 游标
+    fn cursor(&self) -> u128 {
+        self.modified
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or(0)
+    }
+}
+
+/// 最近修改的文件：定期扫描整个根目录，按修改时间从新到旧排列
+///
+/// 扫描结果缓存 `SCAN_TTL`，期间的请求共用同一次扫描；扫描时跳过被排除的路径、
+/// 禁止列出的目录和符号链接，需要授权或受 IP 规则限制的文件在每次请求时按客户端过滤。
+pub struct RecentFiles {
+    window: Duration,
+    limit: usize,
+    scan: Mutex<Option<(Instant, Arc<Vec<ScannedFile>>)>>,
+}
+
+impl RecentFiles {
+    /// `window`、`limit` 为未指定 `window=`、`limit=` 时的默认值
+    pub fn new(window: Duration, limit: usize) -> Self {
+        RecentFiles {
+            window,
+            limit: limit.clamp(1, MAX_LIMIT),
+            scan: Mutex::new(None),
+        }
+    }
+
+    async fn files(&self, state: &Arc<AppState>) -> Arc<Vec<ScannedFile>> {
+        let mut scan = self.scan.lock().await;
+        if let Some((at, files)) = scan.as_ref() {
+            if at.elapsed() < SCAN_TTL {
+                return files.clone();
+            }
+        }
+        let task = {
+            let state = state.clone();
+            tokio::task::spawn_blocking(move || scan_files(&state))
+        };
+        let files = Arc::new(task.await.unwrap_or_default());
+        *scan = Some((Instant::now(), files.clone()));
+        files
+    }
+}
+
+fn scan_files(state: &AppState) -> Vec<ScannedFile> {
+    let root = &state.root_dir;
+    let mut files: Vec<ScannedFile> = WalkDir::new(root)
+        .min_depth(1)
+        .into_iter()
+        .filter_entry(|entry| {
+            let access = state.access.check(
+                &relative_path(root, entry.path()),
+                entry.file_type().is_dir(),
+            );
+            !access.denied && (!entry.file_type().is_dir() || access.listing)
+        })
+        .filter_map(Result::ok)
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            Some(ScannedFile {
+                path: relative_path(root, entry.path()),
+                size: metadata.len(),
+                modified: metadata.modified().ok()?,
+            })
+        })
+        .collect();
+    files.sort_by(|a, b| {
+        b.modified
+            .cmp(&a.modified)
+            .then_with(|| a.path.cmp(&b.path))
+    });
+    files.truncate(MAX_SCANNED);
+    files
+}
+
+/// 相对根目录、以 `/` 分隔的路径
+fn relative_path(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// 最近修改列表中的一项
+#[derive(Serialize)]
+struct RecentEntry {
+    /// 以 `/` 开头、相对根目录的路径
+    path: String,
+    href: String,
+    /// 所在目录的链接地址
+    folder: String,
+    size: u64,
+    size_display: String,
+    /// 修改时间（RFC 3339，UTC）
+    modified: String,
+    /// 该文件的修改时间对应的游标
+    cursor: String,
+    #[serde(skip)]
+    timestamp: i64,
+}
+
+/// `GET /_recent` 的 JSON 结果
+#[derive(Serialize)]
+struct RecentList {
+    /// 请求中的 `since=`，未指定时为 null
+    since: Option<String>,
+    /// 时间范围（秒），指定 `since=` 时不使用
+    window: u64,
+    limit: usize,
+    /// 未指定 `since=` 时从新到旧，指定时从旧到新
+    entries: Vec<RecentEntry>,
+    /// 是否因 `limit` 省略了部分文件；指定 `since=` 时用返回的 `cursor` 继续获取
+    more: bool,
+    /// 下次轮询时作为 `since=` 传入，只返回之后修改的文件
+    cursor: String,
+}
+
+/// 是否为最近修改页面的路径
+pub fn is_recent_path(path: &str) -> bool {
+    path == RECENT_PAGE
+}
+
+/// 处理 `GET /_recent`：客户端可以访问的、最近修改的文件
+///
+/// - `window=`：时间范围，例如 `24h`，默认为 `--recent-window`
+/// - `limit=`：最多返回的文件数，默认为 `--recent-limit`
+/// - `since=`：只返回修改时间晚于该游标的文件（从旧到新），游标取自上次结果的 `cursor`
+/// - `format=atom`（或 `Accept: application/atom+xml`）返回 Atom 订阅源，`format=json` 返回 JSON
+pub async fn handle(
+    req: Request<Body>,
+    remote: SocketAddr,
+    state: Arc<AppState>,
+) -> Response<Body> {
+    let lang = Lang::for_request(&req, state.lang);
+    if req.method() != Method::GET && req.method() != Method::HEAD {
+        return text_response(
+            StatusCode::METHOD_NOT_ALLOWED,
+            lang.t("http.method_not_allowed"),
+        );
+    }
+    let recent = &state.recent;
+    let query = req.uri().query();
+    let window = match query_param(query, "window")
+        .map(|w| parse_duration(&w))
+        .transpose()
+    {
+        Ok(window) => window.unwrap_or(recent.window),
+        Err(e) => {
+            return text_response(
+                StatusCode::BAD_REQUEST,
+                &tr!(lang, "recent.bad_window", error = e),
+            )
+        }
+    };
+    let limit = match query_param(query, "limit")
+        .map(|l| l.parse::<usize>())
+        .transpose()
+    {
+        Ok(Some(limit)) if (1..=MAX_LIMIT).contains(&limit) => limit,
+        Ok(None) => recent.limit,
+        _ => {
+            return text_response(
+                StatusCode::BAD_REQUEST,
+                &tr!(lang, "recent.bad_limit", max = MAX_LIMIT),
+            )
+        }
+    };
+    let since = match query_param(query, "since")
+        .map(|s| s.parse::<u128>())
+        .transpose()
+    {
+        Ok(since) => since,
+        Err(_) => return text_response(StatusCode::BAD_REQUEST, lang.t("recent.bad_since")),
+    };
+
+    let files = recent.files(&state).await;
+    let authorized = state.auth.is_authorized(&req, remote);
+    let visible = |file: &ScannedFile| {
+        let access = state.access.check(&file.path, false);
+        (!access.auth_required || authorized)
+            && access
+                .ip_rules
+                .as_ref()
+                .is_none_or(|rules| rules.check(remote.ip()).is_ok())
+    };
+    let from = SystemTime::now().checked_sub(window).unwrap_or(UNIX_EPOCH);
+    let (selected, more, cursor) = select(&files, since, from, limit, visible);
+
+    let list = RecentList {
+        since: since.map(|s| s.to_string()),
+        window: window.as_secs(),
+        limit,
+        entries: selected.into_iter().map(entry).collect(),
+        more,
+        cursor: cursor.to_string(),
+    };
+    let accept = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("");
+    if query_param(query, "format").as_deref() == Some("atom")
+        || accept.contains("application/atom+xml")
+    {
+        let host = req
+            .headers()
+            .get(header::HOST)
+            .and_then(|h| h.to_str().ok())
+            .unwrap_or("127.0.0.1");
+        feed(&list, &format!("{}://{}", state.scheme, host), lang)
+    } else if crate::wants_json(&req) {
+        Response::builder()
+            .header("Content-Type", "application/json")
+            .header(header::CACHE_CONTROL, "no-store")
+            .header("Access-Control-Allow-Origin", "*")
+            .body(Body::from(serde_json::to_string(&list).unwrap()))
+            .unwrap()
+    } else {
+        page(&list, window, lang)
+    }
+}
+
+/// 从按修改时间从新到旧排列的 `files` 中选出要返回的文件，同时返回是否还有更多和新的游标
+///
+/// 指定 `since` 时选出之后修改的文件（从旧到新），否则选出 `from` 之后修改的文件（从新到旧），
+/// 最多 `limit` 个，`visible` 为 false 的文件跳过。
+fn select(
+    files: &[ScannedFile],
+    since: Option<u128>,
+    from: SystemTime,
+    limit: usize,
+    visible: impl Fn(&ScannedFile) -> bool,
+) -> (Vec<&ScannedFile>, bool, u128) {
+    let mut selected: Vec<&ScannedFile> = match since {
+        Some(since) => {
+            let mut newer: Vec<_> = files
+                .iter()
+                .take_while(|file| file.cursor() > since)
+                .filter(|file| visible(file))
+                .collect();
+            newer.sort_by(|a, b| {
+                a.modified
+                    .cmp(&b.modified)
+                    .then_with(|| a.path.cmp(&b.path))
+            });
+            newer
+        }
+        None => files
+            .iter()
+            .take_while(|file| file.modified >= from)
+            .filter(|file| visible(file))
+            .take(limit + 1)
+            .collect(),
+    };
+    let more = selected.len() > limit;
+    if more {
+        // 游标是修改时间：分页时不在修改时间相同的文件之间截断，否则下次轮询会漏掉其余的文件
+        let boundary = selected[limit].cursor();
+        let mut end = limit;
+        if since.is_some() {
+            while end > 0 && selected[end - 1].cursor() == boundary {
+                end -= 1;
+            }
+            if end == 0 {
+                end = selected
+                    .iter()
+                    .take_while(|file| file.cursor() == boundary)
+                    .count();
+            }
+        }
+        selected.truncate(end);
+    }
+    // 没有新文件时游标不变；首次请求时从最新的文件开始，之后只获取新的修改
+    let cursor = selected
+        .iter()
+        .map(|file| file.cursor())
+        .max()
+        .or(since)
+        .or_else(|| files.iter().find(|file| visible(file)).map(|file| file.cursor()))
+        .unwrap_or(0);
+    (selected, more, cursor)
+}
+
+fn entry(file: &ScannedFile) -> RecentEntry {
+    let folder = file.path.rsplit_once('/').map(|(dir, _)| dir).unwrap_or("");
+    let modified = DateTime::<Utc>::from(file.modified);
+    RecentEntry {
+        path: format!("/{}", file.path),
+        href: href_for(&file.path),
+        folder: href_for(folder),
+        size: file.size,
+        size_display: format_size(file.size),
+        modified: modified.to_rfc3339_opts(SecondsFormat::Secs, true),
+        cursor: file.cursor().to_string(),
+        timestamp: modified.timestamp(),
+    }
+}
+
+/// 以 `parse_duration` 接受的形式显示时间范围，例如 `7d`、`36h`
+fn format_window(window: Duration) -> String {
+    let secs = window.as_secs();
+    match secs {
+        0 => "0s".to_string(),
+        _ if secs.is_multiple_of(86400) => format!("{}d", secs / 86400),
+        _ if secs.is_multiple_of(3600) => format!("{}h", secs / 3600),
+        _ if secs.is_multiple_of(60) => format!("{}m", secs / 60),
+        _ => format!("{}s", secs),
+    }
+}
+
+/// Atom 订阅源，每次修改为一个条目（同一文件再次修改时条目 ID 不同）
+fn feed(list: &RecentList, base: &str, lang: Lang) -> Response<Body> {
+    let updated = list
+        .entries
+        .iter()
+        .map(|entry| entry.modified.as_str())
+        .max()
+        .map(str::to_string)
+        .unwrap_or_else(|| Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true));
+    let mut xml = format!(
+        r#"<?xml version="1.0" encoding="utf-8"?>
+<feed xmlns="http://www.w3.org/2005/Atom">
+  <title>{title}</title>
+  <id>{base}{page}</id>
+  <link rel="self" href="{base}{page}?format=atom"/>
+  <link rel="alternate" type="text/html" href="{base}{page}"/>
+  <updated>{updated}</updated>
+  <author><name>{author}</name></author>
+"#,
+        title = html_escape(lang.t("recent.page_title")),
+        base = html_escape(base),
+        page = RECENT_PAGE,
+        updated = updated,
+        author = html_escape(lang.t("listing.title")),
+    );
+    for entry in &list.entries {
+        xml += &format!(
+            r#"  <entry>
+    <title>{path}</title>
+    <id>{base}{href}#{cursor}</id>
+    <link href="{base}{href}"/>
+    <updated>{modified}</updated>
+    <summary>{summary}</summary>
+  </entry>
+"#,
+            path = html_escape(&entry.path),
+            base = html_escape(base),
+            href = html_escape(&entry.href),
+            cursor = entry.cursor,
+            modified = entry.modified,
+            summary = html_escape(&tr!(
+                lang,
+                "recent.entry_summary",
+                size = &entry.size_display,
+                time = format_time(entry.timestamp)
+            )),
+        );
+    }
+    xml += "</feed>\n";
+    Response::builder()
+        .header("Content-Type", "application/atom+xml; charset=utf-8")
+        .header(header::CACHE_CONTROL, "no-store")
+        .body(Body::from(xml))
+        .unwrap()
+}
+
+/// 最近修改页面
+fn page(list: &RecentList, window: Duration, lang: Lang) -> Response<Body> {
+    let mut rows = String::new();
+    for entry in &list.entries {
+        let name = entry.path.rsplit('/').next().unwrap_or(&entry.path);
+        let folder = entry
+            .path
+            .rsplit_once('/')
+            .map(|(dir, _)| dir)
+            .unwrap_or("");
+        rows += &format!(
+            r#"<tr><td>📄 <a href="{}">{}</a></td><td>📁 <a href="{}">{}/</a></td><td>{}</td><td>{}</td></tr>
+"#,
+            html_escape(&entry.href),
+            html_escape(name),
+            html_escape(&entry.folder),
+            html_escape(folder),
+            entry.size_display,
+            format_time(entry.timestamp),
+        );
+    }
+    if list.entries.is_empty() {
+        rows = format!(
+            r#"<tr><td colspan="4" style="text-align:center; color:#666">{}</td></tr>"#,
+            lang.t("recent.none")
+        );
+    }
+    let mut summary = tr!(
+        lang,
+        "recent.summary",
+        count = list.entries.len(),
+        window = format_window(window)
+    );
+    if list.more {
+        summary += " ";
+        summary += &tr!(lang, "recent.more", limit = list.limit);
+    }
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+    <meta charset="utf-8">
+    <title>🕒 {title}</title>
+    <link rel="alternate" type="application/atom+xml" title="{title}" href="{page}?format=atom">
+    <style>
+        body {{ font-family: -apple-system, 'Segoe UI', Roboto, sans-serif; margin: 40px; }}
+        table {{ border-collapse: collapse; width: 100%; }}
+        th, td {{ border-bottom: 1px solid #e9ecef; padding: 8px; text-align: left; }}
+        td:first-child, td:nth-child(2) {{ word-break: break-all; }}
+        .summary {{ color: #666; }}
+    </style>
+</head>
+<body>
+    <h2>🕒 {title}</h2>
+    <p><a href="/">🏠 {home}</a> | 📰 <a href="{page}?format=atom">{feed}</a> | 🧾 <a href="{page}?format=json">JSON</a></p>
+    <form method="get" action="{page}">
+        <label>{label_window} <input name="window" value="{window}" size="6"></label>
+        <label>{label_limit} <input name="limit" value="{limit}" size="6"></label>
+        <button>{show}</button>
+    </form>
+    <p class="summary">{summary}</p>
+    <table>
+        <tr><th>{col_file}</th><th>{col_folder}</th><th>{col_size}</th><th>{col_modified}</th></tr>
+        {rows}
+    </table>
+</body>
+</html>"#,
+        lang = lang.code(),
+        title = lang.t("recent.page_title"),
+        page = RECENT_PAGE,
+        home = lang.t("listing.home"),
+        feed = lang.t("recent.feed"),
+        label_window = lang.t("recent.label_window"),
+        window = format_window(window),
+        label_limit = lang.t("recent.label_limit"),
+        limit = list.limit,
+        show = lang.t("recent.show"),
+        summary = html_escape(&summary),
+        col_file = lang.t("recent.col_file"),
+        col_folder = lang.t("recent.col_folder"),
+        col_size = lang.t("recent.col_size"),
+        col_modified = lang.t("recent.col_modified"),
+        rows = rows,
+    );
+    Response::builder()
+        .header("Content-Type", "text/html; charset=utf-8")
+        .header(header::CACHE_CONTROL, "no-store")
+        .body(Body::from(html))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 按修改时间（纳秒）从新到旧排列的文件，与扫描结果的顺序相同
+    fn scanned(files: &[(&str, u64)]) -> Vec<ScannedFile> {
+        let mut files: Vec<ScannedFile> = files
+            .iter()
+            .map(|(path, nanos)| ScannedFile {
+                path: path.to_string(),
+                size: 0,
+                modified: UNIX_EPOCH + Duration::from_nanos(*nanos),
+            })
+            .collect();
+        files.sort_by(|a, b| {
+            b.modified
+                .cmp(&a.modified)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        files
+    }
+
+    fn paths(selected: &[&ScannedFile]) -> Vec<String> {
+        selected.iter().map(|file| file.path.clone()).collect()
+    }
+
+    #[test]
+    fn pages_do_not_split_equal_modification_times() {
+        let files = scanned(&[("a", 1), ("b", 2), ("c", 2), ("d", 2), ("e", 3)]);
+        let page = |since| select(&files, Some(since), UNIX_EPOCH, 2, |_: &ScannedFile| true);
+
+        // 第 2、3 个文件修改时间相同，只返回第 1 个
+        let (selected, more, cursor) = page(0);
+        assert_eq!((paths(&selected), more, cursor), (vec!["a".to_string()], true, 1));
+        // 相同修改时间的文件超过 limit 时一次全部返回
+        let (selected, more, cursor) = page(cursor);
+        assert_eq!(paths(&selected), ["b", "c", "d"]);
+        assert_eq!((more, cursor), (true, 2));
+        let (selected, more, cursor) = page(cursor);
+        assert_eq!((paths(&selected), more, cursor), (vec!["e".to_string()], false, 3));
+        // 没有新文件时游标不变
+        let (selected, more, cursor) = page(cursor);
+        assert_eq!((selected.len(), more, cursor), (0, false, 3));
+    }
+
+    #[test]
+    fn latest_files_skip_hidden_ones() {
+        let files = scanned(&[("old", 1), ("a", 5), ("hidden", 9), ("b", 6), ("c", 7)]);
+        let visible = |file: &ScannedFile| file.path != "hidden";
+        let from = UNIX_EPOCH + Duration::from_nanos(2);
+
+        let (selected, more, cursor) = select(&files, None, from, 2, visible);
+        assert_eq!(paths(&selected), ["c", "b"]);
+        assert_eq!((more, cursor), (true, 7));
+        let (selected, more, _) = select(&files, None, from, 3, visible);
+        assert_eq!(paths(&selected), ["c", "b", "a"]);
+        assert!(!more);
+
+        // 时间范围内没有文件时，游标从最新的可见文件开始
+        let from = UNIX_EPOCH + Duration::from_nanos(100);
+        let (selected, _, cursor) = select(&files, None, from, 2, visible);
+        assert_eq!((selected.len(), cursor), (0, 7));
+        let (selected, _, _) = select(&files, Some(0), from, 10, visible);
+        assert_eq!(paths(&selected), ["old", "a", "b", "c"]);
+    }
+}
@@ -0,0 +1,325 @@
+use futures_util::Stream;
+use hyper::body::Bytes;
+use hyper::{
+    header,
+    service::{make_service_fn, service_fn},
+    Body, Request, Response, StatusCode,
+};
+use mime_guess::from_path;
+use percent_encoding::{percent_decode_str, utf8_percent_encode};
+use std::{
+    convert::Infallible,
+    fs,
+    io::{self, Write},
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    time::Duration,
+    task::{Context, Poll},
+};
+use tokio::fs::File;
+use tokio::sync::{mpsc, Notify};
+use tokio_util::io::ReaderStream;
+use walkdir::WalkDir;
+
+use crate::banner::{listener_urls, print_urls};
+use crate::i18n::Lang;
+use crate::listener::{self, Conn, ConnectionCaps, ConnectionLimits, Listener};
+use crate::util::{parse_duration, random_token, text_response, SEGMENT};
+use crate::{log_request, qr, shutdown, tr, SendArgs, SharedLogger};
+
+/// 待发送的内容：单个文件，或打包好的目录 zip
+struct Payload {
+    /// 实际读取的文件
+    file: PathBuf,
+    /// 下载时显示的文件名
+    name: String,
+    /// 目录打包生成的临时文件，退出时删除
+    temp: bool,
+}
+
+/// 包装文件流，整个文件交给连接发送后（读到结尾或达到 Content-Length）发出通知；
+/// 客户端中途断开时流被直接丢弃，不计为完成
+struct NotifyOnEnd<S> {
+    inner: S,
+    /// 尚未发送的字节数；设置了 Content-Length 时 hyper 发送完不会再轮询到流结尾
+    remaining: u64,
+    done: Option<mpsc::UnboundedSender<()>>,
+}
+
+impl<S> NotifyOnEnd<S> {
+    fn notify(&mut self) {
+        if let Some(done) = self.done.take() {
+            let _ = done.send(());
+        }
+    }
+}
+
+impl<S: Stream<Item = io::Result<Bytes>> + Unpin> Stream for NotifyOnEnd<S> {
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let poll = Pin::new(&mut self.inner).poll_next(cx);
+        match &poll {
+            Poll::Ready(Some(Ok(chunk))) => {
+                self.remaining = self.remaining.saturating_sub(chunk.len() as u64);
+                if self.remaining == 0 {
+                    self.notify();
+                }
+            }
+            Poll::Ready(None) => self.notify(),
+            _ => {}
+        }
+        poll
+    }
+}
+
+/// `send` 子命令：只发送一个文件（或打包后的目录），完成指定次数下载或超时后退出
+pub async fn run_send(
+    cmd: &SendArgs,
+    listeners: Vec<Listener>,
+    log_mode: Arc<String>,
+    logger: SharedLogger,
+    drain_timeout: Duration,
+    limits: &ConnectionLimits,
+    lang: Lang,
+) {
+    let source = PathBuf::from(&cmd.path);
+    let timeout = match cmd.timeout.as_deref().map(parse_duration).transpose() {
+        Ok(t) => t,
+        Err(e) => {
+            eprintln!("❌ \x1B[91m{}\x1B[0m", e);
+            std::process::exit(1);
+        }
+    };
+
+    let payload = if source.is_file() {
+        Payload {
+            name: file_name(&source),
+            file: source,
+            temp: false,
+        }
+    } else if source.is_dir() {
+        println!("📦 {}", lang.t("send.zipping"));
+        let name = format!("{}.zip", file_name(&source));
+        let target = std::env::temp_dir().join(format!("file_server-{}.zip", random_token(8)));
+        let (src, dst) = (source.clone(), target.clone());
+        match tokio::task::spawn_blocking(move || zip_dir(&src, &dst)).await {
+            Ok(Ok(())) => Payload {
+                file: target,
+                name,
+                temp: true,
+            },
+            Ok(Err(e)) => {
+                let _ = fs::remove_file(&target);
+                eprintln!("❌ \x1B[91m{}\x1B[0m", tr!(lang, "send.zip_failed", error = e));
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("❌ \x1B[91m{}\x1B[0m", e);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        eprintln!("❌ \x1B[91m{}\x1B[0m", tr!(lang, "send.not_found", path = cmd.path));
+        std::process::exit(1);
+    };
+    let payload = Arc::new(payload);
+
+    let token = random_token(16);
+    let url_path = format!("/{}/{}", token, utf8_percent_encode(&payload.name, SEGMENT));
+    let urls = listener_urls(&listeners, "http", &url_path);
+
+    println!("📤 \x1B[92m{}\x1B[0m", tr!(lang, "send.ready", name = payload.name));
+    print_urls(lang.t("banner.address"), &urls);
+    println!(
+        "📥 {}",
+        match timeout {
+            Some(t) => tr!(lang, "send.exit_after_or_timeout", downloads = cmd.downloads, seconds = t.as_secs()),
+            None => tr!(lang, "send.exit_after", downloads = cmd.downloads),
+        }
+    );
+    qr::print_qr(&urls[0]);
+
+    let (done_tx, mut done_rx) = mpsc::unbounded_channel();
+    let token = Arc::new(token);
+    let make_service = make_service_fn(move |_conn: &Conn| {
+        let payload = payload.clone();
+        let token = token.clone();
+        let log_mode = log_mode.clone();
+        let logger = logger.clone();
+        let done_tx = done_tx.clone();
+        async move {
+            Ok::<_, Infallible>(service_fn(move |req| {
+                handle_send(
+                    req,
+                    payload.clone(),
+                    token.clone(),
+                    log_mode.clone(),
+                    logger.clone(),
+                    done_tx.clone(),
+                    lang,
+                )
+            }))
+        }
+    });
+
+    let downloads = cmd.downloads;
+    let finished = async move {
+        let wait_downloads = async {
+            let mut completed = 0;
+            while completed < downloads && done_rx.recv().await.is_some() {
+                completed += 1;
+                println!(
+                    "✅ {}",
+                    tr!(lang, "send.completed", completed = completed, total = downloads)
+                );
+            }
+        };
+        match timeout {
+            Some(t) => {
+                if tokio::time::timeout(t, wait_downloads).await.is_err() {
+                    println!("⌛ {}", lang.t("send.timed_out"));
+                }
+            }
+            None => wait_downloads.await,
+        }
+    };
+
+    // 正常完成或收到 Ctrl-C / SIGTERM 时都优雅退出
+    let draining = Arc::new(Notify::new());
+    let trigger = {
+        let draining = draining.clone();
+        async move {
+            tokio::select! {
+                _ = finished => {}
+                _ = shutdown::signal() => shutdown::announce(drain_timeout, lang),
+            }
+            draining.notify_one();
+        }
+    };
+    let server = listener::server(listeners, limits, ConnectionCaps::new(limits), None, lang)
+        .serve(make_service)
+        .with_graceful_shutdown(trigger);
+    if let Err(e) = shutdown::drain(server, draining, drain_timeout, lang).await {
+        eprintln!("❌ \x1B[91m{}\x1B[0m", tr!(lang, "common.server_error", error = e));
+    }
+    println!("👋 {}", lang.t("common.bye"));
+}
+
+/// 服务结束、最后一个引用释放时删除临时 zip
+impl Drop for Payload {
+    fn drop(&mut self) {
+        if self.temp {
+            let _ = fs::remove_file(&self.file);
+        }
+    }
+}
+
+async fn handle_send(
+    req: Request<Body>,
+    payload: Arc<Payload>,
+    token: Arc<String>,
+    log_mode: Arc<String>,
+    logger: SharedLogger,
+    done_tx: mpsc::UnboundedSender<()>,
+    lang: Lang,
+) -> Result<Response<Body>, Infallible> {
+    let path = req.uri().path().to_string();
+    log_request(&log_mode, logger, format!("{} {}", req.method(), path));
+    let lang = Lang::for_request(&req, lang);
+
+    // 只响应 /<token>、/<token>/ 和 /<token>/<文件名>
+    let decoded = percent_decode_str(&path).decode_utf8_lossy();
+    let rest = decoded.trim_start_matches('/');
+    let matches = match rest.split_once('/') {
+        None => rest == token.as_str(),
+        Some((t, name)) => t == token.as_str() && (name.is_empty() || name == payload.name),
+    };
+    if !matches {
+        return Ok(text_response(StatusCode::NOT_FOUND, lang.t("http.not_found")));
+    }
+
+    match File::open(&payload.file).await {
+        Ok(file) => {
+            let len = file.metadata().await.map(|m| m.len()).ok();
+            let mime = from_path(&payload.name).first_or_octet_stream();
+            let stream = NotifyOnEnd {
+                inner: ReaderStream::new(file),
+                remaining: len.unwrap_or(u64::MAX),
+                done: Some(done_tx),
+            };
+            let mut builder = Response::builder()
+                .header("Content-Type", mime.as_ref())
+                .header(
+                    header::CONTENT_DISPOSITION,
+                    format!(
+                        "attachment; filename*=UTF-8''{}",
+                        utf8_percent_encode(&payload.name, SEGMENT)
+                    ),
+                );
+            if let Some(len) = len {
+                builder = builder.header(header::CONTENT_LENGTH, len);
+            }
+            Ok(builder.body(Body::wrap_stream(stream)).unwrap())
+        }
+        Err(_) => Ok(text_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            lang.t("http.open_file_failed"),
+        )),
+    }
+}
+
+fn file_name(path: &Path) -> String {
+    path.canonicalize()
+        .ok()
+        .and_then(|p| p.file_name().map(|n| n.to_string_lossy().to_string()))
+        .unwrap_or_else(|| "download".to_string())
+}
+
+/// 将目录递归打包为 zip 文件
+fn zip_dir(source: &Path, target: &Path) -> io::Result<()> {
+    use zip::write::SimpleFileOptions;
+
+    let file = fs::File::create(target)?;
+    let mut zip = zip::ZipWriter::new(io::BufWriter::new(file));
+    let options = SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated)
+        .large_file(true);
+
+    for entry in WalkDir::new(source).min_depth(1) {
+        let entry = entry.map_err(io::Error::other)?;
+        let relative = entry
+            .path()
+            .strip_prefix(source)
+            .map_err(io::Error::other)?
+            .to_string_lossy()
+            .replace('\\', "/");
+        let options = match entry.metadata().ok().and_then(|m| m.modified().ok()) {
+            Some(modified) => options.last_modified_time(zip_time(modified)),
+            None => options,
+        };
+        if entry.file_type().is_dir() {
+            zip.add_directory(relative, options).map_err(io::Error::other)?;
+        } else if entry.file_type().is_file() {
+            zip.start_file(relative, options).map_err(io::Error::other)?;
+            io::copy(&mut fs::File::open(entry.path())?, &mut zip)?;
+        }
+    }
+    zip.finish().map_err(io::Error::other)?.flush()
+}
+
+/// 将文件修改时间转换为 zip 条目时间（本地时区），超出 zip 可表示范围时使用默认值
+fn zip_time(modified: std::time::SystemTime) -> zip::DateTime {
+    use chrono::{Datelike, Timelike};
+    let t: chrono::DateTime<chrono::Local> = modified.into();
+    zip::DateTime::from_date_and_time(
+        t.year().clamp(1980, 2107) as u16,
+        t.month() as u8,
+        t.day() as u8,
+        t.hour() as u8,
+        t.minute() as u8,
+        t.second() as u8,
+    )
+    .unwrap_or_default()
+}
@@ -0,0 +1,828 @@
+use chrono::Utc;
+use hmac::{Hmac, Mac};
+use hyper::{header, Body, Method, Request, Response, StatusCode};
+use mime_guess::from_path;
+use percent_encoding::{percent_decode_str, utf8_percent_encode};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    convert::Infallible,
+    fs,
+    io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::fs::File;
+
+use crate::auth;
+use crate::limit;
+use crate::i18n::Lang;
+use crate::util::{
+    format_time, html_escape, normalize_relative, parse_duration, query_param, random_token, read_body, text_response,
+    SEGMENT,
+};
+use crate::{log_request, tr, AppState};
+
+type HmacSha256 = Hmac<Sha256>;
+
+/// 分享链接下载路径前缀
+const SHARE_PREFIX: &str = "/_share/";
+/// 分享管理页面路径
+const SHARES_PAGE: &str = "/_shares";
+/// 分享链接存储文件名
+const STORE_FILE: &str = "shares.json";
+/// 创建分享请求体的最大字节数
+const MAX_CREATE_BODY: usize = 16 * 1024;
+/// 提交密码请求体的最大字节数
+const MAX_PASSWORD_BODY: usize = 4 * 1024;
+
+/// 一条分享链接
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ShareLink {
+    pub id: String,
+    /// 相对根目录的文件路径，使用 `/` 分隔
+    pub path: String,
+    pub created: i64,
+    pub expires: i64,
+    pub max_downloads: Option<u32>,
+    pub downloads: u32,
+    /// `<盐>$<HMAC-SHA256>`；旧版本保存的是不带盐的 SHA-256
+    password_hash: Option<String>,
+}
+
+impl ShareLink {
+    fn is_expired(&self, now: i64) -> bool {
+        now >= self.expires
+    }
+
+    fn is_exhausted(&self) -> bool {
+        self.max_downloads.is_some_and(|max| self.downloads >= max)
+    }
+
+    fn file_name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or(&self.path)
+    }
+}
+
+/// 磁盘上的存储格式
+#[derive(Serialize, Deserialize, Default)]
+struct StoreData {
+    secret: String,
+    shares: Vec<ShareLink>,
+}
+
+/// 分享链接校验失败的原因
+#[derive(Debug)]
+enum ShareError {
+    NotFound,
+    BadSignature,
+    Expired,
+    Exhausted,
+    PasswordRequired,
+    WrongPassword,
+}
+
+/// 分享链接存储，保存在数据目录下的 `shares.json` 中，
+/// 服务器与 `share` 子命令共用同一份文件，因此每次访问都重新读取
+pub struct ShareStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl ShareStore {
+    /// 打开（必要时创建）数据目录下的分享存储
+    pub fn open(data_dir: &Path) -> io::Result<Self> {
+        fs::create_dir_all(data_dir)?;
+        let store = ShareStore {
+            path: data_dir.join(STORE_FILE),
+            lock: Mutex::new(()),
+        };
+        let _guard = store.lock.lock().unwrap();
+        let data = store.load()?;
+        if data.secret.is_empty() {
+            store.save(&StoreData {
+                secret: random_token(32),
+                shares: data.shares,
+            })?;
+        }
+        drop(_guard);
+        Ok(store)
+    }
+
+    fn load(&self) -> io::Result<StoreData> {
+        match fs::read(&self.path) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(StoreData::default()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 先写临时文件再重命名，避免写入中途崩溃损坏存储
+    fn save(&self, data: &StoreData) -> io::Result<()> {
+        let tmp = self.path.with_extension("json.tmp");
+        let json = serde_json::to_vec_pretty(data)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, &self.path)
+    }
+
+    /// 创建一条新的分享链接
+    pub fn create(
+        &self,
+        path: &str,
+        ttl: Duration,
+        max_downloads: Option<u32>,
+        password: Option<&str>,
+    ) -> io::Result<ShareLink> {
+        let now = Utc::now().timestamp();
+        let expires = i64::try_from(ttl.as_secs())
+            .ok()
+            .and_then(|ttl| now.checked_add(ttl))
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "expiry out of range"))?;
+        let _guard = self.lock.lock().unwrap();
+        let mut data = self.load()?;
+        let id = random_token(12);
+        let link = ShareLink {
+            password_hash: password.map(hash_password),
+            id,
+            path: path.to_string(),
+            created: now,
+            expires,
+            max_downloads,
+            downloads: 0,
+        };
+        data.shares.retain(|s| !s.is_expired(now));
+        data.shares.push(link.clone());
+        self.save(&data)?;
+        Ok(link)
+    }
+
+    /// 列出所有分享链接
+    pub fn list(&self) -> io::Result<Vec<ShareLink>> {
+        let _guard = self.lock.lock().unwrap();
+        Ok(self.load()?.shares)
+    }
+
+    /// 撤销分享链接，返回是否存在该链接
+    pub fn revoke(&self, id: &str) -> io::Result<bool> {
+        let _guard = self.lock.lock().unwrap();
+        let mut data = self.load()?;
+        let before = data.shares.len();
+        data.shares.retain(|s| s.id != id);
+        let removed = data.shares.len() != before;
+        if removed {
+            self.save(&data)?;
+        }
+        Ok(removed)
+    }
+
+    /// 生成分享链接的路径和查询部分
+    pub fn signed_path(&self, link: &ShareLink) -> io::Result<String> {
+        let _guard = self.lock.lock().unwrap();
+        let data = self.load()?;
+        Ok(format!(
+            "{}{}/{}?e={}&s={}",
+            SHARE_PREFIX,
+            link.id,
+            utf8_percent_encode(link.file_name(), SEGMENT),
+            link.expires,
+            sign(&data.secret, link)
+        ))
+    }
+
+    /// 校验签名、有效期、下载次数和密码，不计入下载次数
+    fn authorize(
+        &self,
+        id: &str,
+        expires: &str,
+        signature: &str,
+        password: Option<&str>,
+    ) -> Result<ShareLink, ShareError> {
+        let _guard = self.lock.lock().unwrap();
+        let data = self.load().map_err(|_| ShareError::NotFound)?;
+        let link = data
+            .shares
+            .iter()
+            .find(|s| s.id == id)
+            .ok_or(ShareError::NotFound)?;
+
+        if expires != link.expires.to_string() || !verify(&data.secret, link, signature) {
+            return Err(ShareError::BadSignature);
+        }
+        if link.is_expired(Utc::now().timestamp()) {
+            return Err(ShareError::Expired);
+        }
+        if link.is_exhausted() {
+            return Err(ShareError::Exhausted);
+        }
+        if let Some(hash) = &link.password_hash {
+            match password {
+                None => return Err(ShareError::PasswordRequired),
+                Some(p) if !password_matches(hash, id, p) => return Err(ShareError::WrongPassword),
+                Some(_) => {}
+            }
+        }
+        Ok(link.clone())
+    }
+
+    /// 计入一次下载；在开始发送文件前调用，期间被撤销、过期或用完次数时返回错误
+    fn redeem(&self, id: &str) -> Result<ShareLink, ShareError> {
+        let _guard = self.lock.lock().unwrap();
+        let mut data = self.load().map_err(|_| ShareError::NotFound)?;
+        let link = data
+            .shares
+            .iter_mut()
+            .find(|s| s.id == id)
+            .ok_or(ShareError::NotFound)?;
+        if link.is_expired(Utc::now().timestamp()) {
+            return Err(ShareError::Expired);
+        }
+        if link.is_exhausted() {
+            return Err(ShareError::Exhausted);
+        }
+        link.downloads += 1;
+        let link = link.clone();
+        self.save(&data).map_err(|_| ShareError::NotFound)?;
+        Ok(link)
+    }
+}
+
+fn signing_input(link: &ShareLink) -> String {
+    format!("{}\n{}\n{}", link.id, link.path, link.expires)
+}
+
+fn sign(secret: &str, link: &ShareLink) -> String {
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(signing_input(link).as_bytes());
+    hex::encode(mac.finalize().into_bytes())
+}
+
+fn verify(secret: &str, link: &ShareLink, signature: &str) -> bool {
+    let Ok(expected) = hex::decode(signature) else {
+        return false;
+    };
+    let mut mac = HmacSha256::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(signing_input(link).as_bytes());
+    mac.verify_slice(&expected).is_ok()
+}
+
+/// 以每条链接随机生成的盐计算密码摘要
+fn hash_password(password: &str) -> String {
+    salted_hash(&random_token(16), password)
+}
+
+fn salted_hash(salt: &str, password: &str) -> String {
+    let mut mac = HmacSha256::new_from_slice(salt.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(password.as_bytes());
+    format!("{}${}", salt, hex::encode(mac.finalize().into_bytes()))
+}
+
+/// 以常量时间比较密码摘要，兼容旧版本保存的 `sha256("<id>:<密码>")`
+fn password_matches(stored: &str, id: &str, password: &str) -> bool {
+    let expected = match stored.split_once('$') {
+        Some((salt, _)) => salted_hash(salt, password),
+        None => hex::encode(Sha256::digest(format!("{}:{}", id, password).as_bytes())),
+    };
+    auth::constant_time_eq(stored.as_bytes(), expected.as_bytes())
+}
+
+/// 是否为分享相关的请求路径
+pub fn is_share_path(path: &str) -> bool {
+    path.starts_with(SHARE_PREFIX) || path == SHARES_PAGE || path.starts_with("/_shares/")
+}
+
+/// 处理分享相关的请求：下载、创建、列表与撤销
+pub async fn handle_share_request(
+    req: Request<Body>,
+    remote: SocketAddr,
+    state: Arc<AppState>,
+) -> Result<Response<Body>, Infallible> {
+    let method = req.method().clone();
+    let path = req.uri().path().to_string();
+    log_request(&state.log_mode, state.logger.clone(), format!("{} {}", method, path));
+    let (store, auth) = (&state.share_store, &state.auth);
+    let lang = Lang::for_request(&req, state.lang);
+
+    if let Some(rest) = path.strip_prefix(SHARE_PREFIX) {
+        return Ok(serve_share(req, rest, remote, &state, lang).await);
+    }
+
+    if !auth.is_authorized(&req, remote) {
+        return Ok(auth::unauthorized(lang));
+    }
+    if method == Method::POST {
+        if let Some(response) = auth::reject_cross_site(&req, lang) {
+            return Ok(response);
+        }
+    }
+
+    let response = match (method, path.as_str()) {
+        (Method::GET, SHARES_PAGE) => {
+            let mut response = list_page(&req, store, state.scheme, lang);
+            if let Some(cookie) = auth.cookie_for(&req) {
+                response
+                    .headers_mut()
+                    .insert(header::SET_COOKIE, cookie.parse().unwrap());
+            }
+            response
+        }
+        (Method::POST, "/_shares/create") => create_from_request(req, &state, lang).await,
+        (Method::POST, "/_shares/revoke") => revoke_from_request(req, store, lang).await,
+        _ => text_response(StatusCode::NOT_FOUND, lang.t("http.not_found")),
+    };
+    Ok(response)
+}
+
+/// 处理 `/_share/<id>/<name>?e=..&s=..` 下载请求；密码通过 POST 表单提交，不出现在地址中
+async fn serve_share(
+    req: Request<Body>,
+    rest: &str,
+    remote: SocketAddr,
+    state: &AppState,
+    lang: Lang,
+) -> Response<Body> {
+    let id = rest.split('/').next().unwrap_or("");
+    let query = req.uri().query();
+    let (Some(expires), Some(signature)) = (query_param(query, "e"), query_param(query, "s"))
+    else {
+        return text_response(StatusCode::FORBIDDEN, lang.t("share.invalid"));
+    };
+    let (parts, body) = req.into_parts();
+    let password = if parts.method == Method::POST {
+        match read_body(Request::new(body), MAX_PASSWORD_BODY).await {
+            Some(body) => form_urlencoded::parse(&body)
+                .find(|(k, _)| k == "p")
+                .map(|(_, v)| v.into_owned()),
+            None => return text_response(StatusCode::PAYLOAD_TOO_LARGE, lang.t("http.body_too_large")),
+        }
+    } else {
+        None
+    };
+    let req = Request::from_parts(parts, Body::empty());
+
+    let store = &state.share_store;
+    let link = match store.authorize(id, &expires, &signature, password.as_deref()) {
+        Ok(link) => link,
+        Err(e) => return share_error(e, &expires, &signature, lang),
+    };
+
+    // 分享链接不受 auth 设置限制，但创建后被排除的文件不再提供
+    if state.access.check(&link.path, false).denied {
+        return text_response(StatusCode::NOT_FOUND, lang.t("http.not_found"));
+    }
+    let full_path = state.root_dir.join(&link.path);
+    let Some(slot) = state.limits.download_slot() else {
+        return limit::too_many_requests(limit::BUSY_RETRY_AFTER, lang.t("http.too_many_downloads"));
+    };
+    match File::open(&full_path).await {
+        Ok(file) => {
+            // 拿到下载名额并打开文件后才计入次数，繁忙或文件缺失时不消耗次数
+            let link = match store.redeem(id) {
+                Ok(link) => link,
+                Err(e) => return share_error(e, &expires, &signature, lang),
+            };
+            let mime = from_path(&full_path).first_or_octet_stream();
+            let size = file.metadata().await.ok().map(|m| m.len());
+            let body = state.limits.file_body(file, &req, slot);
+            let path = format!("/{}", link.path);
+            Response::builder()
+                .header("Content-Type", mime.as_ref())
+                .header(
+                    header::CONTENT_DISPOSITION,
+                    format!(
+                        "attachment; filename*=UTF-8''{}",
+                        utf8_percent_encode(link.file_name(), SEGMENT)
+                    ),
+                )
+                .header(header::CACHE_CONTROL, "no-store")
+                .body(state.monitor.track_download(body, &path, remote.ip(), size))
+                .unwrap()
+        }
+        Err(_) => text_response(StatusCode::NOT_FOUND, lang.t("http.not_found")),
+    }
+}
+
+/// 链接校验失败时的响应
+fn share_error(error: ShareError, expires: &str, signature: &str, lang: Lang) -> Response<Body> {
+    match error {
+        ShareError::NotFound => text_response(StatusCode::NOT_FOUND, lang.t("share.not_found")),
+        ShareError::BadSignature => text_response(StatusCode::FORBIDDEN, lang.t("share.invalid")),
+        ShareError::Expired => text_response(StatusCode::GONE, lang.t("share.expired")),
+        ShareError::Exhausted => text_response(StatusCode::GONE, lang.t("share.exhausted")),
+        ShareError::PasswordRequired => password_page(expires, signature, false, lang),
+        ShareError::WrongPassword => password_page(expires, signature, true, lang),
+    }
+}
+
+/// 需要密码时显示的输入页面
+fn password_page(expires: &str, signature: &str, wrong: bool, lang: Lang) -> Response<Body> {
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="{}">
+<head><meta charset="utf-8"><meta name="viewport" content="width=device-width, initial-scale=1.0"><title>🔒 {}</title></head>
+<body style="font-family: sans-serif; max-width: 420px; margin: 80px auto;">
+    <h3>🔒 {}</h3>
+    {}
+    <form method="post" action="?e={}&amp;s={}">
+        <input type="password" name="p" autofocus placeholder="{}">
+        <button type="submit">{}</button>
+    </form>
+</body>
+</html>"#,
+        lang.code(),
+        lang.t("share.password_title"),
+        lang.t("share.password_heading"),
+        if wrong {
+            format!("<p style=\"color:#c00\">{}</p>", lang.t("share.wrong_password"))
+        } else {
+            String::new()
+        },
+        html_escape(expires),
+        html_escape(signature),
+        lang.t("share.password_placeholder"),
+        lang.t("share.download"),
+    );
+    Response::builder()
+        .status(StatusCode::UNAUTHORIZED)
+        .header("Content-Type", "text/html; charset=utf-8")
+        .body(Body::from(html))
+        .unwrap()
+}
+
+/// UI 创建分享链接的请求体
+#[derive(Deserialize)]
+struct CreateRequest {
+    path: String,
+    expires: Option<String>,
+    max_downloads: Option<u32>,
+    password: Option<String>,
+}
+
+/// `POST /_shares/create`，返回 JSON `{ "url": ..., "id": ..., "expires": ... }`
+async fn create_from_request(req: Request<Body>, state: &AppState, lang: Lang) -> Response<Body> {
+    let store = &state.share_store;
+    let host = req
+        .headers()
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("127.0.0.1")
+        .to_string();
+    let body = match read_body(req, MAX_CREATE_BODY).await {
+        Some(body) => body,
+        None => return text_response(StatusCode::PAYLOAD_TOO_LARGE, lang.t("http.body_too_large")),
+    };
+    let create: CreateRequest = match serde_json::from_slice(&body) {
+        Ok(c) => c,
+        Err(e) => return text_response(StatusCode::BAD_REQUEST, &format!("400 - {}", e)),
+    };
+    let ttl = match parse_duration(create.expires.as_deref().unwrap_or("24h")) {
+        Ok(ttl) => ttl,
+        Err(e) => return text_response(StatusCode::BAD_REQUEST, &format!("400 - {}", e)),
+    };
+    let decoded = percent_decode_str(&create.path).decode_utf8_lossy().to_string();
+    let Some(relative) = normalize_relative(&decoded) else {
+        return text_response(StatusCode::BAD_REQUEST, lang.t("http.bad_path"));
+    };
+    if !state.root_dir.join(&relative).is_file() || state.access.check(&relative, false).denied {
+        return text_response(StatusCode::NOT_FOUND, lang.t("http.not_found"));
+    }
+    let password = create.password.filter(|p| !p.is_empty());
+
+    let result = store
+        .create(&relative, ttl, create.max_downloads, password.as_deref())
+        .and_then(|link| Ok((store.signed_path(&link)?, link)));
+    match result {
+        Ok((signed, link)) => {
+            let json = serde_json::json!({
+                "id": link.id,
+                "url": format!("{}://{}{}", state.scheme, host, signed),
+                "expires": link.expires,
+            });
+            Response::builder()
+                .header("Content-Type", "application/json")
+                .body(Body::from(json.to_string()))
+                .unwrap()
+        }
+        Err(e) if e.kind() == io::ErrorKind::InvalidInput => {
+            text_response(StatusCode::BAD_REQUEST, lang.t("share.bad_expires"))
+        }
+        Err(e) => text_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("500 - {}", e)),
+    }
+}
+
+/// `POST /_shares/revoke`，表单字段 `id`，完成后跳回列表页
+async fn revoke_from_request(req: Request<Body>, store: &ShareStore, lang: Lang) -> Response<Body> {
+    let Some(body) = read_body(req, MAX_CREATE_BODY).await else {
+        return text_response(StatusCode::PAYLOAD_TOO_LARGE, lang.t("http.body_too_large"));
+    };
+    let id = form_urlencoded::parse(&body)
+        .find(|(k, _)| k == "id")
+        .map(|(_, v)| v.into_owned())
+        .unwrap_or_default();
+    match store.revoke(&id) {
+        Ok(true) => Response::builder()
+            .status(StatusCode::SEE_OTHER)
+            .header(header::LOCATION, SHARES_PAGE)
+            .body(Body::empty())
+            .unwrap(),
+        Ok(false) => text_response(StatusCode::NOT_FOUND, lang.t("share.revoke_not_found")),
+        Err(e) => text_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("500 - {}", e)),
+    }
+}
+
+/// 分享链接管理页面，`scheme` 为生成链接使用的协议
+fn list_page(req: &Request<Body>, store: &ShareStore, scheme: &str, lang: Lang) -> Response<Body> {
+    let shares = match store.list() {
+        Ok(shares) => shares,
+        Err(e) => return text_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("500 - {}", e)),
+    };
+    let host = req
+        .headers()
+        .get(header::HOST)
+        .and_then(|h| h.to_str().ok())
+        .unwrap_or("127.0.0.1");
+    let now = Utc::now().timestamp();
+
+    let mut rows = String::new();
+    for link in &shares {
+        let status = if link.is_expired(now) {
+            format!("⌛ {}", lang.t("share.status_expired"))
+        } else if link.is_exhausted() {
+            format!("🚫 {}", lang.t("share.status_exhausted"))
+        } else {
+            format!("✅ {}", lang.t("share.status_active"))
+        };
+        let url = store
+            .signed_path(link)
+            .map(|p| format!("{}://{}{}", scheme, host, p))
+            .unwrap_or_default();
+        rows += &format!(
+            r#"<tr><td><a href="/{}">{}</a></td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td>
+<td><input readonly value="{}" onclick="this.select()"></td>
+<td><form method="post" action="/_shares/revoke"><input type="hidden" name="id" value="{}"><button>{}</button></form></td></tr>"#,
+            html_escape(&link.path),
+            html_escape(&link.path),
+            format_time(link.created),
+            format_time(link.expires),
+            match link.max_downloads {
+                Some(max) => format!("{} / {}", link.downloads, max),
+                None => format!("{} / ∞", link.downloads),
+            },
+            if link.password_hash.is_some() { "🔒" } else { "" },
+            status,
+            html_escape(&url),
+            html_escape(&link.id),
+            lang.t("share.revoke"),
+        );
+    }
+    if shares.is_empty() {
+        rows = format!(
+            r#"<tr><td colspan="8" style="text-align:center; color:#666">{}</td></tr>"#,
+            lang.t("share.none")
+        );
+    }
+
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+    <meta charset="utf-8">
+    <title>🔗 {title}</title>
+    <style>
+        body {{ font-family: -apple-system, 'Segoe UI', Roboto, sans-serif; margin: 40px; }}
+        table {{ border-collapse: collapse; width: 100%; }}
+        th, td {{ border-bottom: 1px solid #e9ecef; padding: 8px; text-align: left; }}
+        input[readonly] {{ width: 260px; }}
+    </style>
+</head>
+<body>
+    <h2>🔗 {title}</h2>
+    <p><a href="/">🏠 {home}</a></p>
+    <table>
+        <tr>{header}<th></th></tr>
+        {rows}
+    </table>
+</body>
+</html>"#,
+        lang = lang.code(),
+        title = lang.t("share.page_title"),
+        home = lang.t("listing.home"),
+        header = [
+            "share.col_file",
+            "share.col_created",
+            "share.col_expires",
+            "share.col_downloads",
+            "share.col_password",
+            "share.col_status",
+            "share.col_link",
+        ]
+        .iter()
+        .map(|key| format!("<th>{}</th>", lang.t(key)))
+        .collect::<String>(),
+        rows = rows
+    );
+    Response::builder()
+        .header("Content-Type", "text/html; charset=utf-8")
+        .header(header::CACHE_CONTROL, "no-store")
+        .body(Body::from(html))
+        .unwrap()
+}
+
+/// `share` 子命令：在命令行中创建、列出或撤销分享链接
+pub fn run_share_command(
+    cmd: &crate::ShareArgs,
+    root_dir: &Path,
+    data_dir: &Path,
+    port: u16,
+    lang: Lang,
+) {
+    let store = match ShareStore::open(data_dir) {
+        Ok(store) => store,
+        Err(e) => {
+            eprintln!(
+                "❌ \x1B[91m{}\x1B[0m",
+                tr!(lang, "startup.share_store_failed", path = data_dir.display(), error = e)
+            );
+            std::process::exit(1);
+        }
+    };
+    let base_url = cmd
+        .base_url
+        .clone()
+        .unwrap_or_else(|| format!("http://127.0.0.1:{}", port));
+    let base_url = base_url.trim_end_matches('/');
+
+    if let Some(id) = &cmd.revoke {
+        match store.revoke(id) {
+            Ok(true) => println!("🗑️ {}", tr!(lang, "share.cli_revoked", id = id)),
+            Ok(false) => {
+                eprintln!("❌ \x1B[91m{}\x1B[0m", tr!(lang, "share.cli_no_such", id = id));
+                std::process::exit(1);
+            }
+            Err(e) => {
+                eprintln!("❌ \x1B[91m{}\x1B[0m", e);
+                std::process::exit(1);
+            }
+        }
+        return;
+    }
+
+    if cmd.list {
+        let shares = store.list().unwrap_or_default();
+        if shares.is_empty() {
+            println!("{}", lang.t("share.none"));
+        }
+        let now = Utc::now().timestamp();
+        for link in shares {
+            let state = if link.is_expired(now) {
+                lang.t("share.status_expired")
+            } else if link.is_exhausted() {
+                lang.t("share.status_exhausted")
+            } else {
+                lang.t("share.status_active")
+            };
+            println!(
+                "{}  {}  {}  {}/{}  [{}]",
+                link.id,
+                link.path,
+                tr!(lang, "share.cli_expires", time = format_time(link.expires)),
+                link.downloads,
+                link.max_downloads.map(|m| m.to_string()).unwrap_or_else(|| "∞".into()),
+                state
+            );
+        }
+        return;
+    }
+
+    let Some(path) = &cmd.path else {
+        return;
+    };
+    let full = PathBuf::from(path);
+    let full = if full.is_absolute() { full } else { std::env::current_dir().unwrap().join(full) };
+    let relative = full
+        .canonicalize()
+        .ok()
+        .and_then(|p| {
+            let root = root_dir.canonicalize().ok()?;
+            p.strip_prefix(&root).ok().map(|r| r.to_path_buf())
+        })
+        .and_then(|r| normalize_relative(&r.to_string_lossy().replace('\\', "/")));
+    let Some(relative) = relative.filter(|r| root_dir.join(r).is_file()) else {
+        eprintln!("❌ \x1B[91m{}\x1B[0m", tr!(lang, "share.cli_not_in_root", path = path));
+        std::process::exit(1);
+    };
+    let ttl = match parse_duration(&cmd.expires) {
+        Ok(ttl) => ttl,
+        Err(e) => {
+            eprintln!("❌ \x1B[91m{}\x1B[0m", e);
+            std::process::exit(1);
+        }
+    };
+
+    match store
+        .create(&relative, ttl, cmd.max_downloads, cmd.password.as_deref())
+        .and_then(|link| Ok((store.signed_path(&link)?, link)))
+    {
+        Ok((signed, link)) => {
+            println!("🔗 {}{}", base_url, signed);
+            println!("⌛ {}", tr!(lang, "share.cli_expires_at", time = format_time(link.expires)));
+            if let Some(max) = link.max_downloads {
+                println!("📥 {}", tr!(lang, "share.cli_max_downloads", max = max));
+            }
+            if link.password_hash.is_some() {
+                println!("🔒 {}", lang.t("share.password_title"));
+            }
+        }
+        Err(e) => {
+            eprintln!("❌ \x1B[91m{}\x1B[0m", e);
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TestDir;
+
+    /// 从 `signed_path` 中取出 `e` 和 `s` 参数
+    fn signature(store: &ShareStore, link: &ShareLink) -> (String, String) {
+        let path = store.signed_path(link).unwrap();
+        let query = path.split_once('?').map(|(_, q)| q);
+        (query_param(query, "e").unwrap(), query_param(query, "s").unwrap())
+    }
+
+    #[test]
+    fn signatures_are_verified() {
+        let dir = TestDir::new("share_signing");
+        let store = ShareStore::open(&dir).unwrap();
+        let link = store.create("docs/a.txt", Duration::from_secs(3600), None, None).unwrap();
+        let (e, s) = signature(&store, &link);
+        assert!(store.authorize(&link.id, &e, &s, None).is_ok());
+
+        let last = if s.ends_with('0') { '1' } else { '0' };
+        let tampered = format!("{}{}", &s[..s.len() - 1], last);
+        let later = (link.expires + 60).to_string();
+        assert!(matches!(store.authorize(&link.id, &e, &tampered, None), Err(ShareError::BadSignature)));
+        assert!(matches!(store.authorize(&link.id, &e, "not hex", None), Err(ShareError::BadSignature)));
+        assert!(matches!(store.authorize(&link.id, &later, &s, None), Err(ShareError::BadSignature)));
+        assert!(matches!(store.authorize("missing", &e, &s, None), Err(ShareError::NotFound)));
+
+        // 签名与链接绑定，不能挪用到另一条链接上
+        let other = store.create("docs/b.txt", Duration::from_secs(3600), None, None).unwrap();
+        assert!(matches!(store.authorize(&other.id, &e, &s, None), Err(ShareError::BadSignature)));
+
+        // 重新打开后密钥不变，已发出的链接仍然有效
+        let reopened = ShareStore::open(&dir).unwrap();
+        assert!(reopened.authorize(&link.id, &e, &s, None).is_ok());
+    }
+
+    #[test]
+    fn only_redeemed_downloads_are_counted() {
+        let dir = TestDir::new("share_redeem");
+        let store = ShareStore::open(&dir).unwrap();
+        let link = store.create("a.txt", Duration::from_secs(3600), Some(2), None).unwrap();
+        let (e, s) = signature(&store, &link);
+
+        // 只校验不计数，例如下载名额已满或文件打开失败时
+        for _ in 0..3 {
+            assert!(store.authorize(&link.id, &e, &s, None).is_ok());
+        }
+        assert_eq!(store.redeem(&link.id).unwrap().downloads, 1);
+        assert_eq!(store.redeem(&link.id).unwrap().downloads, 2);
+        assert!(matches!(store.redeem(&link.id), Err(ShareError::Exhausted)));
+        assert!(matches!(store.authorize(&link.id, &e, &s, None), Err(ShareError::Exhausted)));
+        assert_eq!(store.list().unwrap()[0].downloads, 2);
+
+        assert!(store.revoke(&link.id).unwrap());
+        assert!(matches!(store.redeem(&link.id), Err(ShareError::NotFound)));
+    }
+
+    #[test]
+    fn passwords_and_expiry_are_checked() {
+        let dir = TestDir::new("share_password");
+        let store = ShareStore::open(&dir).unwrap();
+        let link = store.create("a.txt", Duration::from_secs(3600), None, Some("pw")).unwrap();
+        let (e, s) = signature(&store, &link);
+        assert!(matches!(store.authorize(&link.id, &e, &s, None), Err(ShareError::PasswordRequired)));
+        assert!(matches!(store.authorize(&link.id, &e, &s, Some("PW")), Err(ShareError::WrongPassword)));
+        assert!(store.authorize(&link.id, &e, &s, Some("pw")).is_ok());
+
+        // 相同密码每次加不同的盐，旧版本不带盐的摘要仍可校验
+        assert_ne!(hash_password("pw"), hash_password("pw"));
+        let legacy = hex::encode(Sha256::digest(b"id:pw"));
+        assert!(password_matches(&legacy, "id", "pw"));
+        assert!(!password_matches(&legacy, "id", "PW"));
+
+        let expired = store.create("a.txt", Duration::ZERO, None, None).unwrap();
+        let (e, s) = signature(&store, &expired);
+        assert!(matches!(store.authorize(&expired.id, &e, &s, None), Err(ShareError::Expired)));
+        assert!(matches!(store.redeem(&expired.id), Err(ShareError::Expired)));
+
+        let too_far = store.create("a.txt", Duration::from_secs(u64::MAX), None, None);
+        assert_eq!(too_far.unwrap_err().kind(), io::ErrorKind::InvalidInput);
+    }
+}
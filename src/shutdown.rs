@@ -0,0 +1,56 @@
+use std::{future::Future, sync::Arc, time::Duration};
+use tokio::sync::Notify;
+
+use crate::i18n::Lang;
+use crate::tr;
+
+/// 等待 Ctrl-C（SIGINT）或 SIGTERM
+pub async fn signal() {
+    let ctrl_c = async {
+        let _ = tokio::signal::ctrl_c().await;
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate()) {
+            Ok(mut sigterm) => {
+                sigterm.recv().await;
+            }
+            Err(_) => std::future::pending::<()>().await,
+        }
+    };
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// 运行设置了优雅退出的服务
+///
+/// `draining` 被通知（即开始优雅退出）后，服务停止接受新连接并等待进行中的请求完成；
+/// 超过 `timeout` 仍未完成时放弃等待，直接返回。
+pub async fn drain<F>(server: F, draining: Arc<Notify>, timeout: Duration, lang: Lang) -> hyper::Result<()>
+where
+    F: Future<Output = hyper::Result<()>>,
+{
+    tokio::pin!(server);
+    tokio::select! {
+        result = &mut server => return result,
+        _ = draining.notified() => {}
+    }
+    match tokio::time::timeout(timeout, server).await {
+        Ok(result) => result,
+        Err(_) => {
+            println!("⌛ {}", lang.t("shutdown.timeout"));
+            Ok(())
+        }
+    }
+}
+
+/// 退出提示
+pub fn announce(timeout: Duration, lang: Lang) {
+    println!("\n🛑 {}", tr!(lang, "shutdown.announce", seconds = timeout.as_secs()));
+}
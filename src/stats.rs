@@ -0,0 +1,299 @@
+use futures_util::StreamExt;
+use hyper::body::{Bytes, HttpBody, SizeHint};
+use hyper::{Body, HeaderMap, Method, StatusCode};
+use std::{
+    collections::{BTreeMap, HashMap},
+    fmt::Write,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    task::{Context, Poll},
+    time::{Duration, Instant},
+};
+
+use crate::i18n::Lang;
+use crate::tr;
+use crate::util::format_size;
+
+/// 请求耗时直方图各桶的上限（秒）
+const LATENCY_BUCKETS: [f64; 11] = [0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+/// 最多记录的不同路径数，超过时只保留请求数较多的一半
+const MAX_TRACKED_PATHS: usize = 10_000;
+
+/// 服务运行期间的统计数据，退出时打印汇总，并通过 `/_metrics` 导出
+pub struct Stats {
+    started: Instant,
+    requests: AtomicU64,
+    bytes_sent: AtomicU64,
+    /// 请求体的字节数
+    bytes_received: AtomicU64,
+    /// 当前打开的连接数
+    connections: AtomicU64,
+    /// 按 (方法, 状态码) 统计的请求数
+    responses: Mutex<BTreeMap<(&'static str, u16), u64>>,
+    latency: Mutex<Histogram>,
+    /// 各路径的请求数
+    paths: Mutex<HashMap<String, u64>>,
+}
+
+/// 请求耗时（到响应头就绪为止）的直方图
+#[derive(Default)]
+struct Histogram {
+    /// 落在各桶中的请求数（不累计），最后一个为超过最大上限的请求
+    counts: [u64; LATENCY_BUCKETS.len() + 1],
+    sum: f64,
+    count: u64,
+}
+
+/// 连接打开期间持有，释放时减少当前连接数
+pub struct ConnectionGuard(Arc<Stats>);
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.0.connections.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Stats {
+    pub fn new() -> Self {
+        Stats {
+            started: Instant::now(),
+            requests: AtomicU64::new(0),
+            bytes_sent: AtomicU64::new(0),
+            bytes_received: AtomicU64::new(0),
+            connections: AtomicU64::new(0),
+            responses: Mutex::new(BTreeMap::new()),
+            latency: Mutex::new(Histogram::default()),
+            paths: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 记录一次请求
+    pub fn record_request(&self) {
+        self.requests.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录请求的结果与耗时
+    pub fn record_response(&self, method: &Method, status: StatusCode, path: &str, elapsed: Duration) {
+        *self
+            .responses
+            .lock()
+            .unwrap()
+            .entry((method_label(method), status.as_u16()))
+            .or_insert(0) += 1;
+
+        let seconds = elapsed.as_secs_f64();
+        let mut latency = self.latency.lock().unwrap();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|le| seconds <= *le)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        latency.counts[bucket] += 1;
+        latency.sum += seconds;
+        latency.count += 1;
+        drop(latency);
+
+        let mut paths = self.paths.lock().unwrap();
+        if let Some(count) = paths.get_mut(path) {
+            *count += 1;
+            return;
+        }
+        if paths.len() >= MAX_TRACKED_PATHS {
+            let mut counts: Vec<u64> = paths.values().copied().collect();
+            counts.sort_unstable_by(|a, b| b.cmp(a));
+            let threshold = counts[MAX_TRACKED_PATHS / 2];
+            paths.retain(|_, count| *count > threshold);
+        }
+        paths.insert(path.to_string(), 1);
+    }
+
+    /// 登记一个新连接
+    pub fn connection(self: &Arc<Self>) -> ConnectionGuard {
+        self.connections.fetch_add(1, Ordering::Relaxed);
+        ConnectionGuard(self.clone())
+    }
+
+    /// 包装请求体，统计收到的字节数
+    pub fn count_received(self: &Arc<Self>, body: Body) -> Body {
+        let stats = self.clone();
+        Body::wrap_stream(body.inspect(move |chunk| {
+            if let Ok(chunk) = chunk {
+                stats
+                    .bytes_received
+                    .fetch_add(chunk.len() as u64, Ordering::Relaxed);
+            }
+        }))
+    }
+
+    pub fn uptime(&self) -> Duration {
+        self.started.elapsed()
+    }
+
+    /// Prometheus 文本格式的指标，路径只列出请求数最多的 `top_paths` 个
+    pub fn prometheus(&self, top_paths: usize) -> String {
+        let mut out = String::new();
+        metric_header(&mut out, "uptime_seconds", "gauge", "Seconds since the server started.");
+        let _ = writeln!(out, "file_server_uptime_seconds {}", self.uptime().as_secs());
+
+        metric_header(&mut out, "requests_total", "counter", "Requests by method and status code.");
+        for ((method, status), count) in self.responses.lock().unwrap().iter() {
+            let _ = writeln!(
+                out,
+                "file_server_requests_total{{method=\"{}\",status=\"{}\"}} {}",
+                method, status, count
+            );
+        }
+
+        metric_header(&mut out, "sent_bytes_total", "counter", "Response body bytes sent.");
+        let _ = writeln!(out, "file_server_sent_bytes_total {}", self.bytes_sent.load(Ordering::Relaxed));
+        metric_header(&mut out, "received_bytes_total", "counter", "Request body bytes received.");
+        let _ = writeln!(
+            out,
+            "file_server_received_bytes_total {}",
+            self.bytes_received.load(Ordering::Relaxed)
+        );
+        metric_header(&mut out, "active_connections", "gauge", "Currently open client connections.");
+        let _ = writeln!(
+            out,
+            "file_server_active_connections {}",
+            self.connections.load(Ordering::Relaxed)
+        );
+
+        metric_header(
+            &mut out,
+            "request_duration_seconds",
+            "histogram",
+            "Time until the response headers were ready.",
+        );
+        let latency = self.latency.lock().unwrap();
+        let mut cumulative = 0;
+        for (le, count) in LATENCY_BUCKETS.iter().zip(latency.counts.iter()) {
+            cumulative += count;
+            let _ = writeln!(
+                out,
+                "file_server_request_duration_seconds_bucket{{le=\"{}\"}} {}",
+                le, cumulative
+            );
+        }
+        let _ = writeln!(
+            out,
+            "file_server_request_duration_seconds_bucket{{le=\"+Inf\"}} {}",
+            latency.count
+        );
+        let _ = writeln!(out, "file_server_request_duration_seconds_sum {}", latency.sum);
+        let _ = writeln!(out, "file_server_request_duration_seconds_count {}", latency.count);
+        drop(latency);
+
+        metric_header(
+            &mut out,
+            "path_requests_total",
+            "counter",
+            "Requests for the most requested paths.",
+        );
+        let mut paths: Vec<(String, u64)> = self
+            .paths
+            .lock()
+            .unwrap()
+            .iter()
+            .map(|(path, count)| (path.clone(), *count))
+            .collect();
+        paths.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        for (path, count) in paths.into_iter().take(top_paths) {
+            let _ = writeln!(
+                out,
+                "file_server_path_requests_total{{path=\"{}\"}} {}",
+                escape_label(&path),
+                count
+            );
+        }
+        out
+    }
+
+    /// 退出时打印的汇总信息
+    pub fn summary(&self, lang: Lang) -> String {
+        let requests = self.requests.load(Ordering::Relaxed);
+        let sent = format_size(self.bytes_sent.load(Ordering::Relaxed));
+        let uptime = self.started.elapsed().as_secs();
+        let uptime = format!("{:02}:{:02}:{:02}", uptime / 3600, uptime / 60 % 60, uptime % 60);
+        format!(
+            "📊 {}",
+            tr!(lang, "shutdown.summary", requests = requests, sent = sent, uptime = uptime)
+        )
+    }
+}
+
+/// 指标中的请求方法，非标准方法归为 OTHER，避免标签数量无限增长
+fn method_label(method: &Method) -> &'static str {
+    match *method {
+        Method::GET => "GET",
+        Method::HEAD => "HEAD",
+        Method::POST => "POST",
+        Method::PUT => "PUT",
+        Method::DELETE => "DELETE",
+        Method::OPTIONS => "OPTIONS",
+        Method::PATCH => "PATCH",
+        _ => "OTHER",
+    }
+}
+
+/// 指标的 HELP 与 TYPE 行
+fn metric_header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP file_server_{} {}", name, help);
+    let _ = writeln!(out, "# TYPE file_server_{} {}", name, kind);
+}
+
+/// 转义 Prometheus 标签值中的反斜杠、引号和换行
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// 统计发送字节数的响应体，保留原响应体的长度信息（Content-Length 不受影响）
+pub struct CountedBody {
+    inner: Body,
+    stats: Arc<Stats>,
+}
+
+impl CountedBody {
+    pub fn new(inner: Body, stats: Arc<Stats>) -> Self {
+        CountedBody { inner, stats }
+    }
+}
+
+impl HttpBody for CountedBody {
+    type Data = Bytes;
+    type Error = hyper::Error;
+
+    fn poll_data(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Self::Data, Self::Error>>> {
+        let poll = Pin::new(&mut self.inner).poll_data(cx);
+        if let Poll::Ready(Some(Ok(chunk))) = &poll {
+            self.stats
+                .bytes_sent
+                .fetch_add(chunk.len() as u64, Ordering::Relaxed);
+        }
+        poll
+    }
+
+    fn poll_trailers(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Result<Option<HeaderMap>, Self::Error>> {
+        Pin::new(&mut self.inner).poll_trailers(cx)
+    }
+
+    fn is_end_stream(&self) -> bool {
+        self.inner.is_end_stream()
+    }
+
+    fn size_hint(&self) -> SizeHint {
+        self.inner.size_hint()
+    }
+}
@@ -0,0 +1,65 @@
+use std::{fs::File, io::BufReader, net::IpAddr, path::Path, sync::Arc};
+use tokio_rustls::rustls::{
+    self,
+    crypto::ring,
+    pki_types::{CertificateDer, PrivateKeyDer, PrivatePkcs8KeyDer},
+    ServerConfig,
+};
+
+/// TCP 上通过 ALPN 协商的协议，优先 HTTP/2
+pub const TCP_ALPN: &[&[u8]] = &[b"h2", b"http/1.1"];
+
+/// QUIC 上的 HTTP/3
+pub const QUIC_ALPN: &[&[u8]] = &[b"h3"];
+
+/// 服务端证书链与私钥
+pub struct TlsIdentity {
+    certs: Vec<CertificateDer<'static>>,
+    key: PrivateKeyDer<'static>,
+}
+
+impl TlsIdentity {
+    /// 从 PEM 文件读取证书链与私钥（PKCS#8、PKCS#1 或 SEC1）
+    pub fn load(cert: &Path, key: &Path) -> Result<Self, String> {
+        let open = |path: &Path| {
+            File::open(path)
+                .map(BufReader::new)
+                .map_err(|e| format!("{}: {}", path.display(), e))
+        };
+        let certs = rustls_pemfile::certs(&mut open(cert)?)
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| format!("{}: {}", cert.display(), e))?;
+        if certs.is_empty() {
+            return Err(format!("{}: no certificate found", cert.display()));
+        }
+        let key = rustls_pemfile::private_key(&mut open(key)?)
+            .map_err(|e| format!("{}: {}", key.display(), e))?
+            .ok_or_else(|| format!("{}: no private key found", key.display()))?;
+        Ok(TlsIdentity { certs, key })
+    }
+
+    /// 生成自签名证书，包含 localhost 和给定的 IP 地址，仅在内存中使用
+    pub fn self_signed(ips: &[IpAddr]) -> Result<Self, String> {
+        let mut names = vec!["localhost".to_string()];
+        names.extend(ips.iter().map(|ip| ip.to_string()));
+        let generated = rcgen::generate_simple_self_signed(names).map_err(|e| e.to_string())?;
+        Ok(TlsIdentity {
+            certs: vec![generated.cert.der().clone()],
+            key: PrivatePkcs8KeyDer::from(generated.key_pair.serialize_der()).into(),
+        })
+    }
+
+    /// 按 ALPN 协议列表创建 rustls 服务端配置
+    pub fn server_config(&self, alpn: &[&[u8]]) -> Result<ServerConfig, String> {
+        let mut config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_protocol_versions(rustls::ALL_VERSIONS)
+            .and_then(|builder| {
+                builder
+                    .with_no_client_auth()
+                    .with_single_cert(self.certs.clone(), self.key.clone_key())
+            })
+            .map_err(|e| e.to_string())?;
+        config.alpn_protocols = alpn.iter().map(|p| p.to_vec()).collect();
+        Ok(config)
+    }
+}
@@ -0,0 +1,612 @@
+use chrono::Utc;
+use hyper::{header, Body, Method, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use std::{
+    fs, io,
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::{atomic::AtomicU64, Arc, Mutex},
+    time::Duration,
+};
+
+use crate::auth;
+use crate::i18n::Lang;
+use crate::upload::unique_path;
+use crate::util::{
+    format_size, format_time, html_escape, move_tree, read_body, remove_tree, text_response,
+};
+use crate::{log_request, tr, AppState};
+
+/// 回收站页面路径
+pub const TRASH_PAGE: &str = "/_trash";
+
+/// 数据目录下的回收站目录
+const TRASH_DIR: &str = "trash";
+/// 回收站索引文件名
+const INDEX_FILE: &str = "index.json";
+/// 回收站中的文件与目录存放在 `items/<id>/<原名称>`
+const ITEMS_DIR: &str = "items";
+/// 恢复、清除请求体的大小上限
+const MAX_ACTION_BODY: usize = 1024;
+/// 自动清除的检查间隔
+const PURGE_INTERVAL: Duration = Duration::from_secs(600);
+
+/// 回收站中的一项
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct TrashItem {
+    pub id: String,
+    /// 删除前相对根目录的路径，使用 `/` 分隔
+    pub path: String,
+    /// 删除时间（Unix 秒）
+    pub deleted: i64,
+    /// 文件大小，目录为其中所有文件的总大小
+    pub size: u64,
+    pub is_dir: bool,
+}
+
+impl TrashItem {
+    fn name(&self) -> &str {
+        self.path.rsplit('/').next().unwrap_or(&self.path)
+    }
+}
+
+/// 服务管理的回收站，位于数据目录下（不在根目录中提供）
+///
+/// 通过文件管理接口删除或覆盖的文件移动到这里，并记录原路径和删除时间，
+/// 可以恢复到原位置或永久清除。超过保留时间的项和超出总大小上限时最早删除的项会被自动清除。
+pub struct Trash {
+    dir: PathBuf,
+    max_age: Option<Duration>,
+    max_size: Option<u64>,
+    lock: Mutex<()>,
+}
+
+impl Trash {
+    /// 打开（必要时创建）数据目录下的回收站，清理索引中不存在的残留项
+    pub fn open(
+        data_dir: &Path,
+        max_age: Option<Duration>,
+        max_size: Option<u64>,
+    ) -> io::Result<Self> {
+        let trash = Trash {
+            dir: data_dir.join(TRASH_DIR),
+            max_age,
+            max_size,
+            lock: Mutex::new(()),
+        };
+        fs::create_dir_all(trash.dir.join(ITEMS_DIR))?;
+        let _guard = trash.lock.lock().unwrap();
+        let items = trash.load()?;
+        for entry in fs::read_dir(trash.dir.join(ITEMS_DIR))? {
+            let entry = entry?;
+            let name = entry.file_name();
+            if !items
+                .iter()
+                .any(|item| name.to_str() == Some(item.id.as_str()))
+            {
+                remove_tree(&entry.path(), &AtomicU64::new(0))?;
+            }
+        }
+        drop(_guard);
+        Ok(trash)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn load(&self) -> io::Result<Vec<TrashItem>> {
+        match fs::read(self.dir.join(INDEX_FILE)) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 先写临时文件再重命名，避免写入中途崩溃损坏索引
+    fn save(&self, items: &[TrashItem]) -> io::Result<()> {
+        let path = self.dir.join(INDEX_FILE);
+        let tmp = path.with_extension("json.tmp");
+        let json = serde_json::to_vec_pretty(items)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, &path)
+    }
+
+    fn item_path(&self, item: &TrashItem) -> PathBuf {
+        self.dir.join(ITEMS_DIR).join(&item.id).join(item.name())
+    }
+
+    /// 把根目录下的 `relative` 移入回收站
+    pub fn put(&self, root: &Path, relative: &str) -> io::Result<TrashItem> {
+        let source = root.join(relative);
+        let metadata = fs::symlink_metadata(&source)?;
+        let size = walkdir::WalkDir::new(&source)
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter_map(|entry| entry.metadata().ok())
+            .filter(|metadata| metadata.is_file())
+            .map(|metadata| metadata.len())
+            .sum();
+        let item = TrashItem {
+            id: crate::util::random_token(8),
+            path: relative.to_string(),
+            deleted: Utc::now().timestamp(),
+            size,
+            is_dir: metadata.is_dir(),
+        };
+        let target = self.item_path(&item);
+        fs::create_dir_all(target.parent().unwrap())?;
+        move_tree(&source, &target)?;
+
+        let _guard = self.lock.lock().unwrap();
+        let result = self.load().and_then(|mut items| {
+            items.push(item.clone());
+            self.save(&items)
+        });
+        // 索引写入失败时移回原处，避免文件既不在原位置也无法恢复
+        if let Err(e) = result {
+            let _ = move_tree(&target, &source);
+            let _ = fs::remove_dir(target.parent().unwrap());
+            return Err(e);
+        }
+        Ok(item)
+    }
+
+    /// 回收站中的所有项，最近删除的在前
+    pub fn list(&self) -> io::Result<Vec<TrashItem>> {
+        let _guard = self.lock.lock().unwrap();
+        let mut items = self.load()?;
+        items.sort_by_key(|item| std::cmp::Reverse(item.deleted));
+        Ok(items)
+    }
+
+    /// 取出一项，索引中不存在时返回 None
+    fn take(&self, id: &str) -> io::Result<Option<TrashItem>> {
+        let _guard = self.lock.lock().unwrap();
+        let mut items = self.load()?;
+        let Some(index) = items.iter().position(|item| item.id == id) else {
+            return Ok(None);
+        };
+        let item = items.remove(index);
+        self.save(&items)?;
+        Ok(Some(item))
+    }
+
+    /// 恢复到原路径，缺少的上级目录会重新创建，原路径已被占用时自动改名；返回恢复后的相对路径
+    pub fn restore(&self, id: &str, root: &Path) -> io::Result<Option<(TrashItem, String)>> {
+        let Some(item) = self.take(id)? else {
+            return Ok(None);
+        };
+        let (parent, name) = item.path.rsplit_once('/').unwrap_or(("", &item.path));
+        let dir = root.join(parent);
+        let target = unique_path(&dir, name);
+        let result =
+            fs::create_dir_all(&dir).and_then(|_| move_tree(&self.item_path(&item), &target));
+        if let Err(e) = result {
+            // 恢复失败时放回索引
+            let _guard = self.lock.lock().unwrap();
+            let mut items = self.load()?;
+            items.push(item);
+            self.save(&items)?;
+            return Err(e);
+        }
+        let _ = fs::remove_dir(self.dir.join(ITEMS_DIR).join(&item.id));
+        let restored = target.file_name().unwrap().to_string_lossy();
+        let relative = if parent.is_empty() {
+            restored.to_string()
+        } else {
+            format!("{}/{}", parent, restored)
+        };
+        Ok(Some((item, relative)))
+    }
+
+    /// 永久清除一项
+    pub fn purge(&self, id: &str) -> io::Result<Option<TrashItem>> {
+        let Some(item) = self.take(id)? else {
+            return Ok(None);
+        };
+        remove_tree(&self.dir.join(ITEMS_DIR).join(&item.id), &AtomicU64::new(0))?;
+        Ok(Some(item))
+    }
+
+    /// 清除超过保留时间的项，再从最早删除的开始清除直到总大小不超过上限，返回被清除的项
+    pub fn purge_expired(&self) -> io::Result<Vec<TrashItem>> {
+        let expired: Vec<String> = {
+            let _guard = self.lock.lock().unwrap();
+            let mut items = self.load()?;
+            items.sort_by_key(|item| item.deleted);
+            let now = Utc::now().timestamp();
+            let mut total: u64 = items.iter().map(|item| item.size).sum();
+            items
+                .iter()
+                .filter(|item| {
+                    // 保留时间极长时按永久保留处理，不能溢出成负数
+                    let too_old = self.max_age.is_some_and(|age| {
+                        let age = i64::try_from(age.as_secs()).unwrap_or(i64::MAX);
+                        now.saturating_sub(item.deleted) >= age
+                    });
+                    let too_big = self.max_size.is_some_and(|max| total > max);
+                    if too_old || too_big {
+                        total -= item.size;
+                    }
+                    too_old || too_big
+                })
+                .map(|item| item.id.clone())
+                .collect()
+        };
+        let mut purged = Vec::new();
+        for id in expired {
+            purged.extend(self.purge(&id)?);
+        }
+        Ok(purged)
+    }
+}
+
+/// 定期自动清除回收站，清除的项记入访问日志
+pub async fn purge_periodically(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let task = {
+            let state = state.clone();
+            tokio::task::spawn_blocking(move || match &state.trash {
+                Some(trash) => trash.purge_expired(),
+                None => Ok(Vec::new()),
+            })
+        };
+        match task.await {
+            Ok(Ok(purged)) => {
+                for item in purged {
+                    log_request(
+                        &state.log_mode,
+                        state.logger.clone(),
+                        format!("FS purge /{} ({}) [auto]", item.path, item.id),
+                    );
+                }
+            }
+            Ok(Err(e)) => eprintln!(
+                "⚠️ \x1B[93m{}\x1B[0m",
+                tr!(state.lang, "trash.purge_failed", error = e)
+            ),
+            Err(_) => {}
+        }
+    }
+}
+
+/// 是否为回收站页面或其操作的路径
+pub fn is_trash_path(path: &str) -> bool {
+    path == TRASH_PAGE || path.starts_with("/_trash/")
+}
+
+/// 处理回收站请求，需要管理权限；未启用回收站（`--no-trash`）时返回 404
+///
+/// - `GET /_trash`：回收站页面，`?format=json` 或 `Accept: application/json` 时返回 JSON
+/// - `POST /_trash/restore`（`id`）：恢复到原路径，需要 `--allow-write`
+/// - `POST /_trash/purge`（`id`，或 `all=1` 清空回收站）：永久清除
+pub async fn handle(
+    req: Request<Body>,
+    remote: SocketAddr,
+    state: Arc<AppState>,
+) -> Response<Body> {
+    let lang = Lang::for_request(&req, state.lang);
+    if state.trash.is_none() {
+        return text_response(StatusCode::NOT_FOUND, lang.t("http.not_found"));
+    }
+    if !state.auth.is_authorized(&req, remote) {
+        return auth::unauthorized(lang);
+    }
+    let path = req.uri().path().to_string();
+    match (req.method().clone(), path.as_str()) {
+        (Method::GET, TRASH_PAGE) => {
+            let mut response = match state.trash.as_ref().unwrap().list() {
+                Ok(items) if crate::wants_json(&req) => Response::builder()
+                    .header("Content-Type", "application/json")
+                    .header(header::CACHE_CONTROL, "no-store")
+                    .body(Body::from(serde_json::to_string(&items).unwrap()))
+                    .unwrap(),
+                Ok(items) => page(&items, &state, lang),
+                Err(e) => text_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("500 - {}", e)),
+            };
+            if let Some(cookie) = state.auth.cookie_for(&req) {
+                response
+                    .headers_mut()
+                    .insert(header::SET_COOKIE, cookie.parse().unwrap());
+            }
+            response
+        }
+        (Method::POST, action @ ("/_trash/restore" | "/_trash/purge")) => {
+            if let Some(response) = auth::reject_cross_site(&req, lang) {
+                return response;
+            }
+            let restore = action == "/_trash/restore";
+            if restore && !state.allow_write {
+                return text_response(StatusCode::FORBIDDEN, lang.t("fs.disabled"));
+            }
+            let Some(body) = read_body(req, MAX_ACTION_BODY).await else {
+                return text_response(StatusCode::PAYLOAD_TOO_LARGE, lang.t("http.body_too_large"));
+            };
+            let field = |name: &str| {
+                form_urlencoded::parse(&body)
+                    .find(|(k, _)| k == name)
+                    .map(|(_, v)| v.into_owned())
+            };
+            let (id, all) = (field("id").unwrap_or_default(), field("all").is_some());
+            let client = remote.ip();
+            let task = {
+                let state = state.clone();
+                tokio::task::spawn_blocking(move || {
+                    if restore {
+                        restore_item(&state, &id, client, lang)
+                    } else if all {
+                        purge_all(&state, client)
+                    } else {
+                        purge_item(&state, &id, client, lang)
+                    }
+                })
+            };
+            match task.await {
+                Ok(Ok(())) => Response::builder()
+                    .status(StatusCode::SEE_OTHER)
+                    .header(header::LOCATION, TRASH_PAGE)
+                    .body(Body::empty())
+                    .unwrap(),
+                Ok(Err((status, message))) => text_response(status, &message),
+                Err(e) => text_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("500 - {}", e)),
+            }
+        }
+        _ => text_response(StatusCode::NOT_FOUND, lang.t("http.not_found")),
+    }
+}
+
+type ActionResult = Result<(), (StatusCode, String)>;
+
+fn internal_error(e: io::Error) -> (StatusCode, String) {
+    (StatusCode::INTERNAL_SERVER_ERROR, format!("500 - {}", e))
+}
+
+/// 恢复一项，原路径所在目录被排除或设置了 `write off` 时拒绝
+fn restore_item(state: &AppState, id: &str, client: std::net::IpAddr, lang: Lang) -> ActionResult {
+    let trash = state.trash.as_ref().unwrap();
+    let items = trash.list().map_err(internal_error)?;
+    let Some(item) = items.iter().find(|item| item.id == id) else {
+        return Err((StatusCode::NOT_FOUND, lang.t("trash.not_found").to_string()));
+    };
+    let access = state.access.check(&item.path, item.is_dir);
+    if access.denied || !access.writable {
+        return Err((
+            StatusCode::FORBIDDEN,
+            tr!(
+                lang,
+                "trash.restore_denied",
+                path = format!("/{}", item.path)
+            ),
+        ));
+    }
+    match trash.restore(id, &state.root_dir).map_err(internal_error)? {
+        Some((item, restored)) => {
+            log_request(
+                &state.log_mode,
+                state.logger.clone(),
+                format!(
+                    "FS restore /{} ({}) -> /{} [{}]",
+                    item.path, item.id, restored, client
+                ),
+            );
+            Ok(())
+        }
+        None => Err((StatusCode::NOT_FOUND, lang.t("trash.not_found").to_string())),
+    }
+}
+
+fn purge_item(state: &AppState, id: &str, client: std::net::IpAddr, lang: Lang) -> ActionResult {
+    let trash = state.trash.as_ref().unwrap();
+    match trash.purge(id).map_err(internal_error)? {
+        Some(item) => {
+            log_request(
+                &state.log_mode,
+                state.logger.clone(),
+                format!("FS purge /{} ({}) [{}]", item.path, item.id, client),
+            );
+            Ok(())
+        }
+        None => Err((StatusCode::NOT_FOUND, lang.t("trash.not_found").to_string())),
+    }
+}
+
+fn purge_all(state: &AppState, client: std::net::IpAddr) -> ActionResult {
+    let trash = state.trash.as_ref().unwrap();
+    for item in trash.list().map_err(internal_error)? {
+        if trash.purge(&item.id).map_err(internal_error)?.is_some() {
+            log_request(
+                &state.log_mode,
+                state.logger.clone(),
+                format!("FS purge /{} ({}) [{}]", item.path, item.id, client),
+            );
+        }
+    }
+    Ok(())
+}
+
+/// 回收站页面
+fn page(items: &[TrashItem], state: &AppState, lang: Lang) -> Response<Body> {
+    let trash = state.trash.as_ref().unwrap();
+    let restore_disabled = if state.allow_write { "" } else { " disabled" };
+    let mut rows = String::new();
+    for item in items {
+        rows += &format!(
+            r#"<tr><td>{} {}</td><td>{}</td><td>{}</td>
+<td><form method="post" action="/_trash/restore"><input type="hidden" name="id" value="{}"><button{}>{}</button></form></td>
+<td><form method="post" action="/_trash/purge" onsubmit="return confirm(CONFIRM_PURGE)"><input type="hidden" name="id" value="{}"><button>{}</button></form></td></tr>"#,
+            if item.is_dir { "📁" } else { "📄" },
+            html_escape(&format!("/{}", item.path)),
+            format_time(item.deleted),
+            format_size(item.size),
+            html_escape(&item.id),
+            restore_disabled,
+            lang.t("trash.restore"),
+            html_escape(&item.id),
+            lang.t("trash.purge"),
+        );
+    }
+    if items.is_empty() {
+        rows = format!(
+            r#"<tr><td colspan="5" style="text-align:center; color:#666">{}</td></tr>"#,
+            lang.t("trash.none")
+        );
+    }
+
+    let total: u64 = items.iter().map(|item| item.size).sum();
+    let mut policy = Vec::new();
+    if let Some(age) = trash.max_age {
+        policy.push(tr!(
+            lang,
+            "trash.policy_age",
+            days = age.as_secs().div_ceil(86400)
+        ));
+    }
+    if let Some(size) = trash.max_size {
+        policy.push(tr!(lang, "trash.policy_size", size = format_size(size)));
+    }
+    let html = format!(
+        r#"<!DOCTYPE html>
+<html lang="{lang}">
+<head>
+    <meta charset="utf-8">
+    <title>🗑️ {title}</title>
+    <style>
+        body {{ font-family: -apple-system, 'Segoe UI', Roboto, sans-serif; margin: 40px; }}
+        table {{ border-collapse: collapse; width: 100%; }}
+        th, td {{ border-bottom: 1px solid #e9ecef; padding: 8px; text-align: left; }}
+        td:first-child {{ word-break: break-all; }}
+        .summary {{ color: #666; }}
+    </style>
+    <script>
+        const CONFIRM_PURGE = {confirm_purge};
+        const CONFIRM_EMPTY = {confirm_empty};
+    </script>
+</head>
+<body>
+    <h2>🗑️ {title}</h2>
+    <p><a href="/">🏠 {home}</a></p>
+    <p class="summary">{summary} {policy}</p>
+    <form method="post" action="/_trash/purge" onsubmit="return confirm(CONFIRM_EMPTY)">
+        <input type="hidden" name="all" value="1"><button{empty_disabled}>{empty}</button>
+    </form>
+    <table>
+        <tr><th>{col_path}</th><th>{col_deleted}</th><th>{col_size}</th><th></th><th></th></tr>
+        {rows}
+    </table>
+</body>
+</html>"#,
+        lang = lang.code(),
+        title = lang.t("trash.page_title"),
+        home = lang.t("listing.home"),
+        confirm_purge = serde_json::Value::from(lang.t("trash.confirm_purge")),
+        confirm_empty = serde_json::Value::from(lang.t("trash.confirm_empty")),
+        summary = html_escape(&tr!(
+            lang,
+            "trash.summary",
+            count = items.len(),
+            size = format_size(total)
+        )),
+        policy = html_escape(&policy.join(" ")),
+        empty_disabled = if items.is_empty() { " disabled" } else { "" },
+        empty = lang.t("trash.empty"),
+        col_path = lang.t("trash.col_path"),
+        col_deleted = lang.t("trash.col_deleted"),
+        col_size = lang.t("trash.col_size"),
+        rows = rows,
+    );
+    Response::builder()
+        .header("Content-Type", "text/html; charset=utf-8")
+        .header(header::CACHE_CONTROL, "no-store")
+        .body(Body::from(html))
+        .unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TestDir;
+
+    /// 根目录和回收站
+    fn setup(name: &str, max_size: Option<u64>) -> (TestDir, PathBuf, Trash) {
+        let dir = TestDir::new(name);
+        let root = dir.join("root");
+        fs::create_dir_all(&root).unwrap();
+        let trash = Trash::open(&dir.join("data"), None, max_size).unwrap();
+        (dir, root, trash)
+    }
+
+    #[test]
+    fn restore_renames_when_the_path_is_taken() {
+        let (_dir, root, trash) = setup("trash_conflict", None);
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::write(root.join("docs/a.txt"), "old").unwrap();
+        let item = trash.put(&root, "docs/a.txt").unwrap();
+        fs::write(root.join("docs/a.txt"), "new").unwrap();
+
+        let (_, restored) = trash.restore(&item.id, &root).unwrap().unwrap();
+        assert_eq!(restored, "docs/a (1).txt");
+        assert_eq!(fs::read_to_string(root.join("docs/a (1).txt")).unwrap(), "old");
+        assert_eq!(fs::read_to_string(root.join("docs/a.txt")).unwrap(), "new");
+        assert!(trash.list().unwrap().is_empty());
+        // 已恢复的项不能再次恢复
+        assert!(trash.restore(&item.id, &root).unwrap().is_none());
+    }
+
+    #[test]
+    fn restore_recreates_missing_directories() {
+        let (_dir, root, trash) = setup("trash_parents", None);
+        fs::create_dir_all(root.join("a/b")).unwrap();
+        fs::write(root.join("a/b/c.txt"), "c").unwrap();
+        let item = trash.put(&root, "a/b").unwrap();
+        assert!(item.is_dir);
+        assert_eq!(item.size, 1);
+        fs::remove_dir(root.join("a")).unwrap();
+
+        let (_, restored) = trash.restore(&item.id, &root).unwrap().unwrap();
+        assert_eq!(restored, "a/b");
+        assert_eq!(fs::read_to_string(root.join("a/b/c.txt")).unwrap(), "c");
+        assert!(!trash.dir().join(ITEMS_DIR).join(&item.id).exists());
+    }
+
+    #[test]
+    fn oldest_items_are_purged_over_the_size_limit() {
+        let (_dir, root, trash) = setup("trash_size", Some(4));
+        let mut ids = Vec::new();
+        for (i, name) in ["a", "b", "c"].into_iter().enumerate() {
+            fs::write(root.join(name), "xx").unwrap();
+            let item = trash.put(&root, name).unwrap();
+            // 删除时间按秒记录，手动错开
+            let mut items = trash.load().unwrap();
+            items.last_mut().unwrap().deleted -= 10 - i as i64;
+            trash.save(&items).unwrap();
+            ids.push(item.id);
+        }
+
+        let purged = trash.purge_expired().unwrap();
+        assert_eq!(purged.len(), 1);
+        assert_eq!(purged[0].id, ids[0]);
+        let left: Vec<String> = trash.list().unwrap().into_iter().map(|item| item.id).collect();
+        assert_eq!(left, [ids[2].clone(), ids[1].clone()]);
+    }
+
+    #[test]
+    fn huge_max_age_keeps_everything() {
+        let dir = TestDir::new("trash_max_age");
+        let root = dir.join("root");
+        fs::create_dir_all(&root).unwrap();
+        let max_age = Duration::from_secs(u64::MAX);
+        let trash = Trash::open(&dir.join("data"), Some(max_age), None).unwrap();
+        fs::write(root.join("a"), "a").unwrap();
+        trash.put(&root, "a").unwrap();
+        assert!(trash.purge_expired().unwrap().is_empty());
+        assert_eq!(trash.list().unwrap().len(), 1);
+
+        let trash = Trash::open(&dir.join("data"), Some(Duration::from_secs(0)), None).unwrap();
+        assert_eq!(trash.purge_expired().unwrap().len(), 1);
+    }
+}
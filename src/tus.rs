@@ -0,0 +1,736 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chrono::{TimeZone, Utc};
+use hyper::body::HttpBody;
+use hyper::{header, Body, Method, Request, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use sha2::digest::DynDigest;
+use std::{
+    collections::HashSet,
+    fs, io,
+    net::{IpAddr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+use tokio::io::AsyncWriteExt;
+
+use crate::auth;
+use crate::fsapi::{self, Conflict, Rejected};
+use crate::i18n::Lang;
+use crate::upload::sanitize_file_name;
+use crate::util::{format_size, random_token, text_response};
+use crate::{log_request, tr, AppState};
+
+/// 断点续传接口路径前缀
+pub const TUS_PREFIX: &str = "/_tus/";
+
+/// 支持的协议版本
+const TUS_VERSION: &str = "1.0.0";
+/// 支持的扩展
+const TUS_EXTENSIONS: &str = "creation,termination,checksum,expiration";
+/// checksum 扩展支持的算法
+const CHECKSUM_ALGORITHMS: &str = "sha256,sha512";
+/// 数据目录下存放未完成上传的目录
+const TUS_DIR: &str = "tus";
+/// 清除过期上传的检查间隔
+const PURGE_INTERVAL: Duration = Duration::from_secs(600);
+
+/// 一个未完成的上传
+#[derive(Serialize, Deserialize)]
+struct Upload {
+    id: String,
+    /// 目标目录，以 `/` 开头
+    dir: String,
+    /// 清理后的文件名
+    name: String,
+    conflict: Conflict,
+    /// 文件总大小
+    length: u64,
+    /// 创建时的 Upload-Metadata，HEAD 时原样返回
+    metadata: String,
+    /// 过期时间（Unix 秒），每次写入后顺延
+    expires: i64,
+}
+
+impl Upload {
+    /// 目标文件路径，用于日志和传输登记
+    fn target(&self) -> String {
+        format!("{}/{}", self.dir.trim_end_matches('/'), self.name)
+    }
+
+    /// 到时间 `now`（Unix 秒）时是否已过期
+    fn is_expired(&self, now: i64) -> bool {
+        self.expires <= now
+    }
+}
+
+/// 未完成上传的暂存区，位于数据目录下（不在根目录中提供）
+///
+/// 每个上传保存为 `<id>.json`（上传信息）和 `<id>.bin`（已收到的数据），
+/// 已收到的字节数即数据文件的大小。超过有效期未完成的上传会被清除。
+pub struct TusStore {
+    dir: PathBuf,
+    expiry: Duration,
+    /// 正在写入的上传，同一上传同时只接受一个 PATCH
+    active: Mutex<HashSet<String>>,
+}
+
+/// 写入期间持有，释放时解除占用
+struct ActiveGuard<'a> {
+    store: &'a TusStore,
+    id: String,
+}
+
+impl Drop for ActiveGuard<'_> {
+    fn drop(&mut self) {
+        self.store.active.lock().unwrap().remove(&self.id);
+    }
+}
+
+impl TusStore {
+    /// 打开（必要时创建）数据目录下的暂存区，清理没有上传信息的残留数据
+    pub fn open(data_dir: &Path, expiry: Duration) -> io::Result<Self> {
+        let store = TusStore {
+            dir: data_dir.join(TUS_DIR),
+            expiry,
+            active: Mutex::new(HashSet::new()),
+        };
+        fs::create_dir_all(&store.dir)?;
+        for entry in fs::read_dir(&store.dir)? {
+            let path = entry?.path();
+            if path.extension().is_some_and(|ext| ext == "bin")
+                && !path.with_extension("json").exists()
+            {
+                fs::remove_file(&path)?;
+            }
+        }
+        Ok(store)
+    }
+
+    fn info_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", id))
+    }
+
+    fn data_path(&self, id: &str) -> PathBuf {
+        self.dir.join(format!("{}.bin", id))
+    }
+
+    /// 读取上传信息，ID 格式不对或不存在时返回 None
+    fn load(&self, id: &str) -> Option<Upload> {
+        if id.len() != 32 || !id.bytes().all(|b| b.is_ascii_hexdigit()) {
+            return None;
+        }
+        let bytes = fs::read(self.info_path(id)).ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    fn save(&self, upload: &Upload) -> io::Result<()> {
+        let path = self.info_path(&upload.id);
+        let tmp = path.with_extension("json.tmp");
+        let json = serde_json::to_vec(upload)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, &path)
+    }
+
+    /// 已收到的字节数
+    fn offset(&self, id: &str) -> u64 {
+        fs::metadata(self.data_path(id))
+            .map(|m| m.len())
+            .unwrap_or(0)
+    }
+
+    fn remove(&self, id: &str) {
+        let _ = fs::remove_file(self.data_path(id));
+        let _ = fs::remove_file(self.info_path(id));
+    }
+
+    /// 占用上传，已被占用时返回 None
+    fn begin(&self, id: &str) -> Option<ActiveGuard<'_>> {
+        if !self.active.lock().unwrap().insert(id.to_string()) {
+            return None;
+        }
+        Some(ActiveGuard {
+            store: self,
+            id: id.to_string(),
+        })
+    }
+
+    /// 从现在起的过期时间，保留时间极长时不会溢出
+    fn expires(&self) -> i64 {
+        let expiry = i64::try_from(self.expiry.as_secs()).unwrap_or(i64::MAX);
+        Utc::now().timestamp().saturating_add(expiry)
+    }
+
+    /// 清除过期的上传（正在写入的除外），返回被清除的上传
+    fn purge_expired(&self) -> io::Result<Vec<Upload>> {
+        let now = Utc::now().timestamp();
+        let mut purged = Vec::new();
+        for entry in fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.extension().is_none_or(|ext| ext != "json") {
+                continue;
+            }
+            let id = path.file_stem().unwrap_or_default().to_string_lossy();
+            let Some(upload) = self.load(&id) else {
+                continue;
+            };
+            if upload.is_expired(now) {
+                if let Some(_guard) = self.begin(&upload.id) {
+                    self.remove(&upload.id);
+                    purged.push(upload);
+                }
+            }
+        }
+        Ok(purged)
+    }
+}
+
+/// 定期清除过期的上传
+pub async fn purge_periodically(state: Arc<AppState>) {
+    let mut interval = tokio::time::interval(PURGE_INTERVAL);
+    loop {
+        interval.tick().await;
+        let task = {
+            let state = state.clone();
+            tokio::task::spawn_blocking(move || match &state.tus {
+                Some(tus) => tus.purge_expired(),
+                None => Ok(Vec::new()),
+            })
+        };
+        if let Ok(Ok(purged)) = task.await {
+            for upload in purged {
+                log_request(
+                    &state.log_mode,
+                    state.logger.clone(),
+                    format!("TUS expired {} ({}) [auto]", upload.target(), upload.id),
+                );
+            }
+        }
+    }
+}
+
+/// 是否为断点续传接口的路径
+pub fn is_tus_path(path: &str) -> bool {
+    path == TUS_PREFIX.trim_end_matches('/') || path.starts_with(TUS_PREFIX)
+}
+
+/// 处理 `/_tus/` 下的请求：tus 1.0 核心协议及 creation、termination、checksum、expiration 扩展
+///
+/// - `OPTIONS /_tus/`：支持的版本、扩展、校验算法和大小上限
+/// - `POST /_tus/`：创建上传，`Upload-Length` 不能超过 `--max-upload-size`，`Upload-Metadata` 中 `filename` 为文件名，`dir` 为目标目录（默认根目录），
+///   `conflict` 为同名文件的处理方式（同文件管理接口，默认返回 409）
+/// - `HEAD /_tus/<id>`：已收到的字节数
+/// - `PATCH /_tus/<id>`：从 `Upload-Offset` 处继续写入，可带 `Upload-Checksum` 校验本次数据
+/// - `DELETE /_tus/<id>`：放弃上传
+///
+/// 已过期但还没被清除的上传，HEAD 和 PATCH 返回 410。
+/// 需要 `--allow-write` 和管理权限，目标路径的校验与普通上传相同。
+/// 全部数据收到后文件放到目标位置，被覆盖的旧内容按普通上传的方式保留。
+pub async fn handle(
+    req: Request<Body>,
+    remote: SocketAddr,
+    state: Arc<AppState>,
+) -> Response<Body> {
+    let lang = Lang::for_request(&req, state.lang);
+    let Some(store) = &state.tus else {
+        return tus_error(StatusCode::FORBIDDEN, lang.t("fs.disabled").to_string());
+    };
+    if !state.auth.is_authorized(&req, remote) {
+        let mut response = auth::unauthorized(lang);
+        response
+            .headers_mut()
+            .insert("Tus-Resumable", TUS_VERSION.parse().unwrap());
+        return response;
+    }
+    // 不支持 PATCH / DELETE 的环境可以用 X-HTTP-Method-Override
+    let method = req
+        .headers()
+        .get("X-HTTP-Method-Override")
+        .and_then(|v| Method::from_bytes(v.as_bytes()).ok())
+        .unwrap_or_else(|| req.method().clone());
+    if method == Method::OPTIONS {
+        return Response::builder()
+            .status(StatusCode::NO_CONTENT)
+            .header("Tus-Resumable", TUS_VERSION)
+            .header("Tus-Version", TUS_VERSION)
+            .header("Tus-Extension", TUS_EXTENSIONS)
+            .header("Tus-Checksum-Algorithm", CHECKSUM_ALGORITHMS)
+            .header("Tus-Max-Size", state.max_upload_size)
+            .body(Body::empty())
+            .unwrap();
+    }
+    if header_str(&req, "Tus-Resumable") != Some(TUS_VERSION) {
+        let mut response = tus_error(
+            StatusCode::PRECONDITION_FAILED,
+            tr!(lang, "tus.unsupported_version"),
+        );
+        response
+            .headers_mut()
+            .insert("Tus-Version", TUS_VERSION.parse().unwrap());
+        return response;
+    }
+    let id = req
+        .uri()
+        .path()
+        .strip_prefix(TUS_PREFIX)
+        .unwrap_or("")
+        .to_string();
+    let result = match (method, id.is_empty()) {
+        (Method::POST, true) => create(&req, remote.ip(), &state, store, lang).await,
+        (Method::HEAD, false) => head(&id, store, lang),
+        (Method::PATCH, false) => patch(req, &id, remote.ip(), &state, store, lang).await,
+        (Method::DELETE, false) => terminate(&id, remote.ip(), &state, store, lang),
+        (_, true) | (Method::GET, false) => Err(Rejected(
+            StatusCode::METHOD_NOT_ALLOWED,
+            lang.t("http.method_not_allowed").to_string(),
+        )),
+        _ => Err(Rejected(
+            StatusCode::NOT_FOUND,
+            lang.t("http.not_found").to_string(),
+        )),
+    };
+    result.unwrap_or_else(|Rejected(status, message)| tus_error(status, message))
+}
+
+/// 创建上传，校验目标路径后返回上传地址
+async fn create(
+    req: &Request<Body>,
+    client: IpAddr,
+    state: &Arc<AppState>,
+    store: &TusStore,
+    lang: Lang,
+) -> Result<Response<Body>, Rejected> {
+    let bad_request = |key: &str| Rejected(StatusCode::BAD_REQUEST, tr!(lang, key));
+    let length: u64 = header_str(req, "Upload-Length")
+        .and_then(|v| v.parse().ok())
+        .ok_or_else(|| bad_request("tus.bad_length"))?;
+    if length > state.max_upload_size {
+        return Err(Rejected(
+            StatusCode::PAYLOAD_TOO_LARGE,
+            tr!(lang, "tus.too_large", size = format_size(state.max_upload_size)),
+        ));
+    }
+    let raw_metadata = header_str(req, "Upload-Metadata").unwrap_or("").to_string();
+    let metadata = parse_metadata(&raw_metadata).ok_or_else(|| bad_request("tus.bad_metadata"))?;
+    let field = |key: &str| {
+        metadata
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    };
+    let name = field("filename")
+        .and_then(sanitize_file_name)
+        .ok_or_else(|| bad_request("tus.no_filename"))?;
+    let dir = field("dir").unwrap_or("/").to_string();
+    let conflict = match field("conflict") {
+        Some(value) => serde_json::from_value(serde_json::Value::String(value.to_string()))
+            .map_err(|e| Rejected(StatusCode::BAD_REQUEST, format!("400 - {}", e)))?,
+        None => Conflict::Fail,
+    };
+    fsapi::check_upload(state, client, lang, &dir, &name, conflict)?;
+
+    let upload = Upload {
+        id: random_token(16),
+        dir,
+        name,
+        conflict,
+        length,
+        metadata: raw_metadata,
+        expires: store.expires(),
+    };
+    let created = fs::File::create(store.data_path(&upload.id)).and_then(|_| store.save(&upload));
+    if let Err(e) = created {
+        store.remove(&upload.id);
+        return Err(server_error(e));
+    }
+    log_request(
+        &state.log_mode,
+        state.logger.clone(),
+        format!(
+            "TUS create {} ({}, {} bytes) [{}]",
+            upload.target(),
+            upload.id,
+            length,
+            client
+        ),
+    );
+    // 空文件无需再发送数据
+    if length == 0 {
+        complete(&upload, client, state, store, lang).await?;
+    }
+    Ok(Response::builder()
+        .status(StatusCode::CREATED)
+        .header("Tus-Resumable", TUS_VERSION)
+        .header(header::LOCATION, format!("{}{}", TUS_PREFIX, upload.id))
+        .header("Upload-Expires", http_date(upload.expires))
+        .body(Body::empty())
+        .unwrap())
+}
+
+/// 查询已收到的字节数
+fn head(id: &str, store: &TusStore, lang: Lang) -> Result<Response<Body>, Rejected> {
+    let upload = find(store, id, lang)?;
+    let mut response = Response::builder()
+        .header("Tus-Resumable", TUS_VERSION)
+        .header(header::CACHE_CONTROL, "no-store")
+        .header("Upload-Offset", store.offset(id))
+        .header("Upload-Length", upload.length)
+        .header("Upload-Expires", http_date(upload.expires));
+    if !upload.metadata.is_empty() {
+        response = response.header("Upload-Metadata", &upload.metadata);
+    }
+    Ok(response.body(Body::empty()).unwrap())
+}
+
+/// 从当前位置继续写入，数据全部收到后放到目标位置
+///
+/// 连接中断时保留已写入的数据，客户端用 HEAD 查询位置后继续；
+/// 带 `Upload-Checksum` 时只有校验通过的数据才会保留。
+async fn patch(
+    req: Request<Body>,
+    id: &str,
+    client: IpAddr,
+    state: &Arc<AppState>,
+    store: &TusStore,
+    lang: Lang,
+) -> Result<Response<Body>, Rejected> {
+    if header_str(&req, header::CONTENT_TYPE.as_str()) != Some("application/offset+octet-stream") {
+        return Err(Rejected(
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            tr!(lang, "tus.bad_content_type"),
+        ));
+    }
+    // 先占用再读取，避免读取后被定期清除删掉
+    let Some(_guard) = store.begin(id) else {
+        return Err(Rejected(StatusCode::CONFLICT, tr!(lang, "tus.busy")));
+    };
+    let mut upload = find(store, id, lang)?;
+    let offset = store.offset(id);
+    if header_str(&req, "Upload-Offset").and_then(|v| v.parse().ok()) != Some(offset) {
+        return Err(Rejected(
+            StatusCode::CONFLICT,
+            tr!(lang, "tus.offset_mismatch", offset = offset),
+        ));
+    }
+    let mut checksum = match header_str(&req, "Upload-Checksum") {
+        Some(value) => Some(
+            parse_checksum(value)
+                .ok_or_else(|| Rejected(StatusCode::BAD_REQUEST, tr!(lang, "tus.bad_checksum")))?,
+        ),
+        None => None,
+    };
+
+    let data = store.data_path(id);
+    let mut file = tokio::fs::OpenOptions::new()
+        .append(true)
+        .open(&data)
+        .await
+        .map_err(server_error)?;
+    let content_length =
+        header_str(&req, header::CONTENT_LENGTH.as_str()).and_then(|v| v.parse().ok());
+    let mut body =
+        state
+            .monitor
+            .track_upload(req.into_body(), &upload.target(), client, content_length);
+    let remaining = upload.length - offset;
+    let verified = checksum.is_some();
+    let mut written = 0u64;
+    let mut interrupted = false;
+    let result = loop {
+        let chunk = match body.data().await {
+            None => break Ok(()),
+            Some(Err(e)) => {
+                interrupted = true;
+                break Err(server_error(io::Error::other(e)));
+            }
+            Some(Ok(chunk)) => chunk,
+        };
+        written += chunk.len() as u64;
+        if written > remaining {
+            break Err(Rejected(
+                StatusCode::PAYLOAD_TOO_LARGE,
+                tr!(lang, "tus.too_long", length = upload.length),
+            ));
+        }
+        if let Some((hasher, _)) = &mut checksum {
+            hasher.update(&chunk);
+        }
+        if let Err(e) = file.write_all(&chunk).await {
+            break Err(server_error(e));
+        }
+    };
+    let result = result.and_then(|_| match checksum {
+        Some((hasher, expected)) => {
+            if *hasher.finalize() == *expected {
+                Ok(())
+            } else {
+                Err(Rejected(
+                    StatusCode::from_u16(460).unwrap(),
+                    tr!(lang, "tus.checksum_mismatch"),
+                ))
+            }
+        }
+        None => Ok(()),
+    });
+    let _ = file.sync_data().await;
+    drop(file);
+    // 未带校验时连接中断保留已收到的部分，其他错误丢弃本次写入的数据
+    if let Err(error) = result {
+        if verified || !interrupted {
+            let _ = fs::OpenOptions::new()
+                .write(true)
+                .open(&data)
+                .and_then(|f| f.set_len(offset));
+        }
+        return Err(error);
+    }
+
+    let offset = store.offset(id);
+    if offset == upload.length {
+        complete(&upload, client, state, store, lang).await?;
+    } else {
+        upload.expires = store.expires();
+        store.save(&upload).map_err(server_error)?;
+    }
+    Ok(Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .header("Tus-Resumable", TUS_VERSION)
+        .header("Upload-Offset", offset)
+        .header("Upload-Expires", http_date(upload.expires))
+        .body(Body::empty())
+        .unwrap())
+}
+
+/// 再次校验目标路径后放到目标位置；校验失败时上传无法完成，一并清除
+async fn complete(
+    upload: &Upload,
+    client: IpAddr,
+    state: &Arc<AppState>,
+    store: &TusStore,
+    lang: Lang,
+) -> Result<String, Rejected> {
+    let checked = fsapi::check_upload(
+        state,
+        client,
+        lang,
+        &upload.dir,
+        &upload.name,
+        upload.conflict,
+    );
+    let result = match checked {
+        Ok((relative, replace)) => {
+            let temp = store.data_path(&upload.id);
+            fsapi::finish_upload(state, client, relative, replace, temp, &[], lang).await
+        }
+        Err(e) => Err(e),
+    };
+    store.remove(&upload.id);
+    result
+}
+
+/// 放弃上传，删除已收到的数据
+fn terminate(
+    id: &str,
+    client: IpAddr,
+    state: &AppState,
+    store: &TusStore,
+    lang: Lang,
+) -> Result<Response<Body>, Rejected> {
+    let upload = store.load(id).ok_or_else(|| not_found(lang))?;
+    let Some(_guard) = store.begin(id) else {
+        return Err(Rejected(StatusCode::CONFLICT, tr!(lang, "tus.busy")));
+    };
+    store.remove(id);
+    log_request(
+        &state.log_mode,
+        state.logger.clone(),
+        format!(
+            "TUS terminate {} ({}) [{}]",
+            upload.target(),
+            upload.id,
+            client
+        ),
+    );
+    Ok(Response::builder()
+        .status(StatusCode::NO_CONTENT)
+        .header("Tus-Resumable", TUS_VERSION)
+        .body(Body::empty())
+        .unwrap())
+}
+
+/// 解析 `Upload-Metadata`：逗号分隔的 `键 Base64值`，值可以省略
+fn parse_metadata(value: &str) -> Option<Vec<(String, String)>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, encoded) = pair.split_once(' ').unwrap_or((pair, ""));
+            let decoded = STANDARD.decode(encoded.trim()).ok()?;
+            Some((key.to_string(), String::from_utf8(decoded).ok()?))
+        })
+        .collect()
+}
+
+/// 解析 `Upload-Checksum`：`算法 Base64摘要`
+fn parse_checksum(value: &str) -> Option<(Box<dyn DynDigest + Send>, Vec<u8>)> {
+    let (algorithm, encoded) = value.trim().split_once(' ')?;
+    let hasher: Box<dyn DynDigest + Send> = match algorithm {
+        "sha256" => Box::new(sha2::Sha256::default()),
+        "sha512" => Box::new(sha2::Sha512::default()),
+        _ => return None,
+    };
+    Some((hasher, STANDARD.decode(encoded.trim()).ok()?))
+}
+
+fn header_str<'a>(req: &'a Request<Body>, name: &str) -> Option<&'a str> {
+    req.headers().get(name).and_then(|v| v.to_str().ok())
+}
+
+/// HTTP 日期格式，例如 `Sun, 06 Nov 1994 08:49:37 GMT`
+fn http_date(ts: i64) -> String {
+    Utc.timestamp_opt(ts, 0)
+        .single()
+        .map(|t| t.format("%a, %d %b %Y %H:%M:%S GMT").to_string())
+        .unwrap_or_default()
+}
+
+/// 读取未过期的上传；已过期但还没被清除的返回 410
+fn find(store: &TusStore, id: &str, lang: Lang) -> Result<Upload, Rejected> {
+    let upload = store.load(id).ok_or_else(|| not_found(lang))?;
+    if upload.is_expired(Utc::now().timestamp()) {
+        return Err(Rejected(StatusCode::GONE, tr!(lang, "tus.expired")));
+    }
+    Ok(upload)
+}
+
+fn not_found(lang: Lang) -> Rejected {
+    Rejected(StatusCode::NOT_FOUND, tr!(lang, "tus.not_found"))
+}
+
+fn server_error(e: io::Error) -> Rejected {
+    Rejected(StatusCode::INTERNAL_SERVER_ERROR, format!("500 - {}", e))
+}
+
+/// 错误响应同样带上 `Tus-Resumable`
+fn tus_error(status: StatusCode, message: String) -> Response<Body> {
+    let mut response = text_response(status, &message);
+    response
+        .headers_mut()
+        .insert("Tus-Resumable", TUS_VERSION.parse().unwrap());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TestDir;
+
+    fn upload(id: &str, length: u64, expires: i64) -> Upload {
+        Upload {
+            id: id.to_string(),
+            dir: "/".to_string(),
+            name: "a.bin".to_string(),
+            conflict: Conflict::Fail,
+            length,
+            metadata: String::new(),
+            expires,
+        }
+    }
+
+    #[test]
+    fn offset_follows_received_data() {
+        let dir = TestDir::new("tus_offset");
+        let store = TusStore::open(&dir, Duration::from_secs(60)).unwrap();
+        let id = random_token(16);
+        fs::File::create(store.data_path(&id)).unwrap();
+        store.save(&upload(&id, 10, store.expires())).unwrap();
+
+        assert_eq!(store.load(&id).unwrap().length, 10);
+        assert_eq!(store.offset(&id), 0);
+        fs::write(store.data_path(&id), b"hello").unwrap();
+        assert_eq!(store.offset(&id), 5);
+
+        // ID 格式不对的不去读文件
+        assert!(store.load("../tus/x").is_none());
+        assert!(store.load(&random_token(16)).is_none());
+        store.remove(&id);
+        assert!(store.load(&id).is_none());
+        assert_eq!(store.offset(&id), 0);
+    }
+
+    #[test]
+    fn only_one_writer_at_a_time() {
+        let dir = TestDir::new("tus_begin");
+        let store = TusStore::open(&dir, Duration::from_secs(60)).unwrap();
+        let guard = store.begin("a").unwrap();
+        assert!(store.begin("a").is_none());
+        assert!(store.begin("b").is_some());
+        drop(guard);
+        assert!(store.begin("a").is_some());
+    }
+
+    #[test]
+    fn expired_uploads_are_purged_unless_active() {
+        let dir = TestDir::new("tus_purge");
+        let store = TusStore::open(&dir, Duration::from_secs(60)).unwrap();
+        let now = Utc::now().timestamp();
+        let (live, expired, busy) = (random_token(16), random_token(16), random_token(16));
+        for (id, expires) in [(&live, now + 60), (&expired, now), (&busy, now - 1)] {
+            fs::File::create(store.data_path(id)).unwrap();
+            store.save(&upload(id, 1, expires)).unwrap();
+        }
+        assert!(!store.load(&live).unwrap().is_expired(now));
+        assert!(store.load(&expired).unwrap().is_expired(now));
+
+        let _guard = store.begin(&busy).unwrap();
+        let purged = store.purge_expired().unwrap();
+        assert_eq!(purged.len(), 1);
+        assert_eq!(purged[0].id, expired);
+        assert!(!store.data_path(&expired).exists());
+        assert!(store.load(&live).is_some());
+        assert!(store.load(&busy).is_some());
+    }
+
+    #[test]
+    fn leftover_data_is_removed_on_open() {
+        let dir = TestDir::new("tus_open");
+        let store = TusStore::open(&dir, Duration::from_secs(60)).unwrap();
+        let (kept, orphan) = (random_token(16), random_token(16));
+        fs::File::create(store.data_path(&kept)).unwrap();
+        store.save(&upload(&kept, 1, store.expires())).unwrap();
+        fs::File::create(store.data_path(&orphan)).unwrap();
+
+        let store = TusStore::open(&dir, Duration::from_secs(60)).unwrap();
+        assert!(store.data_path(&kept).exists());
+        assert!(!store.data_path(&orphan).exists());
+    }
+
+    #[test]
+    fn parses_metadata_and_checksum() {
+        let metadata = parse_metadata("filename YS50eHQ=, dir Lw==,empty").unwrap();
+        assert_eq!(
+            metadata,
+            [
+                ("filename".to_string(), "a.txt".to_string()),
+                ("dir".to_string(), "/".to_string()),
+                ("empty".to_string(), String::new()),
+            ]
+        );
+        assert!(parse_metadata("filename !!!").is_none());
+
+        let (_, digest) = parse_checksum("sha256 AAEC").unwrap();
+        assert_eq!(digest, [0, 1, 2]);
+        assert!(parse_checksum("md5 AAEC").is_none());
+        assert!(parse_checksum("sha256").is_none());
+    }
+
+    #[test]
+    fn huge_expiry_does_not_wrap() {
+        let dir = TestDir::new("tus_expiry");
+        let store = TusStore::open(&dir, Duration::from_secs(u64::MAX)).unwrap();
+        assert_eq!(store.expires(), i64::MAX);
+        assert!(!upload("a", 1, store.expires()).is_expired(Utc::now().timestamp()));
+    }
+}
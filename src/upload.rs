@@ -0,0 +1,473 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use futures_util::{Stream, StreamExt};
+use hyper::{body::Bytes, HeaderMap};
+use sha2::{Digest, Sha256};
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+use tokio::{fs, io::AsyncWriteExt};
+
+use crate::checksum::{Algorithm, Hasher};
+use crate::i18n::Lang;
+use crate::tr;
+use crate::util::{format_size, random_token};
+
+/// Windows 下的保留设备名，不能直接作为文件名
+const RESERVED_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+/// 文件名最大字节数
+const MAX_NAME_BYTES: usize = 255;
+
+/// multipart 中摘要字段的最大长度
+const MAX_DIGEST_FIELD: usize = 256;
+
+/// 已保存的上传文件
+#[derive(Debug, Clone)]
+pub struct ReceivedFile {
+    pub path: PathBuf,
+    pub name: String,
+    pub size: u64,
+    pub sha256: String,
+    /// 客户端声明并已校验的摘要
+    pub verified: Vec<FileDigest>,
+}
+
+/// 上传失败的原因
+#[derive(Debug)]
+pub enum UploadError {
+    /// 文件超过大小上限
+    TooLarge(u64),
+    /// 缺少文件名或文件名无效
+    BadName,
+    /// 摘要请求头或字段格式错误
+    BadDigest(String),
+    /// 摘要请求头中没有支持的算法
+    UnsupportedDigest(String),
+    /// multipart 请求带有摘要请求头（应使用摘要字段）
+    MultipartDigest,
+    /// 内容与客户端声明的摘要不符
+    DigestMismatch(Algorithm),
+    Multipart(multer::Error),
+    Io(io::Error),
+}
+
+impl UploadError {
+    /// 返回给客户端的错误信息
+    pub fn message(&self, lang: Lang) -> String {
+        match self {
+            UploadError::TooLarge(limit) => {
+                tr!(lang, "upload.too_large", size = format_size(*limit))
+            }
+            UploadError::BadName => tr!(lang, "upload.bad_name"),
+            UploadError::BadDigest(source) => tr!(lang, "upload.bad_digest", source = source),
+            UploadError::UnsupportedDigest(source) => {
+                tr!(lang, "upload.unsupported_digest", source = source)
+            }
+            UploadError::MultipartDigest => tr!(lang, "upload.multipart_digest"),
+            UploadError::DigestMismatch(algorithm) => {
+                tr!(lang, "upload.digest_mismatch", algorithm = algorithm.name())
+            }
+            UploadError::Multipart(e) => e.to_string(),
+            UploadError::Io(e) => e.to_string(),
+        }
+    }
+}
+
+impl From<io::Error> for UploadError {
+    fn from(e: io::Error) -> Self {
+        UploadError::Io(e)
+    }
+}
+
+impl From<multer::Error> for UploadError {
+    fn from(e: multer::Error) -> Self {
+        UploadError::Multipart(e)
+    }
+}
+
+impl From<hyper::Error> for UploadError {
+    fn from(e: hyper::Error) -> Self {
+        UploadError::Io(io::Error::other(e))
+    }
+}
+
+/// 客户端声明的文件摘要，写入时校验
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileDigest {
+    pub algorithm: Algorithm,
+    pub digest: Vec<u8>,
+}
+
+impl fmt::Display for FileDigest {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}={}", self.algorithm.name(), hex::encode(&self.digest))
+    }
+}
+
+/// 访问日志中记录的已校验摘要，没有时为空
+pub fn verified_note(digests: &[FileDigest]) -> String {
+    if digests.is_empty() {
+        return String::new();
+    }
+    let digests: Vec<String> = digests.iter().map(FileDigest::to_string).collect();
+    format!(" verified {}", digests.join(" "))
+}
+
+/// 请求头中声明的内容摘要：`Content-MD5`、`Digest`（RFC 3230）和 `Repr-Digest`（RFC 9530）
+///
+/// 忽略不支持的算法，但请求头中一个支持的算法都没有时视为错误，避免在客户端以为已校验时放行。
+pub fn header_digests(headers: &HeaderMap) -> Result<Vec<FileDigest>, UploadError> {
+    let mut digests = Vec::new();
+    for value in headers.get_all("content-md5") {
+        let digest = value
+            .to_str()
+            .ok()
+            .and_then(|v| decode_digest(Algorithm::Md5, v.trim()))
+            .ok_or_else(|| UploadError::BadDigest("Content-MD5".into()))?;
+        digests.push(digest);
+    }
+    for (name, strip) in [("digest", false), ("repr-digest", true)] {
+        let source = if strip { "Repr-Digest" } else { "Digest" };
+        for value in headers.get_all(name) {
+            let value = value
+                .to_str()
+                .map_err(|_| UploadError::BadDigest(source.into()))?;
+            let mut supported = false;
+            for item in value.split(',').map(str::trim).filter(|i| !i.is_empty()) {
+                let (algorithm, encoded) = item
+                    .split_once('=')
+                    .ok_or_else(|| UploadError::BadDigest(source.into()))?;
+                let Some(algorithm) = http_algorithm(algorithm) else {
+                    continue;
+                };
+                // Repr-Digest 的值是结构化字段的字节序列 `:<base64>:`，可能带参数
+                let encoded = if strip {
+                    let encoded = encoded.split(';').next().unwrap_or("").trim();
+                    encoded
+                        .strip_prefix(':')
+                        .and_then(|e| e.strip_suffix(':'))
+                        .ok_or_else(|| UploadError::BadDigest(source.into()))?
+                } else {
+                    encoded.trim()
+                };
+                let digest = decode_digest(algorithm, encoded)
+                    .ok_or_else(|| UploadError::BadDigest(source.into()))?;
+                digests.push(digest);
+                supported = true;
+            }
+            if !supported {
+                return Err(UploadError::UnsupportedDigest(source.into()));
+            }
+        }
+    }
+    Ok(digests)
+}
+
+/// HTTP 摘要算法名（RFC 3230 / RFC 9530 注册表，不区分大小写）
+fn http_algorithm(name: &str) -> Option<Algorithm> {
+    match name.trim().to_ascii_lowercase().as_str() {
+        "sha-256" => Some(Algorithm::Sha256),
+        "sha" => Some(Algorithm::Sha1),
+        "md5" => Some(Algorithm::Md5),
+        _ => None,
+    }
+}
+
+/// 解码 Base64 摘要并检查长度
+fn decode_digest(algorithm: Algorithm, encoded: &str) -> Option<FileDigest> {
+    let digest = STANDARD.decode(encoded).ok()?;
+    (digest.len() == digest_len(algorithm)).then_some(FileDigest { algorithm, digest })
+}
+
+fn digest_len(algorithm: Algorithm) -> usize {
+    match algorithm {
+        Algorithm::Sha256 | Algorithm::Blake3 => 32,
+        Algorithm::Sha1 => 20,
+        Algorithm::Md5 => 16,
+    }
+}
+
+/// multipart 中的摘要字段：字段名为算法名（`sha256`、`sha1`、`md5`、`blake3`），值为十六进制摘要，
+/// 校验其后的下一个文件字段；不是摘要字段时返回 None
+pub async fn digest_field(mut field: multer::Field<'_>) -> Result<Option<FileDigest>, UploadError> {
+    let Some(algorithm) = field.name().and_then(Algorithm::parse) else {
+        return Ok(None);
+    };
+    let source = algorithm.name().to_string();
+    let mut value = Vec::new();
+    while let Some(chunk) = field.chunk().await? {
+        value.extend_from_slice(&chunk);
+        if value.len() > MAX_DIGEST_FIELD {
+            return Err(UploadError::BadDigest(source));
+        }
+    }
+    let digest = std::str::from_utf8(&value)
+        .ok()
+        .and_then(|v| hex::decode(v.trim()).ok())
+        .filter(|d| d.len() == digest_len(algorithm))
+        .ok_or(UploadError::BadDigest(source))?;
+    Ok(Some(FileDigest { algorithm, digest }))
+}
+
+/// 清理客户端提供的文件名：去掉路径部分、控制字符和各平台的非法字符，
+/// 避免覆盖隐藏文件或写出目标目录；清理后为空则返回 None
+pub fn sanitize_file_name(name: &str) -> Option<String> {
+    let base = name.rsplit(['/', '\\']).next().unwrap_or("");
+    let cleaned: String = base
+        .chars()
+        .filter(|c| !c.is_control())
+        .map(|c| match c {
+            '<' | '>' | ':' | '"' | '|' | '?' | '*' => '_',
+            c => c,
+        })
+        .collect();
+    let mut cleaned = cleaned
+        .trim()
+        .trim_start_matches('.')
+        .trim_end_matches(['.', ' '])
+        .to_string();
+    if cleaned.is_empty() {
+        return None;
+    }
+
+    let stem = cleaned.split('.').next().unwrap_or("").to_ascii_uppercase();
+    if RESERVED_NAMES.contains(&stem.as_str()) {
+        cleaned.insert(0, '_');
+    }
+
+    while cleaned.len() > MAX_NAME_BYTES {
+        cleaned.pop();
+    }
+    Some(cleaned)
+}
+
+/// 目标目录中不存在同名文件的路径，重名时追加 ` (1)`、` (2)` ...
+pub fn unique_path(dir: &Path, name: &str) -> PathBuf {
+    unique_path_by(dir, name, |path| path.exists())
+}
+
+/// 同 [`unique_path`]，由 `taken` 判断路径是否已被占用
+pub fn unique_path_by(dir: &Path, name: &str, taken: impl Fn(&Path) -> bool) -> PathBuf {
+    let candidate = dir.join(name);
+    if !taken(&candidate) {
+        return candidate;
+    }
+    let (stem, ext) = match name.rfind('.') {
+        Some(i) if i > 0 => (&name[..i], &name[i..]),
+        _ => (name, ""),
+    };
+    (1..)
+        .map(|n| dir.join(format!("{} ({}){}", stem, n, ext)))
+        .find(|p| !taken(p))
+        .unwrap()
+}
+
+/// 将 multipart 中的一个文件字段流式写入目标目录，重名时自动改名
+pub async fn save_field(
+    field: multer::Field<'_>,
+    dir: &Path,
+    max_size: Option<u64>,
+    expected: &[FileDigest],
+) -> Result<ReceivedFile, UploadError> {
+    let name = field
+        .file_name()
+        .and_then(sanitize_file_name)
+        .ok_or(UploadError::BadName)?;
+
+    let (temp, size, sha256) = write_temp(field, dir, &name, max_size, expected).await?;
+    let path = unique_path(dir, &name);
+    if let Err(e) = fs::rename(&temp, &path).await {
+        let _ = fs::remove_file(&temp).await;
+        return Err(e.into());
+    }
+    let name = path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or(name);
+    Ok(ReceivedFile {
+        path,
+        name,
+        size,
+        sha256,
+        verified: expected.to_vec(),
+    })
+}
+
+/// 将数据流写入目标目录下的临时文件并同时计算 SHA-256，返回临时文件路径、大小和哈希
+///
+/// 写入时同时计算 `expected` 中各算法的摘要，全部相符才算成功。由调用方把临时文件原子重命名为
+/// 最终文件名；超过 `max_size`、摘要不符或出错时删除临时文件。
+pub async fn write_temp<S, E>(
+    stream: S,
+    dir: &Path,
+    name: &str,
+    max_size: Option<u64>,
+    expected: &[FileDigest],
+) -> Result<(PathBuf, u64, String), UploadError>
+where
+    S: Stream<Item = Result<Bytes, E>>,
+    UploadError: From<E>,
+{
+    // 文件名本身可以长达 MAX_NAME_BYTES，临时文件名截短原名称，避免超出文件系统的长度限制
+    let suffix = format!(".part-{}", random_token(4));
+    let mut prefix = format!(".{}", name);
+    while prefix.len() + suffix.len() > MAX_NAME_BYTES {
+        prefix.pop();
+    }
+    let temp = dir.join(prefix + &suffix);
+    let result = async {
+        futures_util::pin_mut!(stream);
+        let mut file = fs::File::create(&temp).await?;
+        let mut hasher = Sha256::new();
+        // SHA-256 总会计算，其他算法按需各算一次
+        let mut others: Vec<(Algorithm, Hasher)> = Vec::new();
+        for digest in expected {
+            let algorithm = digest.algorithm;
+            if algorithm != Algorithm::Sha256 && others.iter().all(|(a, _)| *a != algorithm) {
+                others.push((algorithm, Hasher::new(algorithm)));
+            }
+        }
+        let mut size = 0u64;
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            size += chunk.len() as u64;
+            if let Some(limit) = max_size {
+                if size > limit {
+                    return Err(UploadError::TooLarge(limit));
+                }
+            }
+            hasher.update(&chunk);
+            for (_, other) in &mut others {
+                other.update(&chunk);
+            }
+            file.write_all(&chunk).await?;
+        }
+        file.sync_all().await?;
+        let mut actual = vec![(Algorithm::Sha256, hasher.finalize().to_vec())];
+        actual.extend(others.into_iter().map(|(a, h)| (a, h.finalize())));
+        for digest in expected {
+            if !actual.contains(&(digest.algorithm, digest.digest.clone())) {
+                return Err(UploadError::DigestMismatch(digest.algorithm));
+            }
+        }
+        Ok((size, hex::encode(&actual[0].1)))
+    }
+    .await;
+
+    match result {
+        Ok((size, sha256)) => Ok((temp, size, sha256)),
+        Err(e) => {
+            let _ = fs::remove_file(&temp).await;
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TestDir;
+
+    const MD5_ABC: &str = "kAFQmDzST7DWlj99KOF/cg==";
+    const SHA256_ABC: &str = "ungWv48Bz+pBQUDeXa4iI7ADYaOWF3qctBD/YfIAFa0=";
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.append(*name, value.parse().unwrap());
+        }
+        headers
+    }
+
+    fn digest(algorithm: Algorithm, encoded: &str) -> FileDigest {
+        decode_digest(algorithm, encoded).unwrap()
+    }
+
+    #[test]
+    fn digests_are_read_from_headers() {
+        let parsed = header_digests(&headers(&[
+            ("content-md5", MD5_ABC),
+            ("digest", &format!("UNIXsum=30, SHA-256={}", SHA256_ABC)),
+            ("repr-digest", &format!("md5=:{}:;x=1", MD5_ABC)),
+        ]))
+        .unwrap();
+        assert_eq!(
+            parsed,
+            [
+                digest(Algorithm::Md5, MD5_ABC),
+                digest(Algorithm::Sha256, SHA256_ABC),
+                digest(Algorithm::Md5, MD5_ABC),
+            ]
+        );
+        assert!(header_digests(&HeaderMap::new()).unwrap().is_empty());
+    }
+
+    #[test]
+    fn malformed_digest_headers_are_rejected() {
+        let rejected =
+            |pairs: &[(&'static str, &str)]| header_digests(&headers(pairs)).unwrap_err();
+        assert!(matches!(rejected(&[("content-md5", "not base64")]), UploadError::BadDigest(_)));
+        // 长度与算法不符
+        assert!(matches!(rejected(&[("content-md5", SHA256_ABC)]), UploadError::BadDigest(_)));
+        assert!(matches!(
+            rejected(&[("repr-digest", &format!("sha-256={}", SHA256_ABC))]),
+            UploadError::BadDigest(_)
+        ));
+        assert!(matches!(
+            rejected(&[("digest", "UNIXsum=30")]),
+            UploadError::UnsupportedDigest(_)
+        ));
+    }
+
+    /// 分两块写入 `abc`
+    async fn write(
+        dir: &Path,
+        max_size: Option<u64>,
+        expected: &[FileDigest],
+    ) -> Result<(PathBuf, u64, String), UploadError> {
+        let chunks = [Ok::<_, io::Error>(Bytes::from("a")), Ok(Bytes::from("bc"))];
+        write_temp(futures_util::stream::iter(chunks), dir, "a.txt", max_size, expected).await
+    }
+
+    #[tokio::test]
+    async fn matching_digests_are_accepted() {
+        let dir = TestDir::new("upload_digest_ok");
+        let expected = [digest(Algorithm::Md5, MD5_ABC), digest(Algorithm::Sha256, SHA256_ABC)];
+        let (temp, size, sha256) = write(&dir, None, &expected).await.unwrap();
+        assert_eq!(size, 3);
+        assert_eq!(sha256, hex::encode(&expected[1].digest));
+        assert_eq!(std::fs::read(temp).unwrap(), b"abc");
+    }
+
+    #[tokio::test]
+    async fn mismatched_or_oversized_uploads_leave_nothing_behind() {
+        let dir = TestDir::new("upload_digest_mismatch");
+        let wrong = FileDigest {
+            algorithm: Algorithm::Sha1,
+            digest: vec![0; 20],
+        };
+        let result = write(&dir, None, &[digest(Algorithm::Md5, MD5_ABC), wrong]).await;
+        assert!(matches!(result, Err(UploadError::DigestMismatch(Algorithm::Sha1))));
+
+        let result = write(&dir, Some(2), &[]).await;
+        assert!(matches!(result, Err(UploadError::TooLarge(2))));
+        assert_eq!(std::fs::read_dir(&*dir).unwrap().count(), 0);
+    }
+
+    #[tokio::test]
+    async fn longest_names_fit_in_temp_files() {
+        let dir = TestDir::new("upload_long_name");
+        for name in ["a".repeat(MAX_NAME_BYTES), "文".repeat(MAX_NAME_BYTES / 3)] {
+            let chunks = [Ok::<_, io::Error>(Bytes::from("abc"))];
+            let stream = futures_util::stream::iter(chunks);
+            let (temp, _, _) = write_temp(stream, &dir, &name, None, &[]).await.unwrap();
+            let temp_name = temp.file_name().unwrap().to_str().unwrap();
+            assert!(temp_name.len() <= MAX_NAME_BYTES, "{}", temp_name);
+            std::fs::rename(&temp, dir.join(&name)).unwrap();
+        }
+    }
+}
@@ -0,0 +1,416 @@
+use chrono::Utc;
+use hyper::{header, Body, Request, Response, StatusCode};
+use mime_guess::from_path;
+use percent_encoding::utf8_percent_encode;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    io::{self, Read, Write},
+    net::SocketAddr,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::UNIX_EPOCH,
+};
+
+use crate::i18n::Lang;
+use crate::limit;
+use crate::util::{query_param, random_token, text_response, SEGMENT};
+use crate::AppState;
+
+/// 数据目录下的历史版本目录
+const VERSIONS_DIR: &str = "versions";
+/// 索引文件名
+const INDEX_FILE: &str = "index.json";
+/// 版本内容按 SHA-256 存放在 `objects/<前两位>/<完整哈希>`，相同内容只保存一份
+const OBJECTS_DIR: &str = "objects";
+
+/// 文件的一个历史版本
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Version {
+    pub id: String,
+    pub sha256: String,
+    pub size: u64,
+    /// 被覆盖前的修改时间（Unix 秒）
+    pub modified: i64,
+    /// 保存时间（Unix 秒）
+    pub saved: i64,
+}
+
+/// 相对路径到历史版本（按保存时间从旧到新）的索引
+type Index = BTreeMap<String, Vec<Version>>;
+
+/// 服务管理的历史版本存储，位于数据目录下（不在根目录中提供）
+///
+/// 通过上传覆盖文件时，旧内容按 SHA-256 去重保存，每个文件保留最近 `keep` 个版本，
+/// 超出的版本连同不再被引用的内容一起删除。
+pub struct VersionStore {
+    dir: PathBuf,
+    keep: usize,
+    lock: Mutex<()>,
+}
+
+impl VersionStore {
+    /// 打开（必要时创建）数据目录下的版本存储，清理未被引用的内容和残留的临时文件
+    pub fn open(data_dir: &Path, keep: usize) -> io::Result<Self> {
+        let store = VersionStore {
+            dir: data_dir.join(VERSIONS_DIR),
+            keep,
+            lock: Mutex::new(()),
+        };
+        fs::create_dir_all(store.dir.join(OBJECTS_DIR))?;
+        let referenced: BTreeSet<String> = store
+            .load()?
+            .values()
+            .flatten()
+            .map(|version| version.sha256.clone())
+            .collect();
+        for entry in walkdir::WalkDir::new(store.dir.join(OBJECTS_DIR)).min_depth(1) {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy();
+            if entry.file_type().is_file() && !referenced.contains(name.as_ref()) {
+                fs::remove_file(entry.path())?;
+            }
+        }
+        Ok(store)
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn keep(&self) -> usize {
+        self.keep
+    }
+
+    fn load(&self) -> io::Result<Index> {
+        match fs::read(self.dir.join(INDEX_FILE)) {
+            Ok(bytes) => serde_json::from_slice(&bytes)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Index::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// 先写临时文件再重命名，避免写入中途崩溃损坏索引
+    fn save(&self, index: &Index) -> io::Result<()> {
+        let path = self.dir.join(INDEX_FILE);
+        let tmp = path.with_extension("json.tmp");
+        let json = serde_json::to_vec_pretty(index)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        fs::write(&tmp, json)?;
+        fs::rename(&tmp, &path)
+    }
+
+    fn object_path(&self, sha256: &str) -> PathBuf {
+        self.dir.join(OBJECTS_DIR).join(&sha256[..2]).join(sha256)
+    }
+
+    /// 保存根目录下文件 `relative` 的当前内容，与最近一个版本相同时不重复保存
+    pub fn snapshot(&self, root: &Path, relative: &str) -> io::Result<Version> {
+        let source = root.join(relative);
+        let metadata = fs::metadata(&source)?;
+        // 复制和计算哈希较慢，在锁外进行；放入内容存储和记入索引必须在同一次加锁中完成，
+        // 否则刚放入（或已存在）的内容可能在记入索引前被另一次保存当作不再引用的内容删除
+        let (tmp, sha256, size) = self.copy_to_temp(&source)?;
+        let modified = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_secs() as i64)
+            .unwrap_or_default();
+
+        let _guard = self.lock.lock().unwrap();
+        let mut index = match self.store_object(&tmp, &sha256).and_then(|_| self.load()) {
+            Ok(index) => index,
+            Err(e) => {
+                let _ = fs::remove_file(&tmp);
+                return Err(e);
+            }
+        };
+        let versions = index.entry(relative.to_string()).or_default();
+        if let Some(last) = versions.last().filter(|last| last.sha256 == sha256) {
+            return Ok(last.clone());
+        }
+        let version = Version {
+            id: random_token(6),
+            sha256,
+            size,
+            modified,
+            saved: Utc::now().timestamp(),
+        };
+        versions.push(version.clone());
+        let excess = versions.len().saturating_sub(self.keep);
+        let removed: Vec<Version> = versions.drain(..excess).collect();
+        self.save(&index)?;
+        self.collect(&index, &removed)?;
+        Ok(version)
+    }
+
+    /// 把文件复制到内容存储下的临时文件，同时计算 SHA-256，返回临时文件、哈希和大小
+    fn copy_to_temp(&self, source: &Path) -> io::Result<(PathBuf, String, u64)> {
+        let tmp = self
+            .dir
+            .join(OBJECTS_DIR)
+            .join(format!(".tmp-{}", random_token(4)));
+        match copy_hashed(source, &tmp) {
+            Ok((sha256, size)) => Ok((tmp, sha256, size)),
+            Err(e) => {
+                let _ = fs::remove_file(&tmp);
+                Err(e)
+            }
+        }
+    }
+
+    /// 把临时文件放入内容存储，相同内容已存在时直接删除临时文件；调用方需持有锁
+    fn store_object(&self, tmp: &Path, sha256: &str) -> io::Result<()> {
+        let object = self.object_path(sha256);
+        if object.exists() {
+            fs::remove_file(tmp)
+        } else {
+            fs::create_dir_all(object.parent().unwrap())?;
+            fs::rename(tmp, &object)
+        }
+    }
+
+    /// 删除已不被任何版本引用的内容
+    fn collect(&self, index: &Index, removed: &[Version]) -> io::Result<()> {
+        for version in removed {
+            let referenced = index
+                .values()
+                .flatten()
+                .any(|other| other.sha256 == version.sha256);
+            if !referenced {
+                match fs::remove_file(self.object_path(&version.sha256)) {
+                    Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 文件的历史版本，最近保存的在前
+    pub fn list(&self, relative: &str) -> io::Result<Vec<Version>> {
+        let _guard = self.lock.lock().unwrap();
+        let mut versions = self.load()?.remove(relative).unwrap_or_default();
+        versions.reverse();
+        Ok(versions)
+    }
+
+    /// 查找文件的指定版本，返回版本和内容所在路径
+    pub fn get(&self, relative: &str, id: &str) -> io::Result<Option<(Version, PathBuf)>> {
+        let version = self.list(relative)?.into_iter().find(|v| v.id == id);
+        Ok(version.map(|v| {
+            let path = self.object_path(&v.sha256);
+            (v, path)
+        }))
+    }
+
+    /// 文件或目录被重命名、移动后，历史版本随之转到新路径；目标路径原有的历史版本被丢弃
+    pub fn rename(&self, from: &str, to: &str) -> io::Result<()> {
+        let _guard = self.lock.lock().unwrap();
+        let mut index = self.load()?;
+        let moved =
+            |path: &str, base: &str| path == base || path.starts_with(&format!("{}/", base));
+        let mut removed = Vec::new();
+        for key in index
+            .keys()
+            .filter(|key| moved(key, to))
+            .cloned()
+            .collect::<Vec<_>>()
+        {
+            removed.extend(index.remove(&key).unwrap_or_default());
+        }
+        let keys: Vec<String> = index
+            .keys()
+            .filter(|key| moved(key, from))
+            .cloned()
+            .collect();
+        if keys.is_empty() && removed.is_empty() {
+            return Ok(());
+        }
+        for key in keys {
+            let versions = index.remove(&key).unwrap_or_default();
+            index.insert(format!("{}{}", to, &key[from.len()..]), versions);
+        }
+        self.save(&index)?;
+        self.collect(&index, &removed)
+    }
+}
+
+/// 复制文件并同时计算 SHA-256，返回哈希和大小
+fn copy_hashed(from: &Path, to: &Path) -> io::Result<(String, u64)> {
+    let mut input = fs::File::open(from)?;
+    let mut output = fs::File::create(to)?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 64 * 1024];
+    let mut size = 0u64;
+    loop {
+        let n = input.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        output.write_all(&buf[..n])?;
+        size += n as u64;
+    }
+    output.sync_all()?;
+    Ok((hex::encode(hasher.finalize()), size))
+}
+
+/// 处理文件路径上的 `?versions`（历史版本列表，JSON）和 `?version=<id>`（下载指定版本），
+/// 没有这两个参数时返回 None
+///
+/// 访问规则与文件本身相同，由调用方检查；文件已被删除时仍可查看其历史版本。
+pub async fn handle_query(
+    req: &Request<Body>,
+    remote: SocketAddr,
+    state: &AppState,
+    relative: &str,
+    lang: Lang,
+) -> Option<Response<Body>> {
+    let query = req.uri().query();
+    let list = query_param(query, "versions").is_some();
+    let id = query_param(query, "version");
+    if !list && id.is_none() {
+        return None;
+    }
+    let Some(store) = &state.versions else {
+        return Some(text_response(
+            StatusCode::NOT_FOUND,
+            lang.t("versions.disabled"),
+        ));
+    };
+    let path = format!("/{}", relative);
+    let Some(id) = id else {
+        let response = match store.list(relative) {
+            Ok(versions) if versions.is_empty() && !state.root_dir.join(relative).is_file() => {
+                text_response(StatusCode::NOT_FOUND, lang.t("http.not_found"))
+            }
+            Ok(versions) => Response::builder()
+                .header("Content-Type", "application/json")
+                .header(header::CACHE_CONTROL, "no-store")
+                .body(Body::from(
+                    serde_json::json!({ "path": path, "versions": versions }).to_string(),
+                ))
+                .unwrap(),
+            Err(e) => text_response(StatusCode::INTERNAL_SERVER_ERROR, &format!("500 - {}", e)),
+        };
+        return Some(response);
+    };
+    let Ok(Some((version, object))) = store.get(relative, &id) else {
+        return Some(text_response(
+            StatusCode::NOT_FOUND,
+            lang.t("versions.not_found"),
+        ));
+    };
+    let Some(slot) = state.limits.download_slot() else {
+        return Some(limit::too_many_requests(
+            limit::BUSY_RETRY_AFTER,
+            lang.t("http.too_many_downloads"),
+        ));
+    };
+    let response = match tokio::fs::File::open(&object).await {
+        Ok(file) => {
+            let name = relative.rsplit('/').next().unwrap_or(relative);
+            let mime = from_path(name).first_or_octet_stream();
+            let body = state.limits.file_body(file, req, slot);
+            Response::builder()
+                .header("Content-Type", mime.as_ref())
+                .header(
+                    header::CONTENT_DISPOSITION,
+                    format!(
+                        "attachment; filename*=UTF-8''{}",
+                        utf8_percent_encode(name, SEGMENT)
+                    ),
+                )
+                .header(header::CACHE_CONTROL, "no-store")
+                .body(state.monitor.track_download(
+                    body,
+                    &format!("{}?version={}", path, version.id),
+                    remote.ip(),
+                    Some(version.size),
+                ))
+                .unwrap()
+        }
+        Err(_) => text_response(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            lang.t("http.open_file_failed"),
+        ),
+    };
+    Some(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::TestDir;
+    use std::sync::Arc;
+
+    /// 索引中的每个版本都有对应的内容
+    fn assert_objects_exist(store: &VersionStore) {
+        for version in store.load().unwrap().values().flatten() {
+            assert!(store.object_path(&version.sha256).is_file(), "{:?}", version);
+        }
+    }
+
+    #[test]
+    fn versions_are_deduplicated_and_collected() {
+        let dir = TestDir::new("versions_collect");
+        let root = dir.join("root");
+        fs::create_dir_all(&root).unwrap();
+        let store = VersionStore::open(&dir.join("data"), 1).unwrap();
+
+        fs::write(root.join("a"), "one").unwrap();
+        let first = store.snapshot(&root, "a").unwrap();
+        // 内容没变时不重复保存
+        assert_eq!(store.snapshot(&root, "a").unwrap().id, first.id);
+
+        fs::write(root.join("a"), "two").unwrap();
+        let second = store.snapshot(&root, "a").unwrap();
+        assert_eq!(store.list("a").unwrap().len(), 1);
+        assert!(!store.object_path(&first.sha256).exists());
+
+        // 不同文件的相同内容只存一份，仍被引用时不删除
+        fs::write(root.join("b"), "two").unwrap();
+        store.snapshot(&root, "b").unwrap();
+        fs::write(root.join("a"), "three").unwrap();
+        store.snapshot(&root, "a").unwrap();
+        assert!(store.object_path(&second.sha256).is_file());
+        assert_objects_exist(&store);
+    }
+
+    #[test]
+    fn concurrent_snapshots_keep_their_objects() {
+        let dir = TestDir::new("versions_race");
+        let root = dir.join("root");
+        fs::create_dir_all(&root).unwrap();
+        let store = Arc::new(VersionStore::open(&dir.join("data"), 1).unwrap());
+        for name in ["a", "b", "c", "d"] {
+            fs::write(root.join(name), name).unwrap();
+        }
+
+        // 每个线程轮流保存两份共享的内容，旧版本不断被淘汰，内容在保存的同时可能正被回收
+        let threads: Vec<_> = ["a", "b", "c", "d"]
+            .into_iter()
+            .map(|name| {
+                let (store, root) = (store.clone(), root.clone());
+                std::thread::spawn(move || {
+                    for i in 0..200 {
+                        let content = if i % 2 == 0 { "x" } else { "y" };
+                        fs::write(root.join(name), content).unwrap();
+                        // 在本线程下次保存前，刚保存的版本只被这个文件引用，内容必须还在
+                        let version = store.snapshot(&root, name).unwrap();
+                        assert!(store.object_path(&version.sha256).is_file());
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_objects_exist(&store);
+    }
+}
@@ -0,0 +1,76 @@
+//! 集成测试共用的辅助代码
+
+use std::io::{BufRead, BufReader};
+use std::net::TcpStream;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::time::Duration;
+
+/// 测试使用的临时目录，按测试名和进程号区分
+pub fn test_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("file_server_test_{}_{}", name, std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// 测试期间运行的服务进程，结束时自动终止
+pub struct Server {
+    child: Child,
+    pub port: u16,
+    root: PathBuf,
+}
+
+impl Server {
+    pub fn start(name: &str, args: &[&str]) -> Server {
+        let root = test_dir(name);
+        std::fs::write(root.join("hello.txt"), "hello").unwrap();
+
+        let mut child = Command::new(env!("CARGO_BIN_EXE_file_server"))
+            .args(["--lang", "en", "--bind", "127.0.0.1:0", "--root"])
+            .arg(&root)
+            .arg("--data-dir")
+            .arg(root.join(".data"))
+            .args(args)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .spawn()
+            .expect("failed to start file_server");
+
+        // 从启动横幅中读出系统分配的端口，其余输出在后台读掉，避免管道关闭导致服务退出
+        let mut lines = BufReader::new(child.stdout.take().unwrap()).lines();
+        let mut port = None;
+        for line in lines.by_ref() {
+            if let Some(p) = line.unwrap().split("Port: ").nth(1) {
+                port = Some(p.trim().parse().unwrap());
+                break;
+            }
+        }
+        std::thread::spawn(move || lines.for_each(drop));
+        Server {
+            child,
+            port: port.expect("no port in banner"),
+            root,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn connect(&self) -> TcpStream {
+        let stream = TcpStream::connect(("127.0.0.1", self.port)).unwrap();
+        stream.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+        stream
+    }
+
+    /// 服务的根目录
+    #[allow(dead_code)]
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl Drop for Server {
+    fn drop(&mut self) {
+        let _ = self.child.kill();
+        let _ = self.child.wait();
+        let _ = std::fs::remove_dir_all(&self.root);
+    }
+}
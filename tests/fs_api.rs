@@ -0,0 +1,107 @@
+//! 文件管理接口的集成测试：本机浏览器默认通过鉴权，跨站请求必须被拒绝
+
+mod common;
+
+use common::Server;
+use std::io::{Read, Write};
+
+/// 发送一个 POST 请求，返回完整响应
+fn post(server: &Server, path: &str, headers: &[(&str, &str)], body: &str) -> String {
+    let mut stream = server.connect();
+    let mut request = format!(
+        "POST {} HTTP/1.1\r\nHost: 127.0.0.1:{}\r\nConnection: close\r\nContent-Length: {}\r\n",
+        path,
+        server.port,
+        body.len()
+    );
+    for (name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+    request.push_str(body);
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+#[test]
+fn cross_site_delete_is_refused() {
+    let server = Server::start("fs_api_cross_site", &["--allow-write"]);
+    let body = r#"{"paths": ["/hello.txt"]}"#;
+
+    // 跨站页面用 text/plain 发出的“简单请求”不经预检
+    let response = post(
+        &server,
+        "/_api/fs/delete",
+        &[("Content-Type", "text/plain"), ("Origin", "https://evil.example")],
+        body,
+    );
+    assert!(response.starts_with("HTTP/1.1 403"), "{}", response);
+
+    // 没有 Origin 时也只接受 JSON 请求体
+    let response = post(&server, "/_api/fs/delete", &[("Content-Type", "text/plain")], body);
+    assert!(response.starts_with("HTTP/1.1 415"), "{}", response);
+
+    let response = post(
+        &server,
+        "/_api/fs/delete",
+        &[("Content-Type", "application/json"), ("Sec-Fetch-Site", "cross-site")],
+        body,
+    );
+    assert!(response.starts_with("HTTP/1.1 403"), "{}", response);
+    assert!(server.root().join("hello.txt").exists());
+
+    // 同源页面的请求照常执行
+    let origin = format!("http://127.0.0.1:{}", server.port);
+    let response = post(
+        &server,
+        "/_api/fs/delete",
+        &[("Content-Type", "application/json"), ("Origin", &origin)],
+        body,
+    );
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert!(!server.root().join("hello.txt").exists());
+}
+
+#[test]
+fn cross_site_upload_is_refused() {
+    let server = Server::start("fs_api_cross_site_upload", &["--allow-write"]);
+    let boundary = "X-BOUNDARY";
+    let body = format!(
+        "--{b}\r\nContent-Disposition: form-data; name=\"file\"; filename=\"evil.txt\"\r\n\r\nevil\r\n--{b}--\r\n",
+        b = boundary
+    );
+    let content_type = format!("multipart/form-data; boundary={}", boundary);
+    let response = post(
+        &server,
+        "/",
+        &[("Content-Type", &content_type), ("Origin", "https://evil.example")],
+        &body,
+    );
+    assert!(response.starts_with("HTTP/1.1 403"), "{}", response);
+    assert!(!server.root().join("evil.txt").exists());
+}
+
+#[test]
+fn same_named_files_are_moved_side_by_side() {
+    let server = Server::start("fs_api_same_name", &["--allow-write"]);
+    let root = server.root();
+    for dir in ["a", "b", "dst"] {
+        std::fs::create_dir(root.join(dir)).unwrap();
+    }
+    std::fs::write(root.join("a/x.txt"), "a").unwrap();
+    std::fs::write(root.join("b/x.txt"), "b").unwrap();
+    let json = [("Content-Type", "application/json")];
+
+    let body = r#"{"paths": ["/a/x.txt", "/b/x.txt"], "to": "/dst"}"#;
+    let response = post(&server, "/_api/fs/move", &json, body);
+    assert!(response.starts_with("HTTP/1.1 409"), "{}", response);
+    assert!(root.join("a/x.txt").exists() && root.join("b/x.txt").exists());
+
+    let body = r#"{"paths": ["/a/x.txt", "/b/x.txt"], "to": "/dst", "conflict": "rename"}"#;
+    let response = post(&server, "/_api/fs/move", &json, body);
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+    assert_eq!(std::fs::read_to_string(root.join("dst/x.txt")).unwrap(), "a");
+    assert_eq!(std::fs::read_to_string(root.join("dst/x (1).txt")).unwrap(), "b");
+}
@@ -0,0 +1,181 @@
+//! 协议支持的集成测试：分别通过 TLS 上的 HTTP/1.1 与 HTTP/2、明文 h2c 以及 HTTP/3 下载文件
+
+mod common;
+
+use common::{test_dir, Server};
+use hyper::body::Buf;
+use hyper::{header, Body, Request, Response, Version};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncReadExt;
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::{
+    crypto::ring,
+    pki_types::{CertificateDer, ServerName},
+    ClientConfig, RootCertStore,
+};
+use tokio_rustls::TlsConnector;
+
+/// 生成 localhost 的自签名证书，写入服务根目录下的隐藏目录，返回证书供客户端信任
+fn start_tls_server(name: &str, extra: &[&str]) -> (Server, CertificateDer<'static>) {
+    let generated = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let dir = test_dir(name).join(".tls");
+    std::fs::create_dir_all(&dir).unwrap();
+    let (cert, key) = (dir.join("cert.pem"), dir.join("key.pem"));
+    std::fs::write(&cert, generated.cert.pem()).unwrap();
+    std::fs::write(&key, generated.key_pair.serialize_pem()).unwrap();
+
+    let mut args = vec![
+        "--tls-cert",
+        cert.to_str().unwrap(),
+        "--tls-key",
+        key.to_str().unwrap(),
+        "--http3",
+    ];
+    args.extend_from_slice(extra);
+    let server = Server::start(name, &args);
+    (server, generated.cert.der().clone())
+}
+
+fn client_config(cert: &CertificateDer<'static>, alpn: &[&[u8]]) -> ClientConfig {
+    let mut roots = RootCertStore::empty();
+    roots.add(cert.clone()).unwrap();
+    let mut config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_root_certificates(roots)
+        .with_no_client_auth();
+    config.alpn_protocols = alpn.iter().map(|p| p.to_vec()).collect();
+    config
+}
+
+/// 通过 TLS 连接发送一次请求，返回协商出的 ALPN 协议和响应
+async fn tls_get(
+    port: u16,
+    cert: &CertificateDer<'static>,
+    alpn: &[&[u8]],
+) -> (Vec<u8>, Response<Body>) {
+    let connector = TlsConnector::from(Arc::new(client_config(cert, alpn)));
+    let tcp = TcpStream::connect(("127.0.0.1", port)).await.unwrap();
+    let tls = connector
+        .connect(ServerName::try_from("localhost").unwrap(), tcp)
+        .await
+        .unwrap();
+    let negotiated = tls.get_ref().1.alpn_protocol().unwrap_or_default().to_vec();
+    let http2 = negotiated == b"h2";
+    let response = get(tls, http2, &format!("https://localhost:{}/hello.txt", port)).await;
+    (negotiated, response)
+}
+
+/// 在已建立的连接上发送 GET 请求
+async fn get<T>(io: T, http2: bool, uri: &str) -> Response<Body>
+where
+    T: tokio::io::AsyncRead + tokio::io::AsyncWrite + Unpin + Send + 'static,
+{
+    let (mut sender, conn) = hyper::client::conn::Builder::new()
+        .http2_only(http2)
+        .handshake(io)
+        .await
+        .unwrap();
+    tokio::spawn(conn);
+    let request = Request::get(uri).body(Body::empty()).unwrap();
+    sender.send_request(request).await.unwrap()
+}
+
+async fn body_text(response: Response<Body>) -> String {
+    let bytes = hyper::body::to_bytes(response.into_body()).await.unwrap();
+    String::from_utf8(bytes.to_vec()).unwrap()
+}
+
+#[tokio::test]
+async fn http2_is_negotiated_over_tls() {
+    let (server, cert) = start_tls_server("h2_tls", &[]);
+    let (alpn, response) = tls_get(server.port, &cert, &[b"h2", b"http/1.1"]).await;
+    assert_eq!(alpn, b"h2");
+    assert_eq!(response.version(), Version::HTTP_2);
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        response.headers().get(header::ALT_SVC).unwrap(),
+        &format!("h3=\":{}\"; ma=86400", server.port)
+    );
+    assert_eq!(body_text(response).await, "hello");
+}
+
+#[tokio::test]
+async fn http1_still_works_over_tls() {
+    let (server, cert) = start_tls_server("h1_tls", &[]);
+    let (alpn, response) = tls_get(server.port, &cert, &[b"http/1.1"]).await;
+    assert_eq!(alpn, b"http/1.1");
+    assert_eq!(response.version(), Version::HTTP_11);
+    assert_eq!(response.status(), 200);
+    assert_eq!(body_text(response).await, "hello");
+}
+
+#[tokio::test]
+async fn h2c_prior_knowledge_on_plain_connections() {
+    let server = Server::start("h2c", &[]);
+    let tcp = TcpStream::connect(("127.0.0.1", server.port)).await.unwrap();
+    let response = get(tcp, true, &format!("http://127.0.0.1:{}/hello.txt", server.port)).await;
+    assert_eq!(response.version(), Version::HTTP_2);
+    assert_eq!(response.status(), 200);
+    // 未启用 HTTP/3 时不发送 Alt-Svc
+    assert!(response.headers().get(header::ALT_SVC).is_none());
+    assert_eq!(body_text(response).await, "hello");
+}
+
+/// 建立到服务的 QUIC 连接
+async fn quic_connect(
+    port: u16,
+    cert: &CertificateDer<'static>,
+) -> Result<quinn::Connection, quinn::ConnectionError> {
+    let crypto = quinn::crypto::rustls::QuicClientConfig::try_from(client_config(cert, &[b"h3"])).unwrap();
+    let mut endpoint = quinn::Endpoint::client("127.0.0.1:0".parse().unwrap()).unwrap();
+    endpoint.set_default_client_config(quinn::ClientConfig::new(Arc::new(crypto)));
+    endpoint
+        .connect(([127, 0, 0, 1], port).into(), "localhost")
+        .unwrap()
+        .await
+}
+
+#[tokio::test]
+async fn http3_over_quic() {
+    let (server, cert) = start_tls_server("h3", &[]);
+    let conn = quic_connect(server.port, &cert).await.unwrap();
+
+    let (mut driver, mut sender) = h3::client::new(h3_quinn::Connection::new(conn)).await.unwrap();
+    tokio::spawn(async move { std::future::poll_fn(|cx| driver.poll_close(cx)).await });
+
+    let request = http::Request::get(format!("https://localhost:{}/hello.txt", server.port))
+        .body(())
+        .unwrap();
+    let mut stream = sender.send_request(request).await.unwrap();
+    stream.finish().await.unwrap();
+    let response = stream.recv_response().await.unwrap();
+    assert_eq!(response.version(), http::Version::HTTP_3);
+    assert_eq!(response.status(), 200);
+
+    let mut body = Vec::new();
+    while let Some(mut chunk) = stream.recv_data().await.unwrap() {
+        body.extend_from_slice(&chunk.copy_to_bytes(chunk.remaining()));
+    }
+    assert_eq!(body, b"hello");
+}
+
+#[tokio::test]
+async fn quic_connections_share_the_connection_caps() {
+    let (server, cert) = start_tls_server("h3_caps", &["--max-connections-per-ip", "1"]);
+    // TCP 连接占用了本机唯一的名额
+    let tcp = TcpStream::connect(("127.0.0.1", server.port)).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert!(quic_connect(server.port, &cert).await.is_err());
+
+    drop(tcp);
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    let conn = quic_connect(server.port, &cert).await.unwrap();
+    // QUIC 连接同样占用名额，此时 TCP 连接被直接关闭
+    let mut tcp = TcpStream::connect(("127.0.0.1", server.port)).await.unwrap();
+    let mut buf = [0u8; 1];
+    let read = tokio::time::timeout(Duration::from_secs(5), tcp.read(&mut buf)).await;
+    assert!(matches!(read, Ok(Ok(0)) | Ok(Err(_))));
+    drop(conn);
+}
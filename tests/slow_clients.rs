@@ -0,0 +1,125 @@
+//! 连接层面限制的集成测试：启动服务进程，模拟慢速和占用连接的客户端
+
+mod common;
+
+use common::Server;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::{Duration, Instant};
+
+const REQUEST: &[u8] = b"GET /hello.txt HTTP/1.1\r\nHost: localhost\r\n\r\n";
+
+/// 读取直到收到 `hello.txt` 的内容
+fn read_response(stream: &mut TcpStream) -> String {
+    let mut response = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !String::from_utf8_lossy(&response).contains("hello") {
+        let n = stream.read(&mut buf).expect("read failed");
+        assert!(n > 0, "connection closed before the response completed");
+        response.extend_from_slice(&buf[..n]);
+    }
+    String::from_utf8_lossy(&response).into_owned()
+}
+
+/// 服务端是否已关闭连接（读到 EOF 或连接被重置）
+fn is_closed(stream: &mut TcpStream) -> bool {
+    let mut buf = [0u8; 1024];
+    loop {
+        match stream.read(&mut buf) {
+            Ok(0) => return true,
+            Ok(_) => continue,
+            Err(e) if e.kind() == std::io::ErrorKind::WouldBlock || e.kind() == std::io::ErrorKind::TimedOut => {
+                return false
+            }
+            Err(_) => return true,
+        }
+    }
+}
+
+#[test]
+fn slow_headers_are_cut_off() {
+    let server = Server::start("slow_headers", &["--header-timeout", "1s"]);
+    let mut stream = server.connect();
+    // 只发送一部分请求头，然后停住
+    stream.write_all(b"GET /hello.txt HTTP/1.1\r\nHost: local").unwrap();
+
+    let started = Instant::now();
+    assert!(is_closed(&mut stream));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn idle_keep_alive_connections_are_closed() {
+    let server = Server::start("idle", &["--header-timeout", "0", "--idle-timeout", "1s"]);
+    let mut stream = server.connect();
+    stream.write_all(REQUEST).unwrap();
+    assert!(read_response(&mut stream).starts_with("HTTP/1.1 200"));
+
+    // 保持连接但不再发送请求
+    let started = Instant::now();
+    assert!(is_closed(&mut stream));
+    assert!(started.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn oversized_headers_are_rejected() {
+    let server = Server::start("large_headers", &["--max-header-size", "8K"]);
+    let mut stream = server.connect();
+    let request = format!(
+        "GET /hello.txt HTTP/1.1\r\nHost: localhost\r\nX-Padding: {}\r\n\r\n",
+        "a".repeat(32 * 1024)
+    );
+    // 服务端可能在读完之前就关闭连接，写入失败也是预期结果
+    let _ = stream.write_all(request.as_bytes());
+
+    let mut response = Vec::new();
+    let _ = stream.read_to_end(&mut response);
+    let response = String::from_utf8_lossy(&response);
+    assert!(
+        response.is_empty() || response.starts_with("HTTP/1.1 431"),
+        "unexpected response: {}",
+        response
+    );
+}
+
+#[test]
+fn max_connections_applies_backpressure() {
+    let server = Server::start(
+        "max_connections",
+        &["--max-connections", "1", "--header-timeout", "0", "--idle-timeout", "0"],
+    );
+    // 第一个连接占用唯一的名额
+    let holder = server.connect();
+    std::thread::sleep(Duration::from_millis(200));
+
+    // 第二个连接在名额释放前得不到响应
+    let mut waiting = server.connect();
+    waiting.write_all(REQUEST).unwrap();
+    waiting.set_read_timeout(Some(Duration::from_secs(1))).unwrap();
+    let mut buf = [0u8; 1];
+    assert!(waiting.read(&mut buf).is_err(), "second connection was served while the limit was reached");
+
+    drop(holder);
+    waiting.set_read_timeout(Some(Duration::from_secs(10))).unwrap();
+    assert!(read_response(&mut waiting).starts_with("HTTP/1.1 200"));
+}
+
+#[test]
+fn per_ip_connection_cap_closes_extra_connections() {
+    let server = Server::start(
+        "per_ip",
+        &["--max-connections-per-ip", "1", "--header-timeout", "0", "--idle-timeout", "0"],
+    );
+    let holder = server.connect();
+    std::thread::sleep(Duration::from_millis(200));
+
+    let mut extra = server.connect();
+    let _ = extra.write_all(REQUEST);
+    assert!(is_closed(&mut extra));
+
+    drop(holder);
+    std::thread::sleep(Duration::from_millis(200));
+    let mut next = server.connect();
+    next.write_all(REQUEST).unwrap();
+    assert!(read_response(&mut next).starts_with("HTTP/1.1 200"));
+}
@@ -0,0 +1,79 @@
+//! 断点续传接口的集成测试：上传大小上限和过期上传
+
+mod common;
+
+use common::Server;
+use std::io::{Read, Write};
+use std::time::Duration;
+
+/// 发送一个不带请求体的 tus 请求，返回完整响应
+fn request(server: &Server, method: &str, path: &str, headers: &[(&str, &str)]) -> String {
+    let mut stream = server.connect();
+    let mut request = format!(
+        "{} {} HTTP/1.1\r\nHost: 127.0.0.1:{}\r\nConnection: close\r\nTus-Resumable: 1.0.0\r\n",
+        method, path, server.port
+    );
+    for (name, value) in headers {
+        request.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    response
+}
+
+fn location(response: &str) -> &str {
+    response
+        .lines()
+        .find_map(|line| line.strip_prefix("location: "))
+        .expect("no location header")
+}
+
+#[test]
+fn upload_length_is_limited() {
+    let server = Server::start("tus_max_size", &["--allow-write", "--max-upload-size", "1K"]);
+
+    let response = request(&server, "OPTIONS", "/_tus/", &[]);
+    assert!(response.contains("tus-max-size: 1024\r\n"), "{}", response);
+
+    // filename: a.bin
+    let metadata = ("Upload-Metadata", "filename YS5iaW4=");
+    let response = request(&server, "POST", "/_tus/", &[("Upload-Length", "1025"), metadata]);
+    assert!(response.starts_with("HTTP/1.1 413"), "{}", response);
+
+    let response = request(&server, "POST", "/_tus/", &[("Upload-Length", "1024"), metadata]);
+    assert!(response.starts_with("HTTP/1.1 201"), "{}", response);
+}
+
+#[test]
+fn expired_uploads_are_gone() {
+    let server = Server::start("tus_expired", &["--allow-write", "--upload-expiry", "1s"]);
+    let response = request(
+        &server,
+        "POST",
+        "/_tus/",
+        &[("Upload-Length", "5"), ("Upload-Metadata", "filename YS5iaW4=")],
+    );
+    assert!(response.starts_with("HTTP/1.1 201"), "{}", response);
+    let path = location(&response).to_string();
+
+    let response = request(&server, "HEAD", &path, &[]);
+    assert!(response.starts_with("HTTP/1.1 200"), "{}", response);
+
+    // 定期清除还没运行，过期的上传也不能再继续
+    std::thread::sleep(Duration::from_millis(2100));
+    let response = request(&server, "HEAD", &path, &[]);
+    assert!(response.starts_with("HTTP/1.1 410"), "{}", response);
+    let response = request(
+        &server,
+        "PATCH",
+        &path,
+        &[
+            ("Content-Type", "application/offset+octet-stream"),
+            ("Upload-Offset", "0"),
+            ("Content-Length", "0"),
+        ],
+    );
+    assert!(response.starts_with("HTTP/1.1 410"), "{}", response);
+}